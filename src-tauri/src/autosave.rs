@@ -0,0 +1,181 @@
+use crate::database::{DatabaseManager, SessionRecord};
+use crate::error::AppError;
+use crate::gamepad::session::PlaySession;
+use crate::gamepad::GamepadManager;
+use chrono::{Local, Timelike};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+const TICK: Duration = Duration::from_secs(1);
+// 1tickの間に壁時計がこれ以上進んでいたらスリープからの復帰とみなす。
+// OSのスリープ通知は受け取っていないため、スリープに入る前には保存しない
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(30);
+
+pub fn save_statistics(
+    db: &Arc<Mutex<DatabaseManager>>,
+    gamepad: &Arc<Mutex<GamepadManager>>,
+) -> Result<(), AppError> {
+    save_pending_counts(db, gamepad)?;
+    save_finished_sessions(db, gamepad)
+}
+
+// ノーツを叩いた日付ごとに、未保存分をデータベースへ加算する。
+// 日別と時間帯別は1つのトランザクションで加算し、失敗した場合はすべて未保存に戻す
+pub fn save_pending_counts(
+    db: &Arc<Mutex<DatabaseManager>>,
+    gamepad: &Arc<Mutex<GamepadManager>>,
+) -> Result<(), AppError> {
+    let gamepad = gamepad.lock()?;
+    let db = db.lock()?;
+    let pending = gamepad.take_pending_counts()?;
+
+    let daily: Vec<(String, u32)> = pending
+        .daily
+        .iter()
+        .map(|(date, &count)| (date.format("%Y-%m-%d").to_string(), count))
+        .collect();
+    let hourly: Vec<(String, u32, u32)> = pending
+        .hourly
+        .iter()
+        .map(|(hour, &count)| (hour.format("%Y-%m-%d").to_string(), hour.hour(), count))
+        .collect();
+    if let Err(e) = db.add_counts(&daily, &hourly) {
+        gamepad.restore_pending_counts(pending)?;
+        return Err(e.into());
+    }
+
+    sync_saved_today(&db, &gamepad)
+}
+
+// 今日の保存済みノーツ数を目標判定に反映する
+pub fn sync_saved_today(db: &DatabaseManager, gamepad: &GamepadManager) -> Result<(), AppError> {
+    let today = gamepad.today()?;
+    let saved_count = db.get(&today.format("%Y-%m-%d").to_string())?.unwrap_or(0);
+    gamepad
+        .set_saved_count(today, saved_count.max(0) as u32)
+        .map_err(AppError::from)
+}
+
+pub fn save_finished_sessions(
+    db: &Arc<Mutex<DatabaseManager>>,
+    gamepad: &Arc<Mutex<GamepadManager>>,
+) -> Result<(), AppError> {
+    let gamepad = gamepad.lock()?;
+    let mut sessions = gamepad.take_finished_sessions()?;
+
+    let db = db.lock()?;
+    while !sessions.is_empty() {
+        if let Err(e) = db.insert_session(&session_record(&sessions[0])) {
+            gamepad.requeue_sessions(sessions)?;
+            return Err(e.into());
+        }
+        sessions.remove(0);
+    }
+    Ok(())
+}
+
+// 最新のバックアップから1日以上経過していればバックアップする
+pub fn backup_if_due(db: &Arc<Mutex<DatabaseManager>>) -> Result<(), AppError> {
+    let db = db.lock()?;
+    db.backup_if_due(Local::now().naive_local(), db.backup_keep())?;
+    Ok(())
+}
+
+fn session_record(session: &PlaySession) -> SessionRecord {
+    SessionRecord {
+        id: 0,
+        start_time: session.start_time.to_rfc3339(),
+        end_time: session.end_time.to_rfc3339(),
+        notes_count: session.notes_count,
+        scratch_count: session.scratch_count,
+        peak_nps: session.peak_nps,
+        average_release_time: session.average_release_time().as_secs_f64() * 1000.0,
+        release_count: session.release_count,
+        key_counts: session.key_counts.clone(),
+    }
+}
+
+// 一定間隔、コントローラー切断時、スリープからの復帰後に統計を保存するバックグラウンドスレッド
+pub struct Autosave {
+    interval: Arc<Mutex<Duration>>,
+    is_running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Autosave {
+    pub fn start(
+        db: Arc<Mutex<DatabaseManager>>,
+        gamepad: Arc<Mutex<GamepadManager>>,
+        interval: Duration,
+    ) -> Autosave {
+        let interval = Arc::new(Mutex::new(interval));
+        let is_running = Arc::new(AtomicBool::new(true));
+
+        let thread_interval = Arc::clone(&interval);
+        let thread_is_running = Arc::clone(&is_running);
+        let handle = thread::spawn(move || {
+            Self::run_loop(db, gamepad, thread_interval, thread_is_running);
+        });
+
+        Autosave {
+            interval,
+            is_running,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn set_interval(&self, interval: Duration) {
+        if let Ok(mut current) = self.interval.lock() {
+            *current = interval;
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.is_running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                eprintln!("Autosave thread panicked");
+            }
+        }
+    }
+
+    fn run_loop(
+        db: Arc<Mutex<DatabaseManager>>,
+        gamepad: Arc<Mutex<GamepadManager>>,
+        interval: Arc<Mutex<Duration>>,
+        is_running: Arc<AtomicBool>,
+    ) {
+        let mut last_saved = Instant::now();
+        let mut last_tick = SystemTime::now();
+
+        while is_running.load(Ordering::Relaxed) {
+            thread::sleep(TICK);
+
+            let now = SystemTime::now();
+            let resumed = now
+                .duration_since(last_tick)
+                .map(|elapsed| elapsed > TICK + SUSPEND_THRESHOLD)
+                .unwrap_or(false);
+            last_tick = now;
+
+            let disconnected = match gamepad.lock() {
+                Ok(gamepad) => gamepad.take_save_request(),
+                Err(_) => false,
+            };
+            let interval = interval.lock().map(|i| *i).unwrap_or(DEFAULT_INTERVAL);
+
+            if resumed || disconnected || last_saved.elapsed() >= interval {
+                if let Err(e) = save_statistics(&db, &gamepad) {
+                    eprintln!("Failed to autosave statistics: {}", e);
+                }
+                if let Err(e) = backup_if_due(&db) {
+                    eprintln!("Failed to backup database: {}", e);
+                }
+                last_saved = Instant::now();
+            }
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use crate::database::aggregate::DailyCount;
+use crate::database::{import, DatabaseManager, ExportFormat, MergeStrategy};
+use crate::error::AppError;
+use crate::gamepad::statistics::play_date;
+use crate::services;
+use crate::settings::Settings;
+use chrono::Local;
+use clap::{Args, Parser, Subcommand};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "key-display",
+    version,
+    about = "Key display and play statistics"
+)]
+pub struct Cli {
+    #[arg(
+        long,
+        help = "Run counting, statistics and servers without opening a window"
+    )]
+    pub headless: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    #[command(about = "Query and maintain the statistics database")]
+    Stats(StatsArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct StatsArgs {
+    #[arg(
+        long,
+        global = true,
+        help = "Path to statistics.db (defaults to the one the app uses)"
+    )]
+    database: Option<PathBuf>,
+    #[arg(long, global = true, help = "Print results as JSON")]
+    json: bool,
+    #[command(subcommand)]
+    command: StatsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum StatsCommand {
+    #[command(about = "Show today's notes count")]
+    Today,
+    #[command(about = "Show daily notes counts for a year")]
+    Year { year: i32 },
+    #[command(about = "Export all statistics (see README for the formats)")]
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        #[arg(long, short)]
+        output: PathBuf,
+    },
+    #[command(about = "Import another statistics.db or an exported CSV/JSON")]
+    Import {
+        path: PathBuf,
+        #[arg(long, value_enum, default_value_t = MergeStrategy::Sum)]
+        strategy: MergeStrategy,
+        #[arg(long, help = "Show the changes without applying them")]
+        dry_run: bool,
+    },
+    #[command(about = "Create a backup in the backups directory")]
+    Backup,
+    #[command(about = "Compact the database file")]
+    Vacuum,
+}
+
+// 設定が読めない場合は既定値を使う
+fn load_settings() -> Settings {
+    services::default_settings_path()
+        .and_then(|path| Settings::load(&path).ok())
+        .unwrap_or_default()
+}
+
+// 存在しないパスを指定した場合に空のデータベースを作らないよう、先に確認する
+fn database_path(path: Option<PathBuf>) -> Result<PathBuf, AppError> {
+    let path = match path {
+        Some(path) => path,
+        None => services::default_local_data_dir()
+            .ok_or_else(|| AppError::File("Failed to find the local data directory".to_string()))?
+            .join(services::DATABASE_FILE),
+    };
+    if !path.exists() {
+        return Err(AppError::File(format!(
+            "Database not found: {}",
+            path.display()
+        )));
+    }
+    Ok(path)
+}
+
+// 参照だけのコマンド用。起動中のアプリが開いていても変更しないよう、移行せず読み取り専用で開く。
+// 古いスキーマでも参照するテーブル（statistics）はあり、エクスポートは足りないテーブルを飛ばす
+fn open_read_only(path: Option<PathBuf>) -> Result<DatabaseManager, AppError> {
+    DatabaseManager::open_read_only(database_path(path)?).map_err(AppError::from)
+}
+
+// 変更するコマンド用。スキーマを最新にしてから使う
+fn open_database(path: Option<PathBuf>) -> Result<DatabaseManager, AppError> {
+    let mut db = DatabaseManager::new(database_path(path)?)?;
+    db.initialize()?;
+    Ok(db)
+}
+
+fn print_json(value: &impl Serialize) -> Result<(), AppError> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize: {}", e)))?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0)
+}
+
+pub fn run(command: Command) -> Result<(), AppError> {
+    match command {
+        Command::Stats(args) => run_stats(args),
+    }
+}
+
+fn run_stats(args: StatsArgs) -> Result<(), AppError> {
+    let settings = load_settings();
+    let now = Local::now().naive_local();
+
+    match args.command {
+        StatsCommand::Today => {
+            let db = open_read_only(args.database)?;
+            let date = play_date(now, settings.day_rollover_hour)
+                .format("%Y-%m-%d")
+                .to_string();
+            let notes_count = db.get(&date)?.unwrap_or(0) as i64;
+            if args.json {
+                return print_json(&DailyCount { date, notes_count });
+            }
+            println!("{}: {} notes", date, notes_count);
+        }
+        StatsCommand::Year { year } => {
+            let db = open_read_only(args.database)?;
+            let days: Vec<DailyCount> = db
+                .get_year_statistics(year)?
+                .into_iter()
+                .map(|(date, notes_count)| DailyCount {
+                    date,
+                    notes_count: notes_count as i64,
+                })
+                .collect();
+            if args.json {
+                return print_json(&days);
+            }
+            for day in &days {
+                println!("{}\t{}", day.date, day.notes_count);
+            }
+            let total: i64 = days.iter().map(|day| day.notes_count).sum();
+            println!("Total\t{} ({} days)", total, days.len());
+        }
+        StatsCommand::Export { format, output } => {
+            let db = open_read_only(args.database)?;
+            let summary = db.export_to_file(&output, format, &Local::now().to_rfc3339())?;
+            if args.json {
+                return print_json(&summary);
+            }
+            println!(
+                "Exported {} days and {} sessions",
+                summary.days, summary.sessions
+            );
+            for file in &summary.files {
+                println!("{}", file);
+            }
+        }
+        StatsCommand::Import {
+            path,
+            strategy,
+            dry_run,
+        } => {
+            let data = import::read_import_source(&path)?;
+            let db = open_database(args.database)?;
+            let preview = db.import_data(&data, strategy, dry_run)?;
+            if args.json {
+                return print_json(&preview);
+            }
+            for day in &preview.days {
+                let before = day
+                    .before
+                    .map_or("-".to_string(), |before| before.to_string());
+                println!("{}\t{} -> {}", day.date, before, day.after);
+            }
+            println!(
+                "{} {} days, {} hourly rows, {} new sessions",
+                if preview.applied {
+                    "Imported"
+                } else {
+                    "Would import"
+                },
+                preview.days.len(),
+                preview.hourly_changes,
+                preview.new_sessions
+            );
+        }
+        StatsCommand::Backup => {
+            let mut db = open_database(args.database)?;
+            db.set_backup_keep(settings.backup_keep as usize);
+            let path = db.create_backup(now, db.backup_keep())?;
+            if args.json {
+                return print_json(&path);
+            }
+            println!("Created {}", path.display());
+        }
+        StatsCommand::Vacuum => {
+            let db = open_database(args.database)?;
+            let path = db.path().to_path_buf();
+            let before = file_size(&path);
+            db.vacuum()?;
+            let after = file_size(&path);
+            if args.json {
+                return print_json(&serde_json::json!({ "before": before, "after": after }));
+            }
+            println!("Vacuumed {}: {} -> {} bytes", path.display(), before, after);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_parse_subcommands() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from(["key-display", "stats", "year", "2025", "--json"]).unwrap();
+        let Some(Command::Stats(args)) = cli.command else {
+            panic!("expected stats");
+        };
+        assert!(args.json);
+        assert!(matches!(args.command, StatsCommand::Year { year: 2025 }));
+
+        let cli = Cli::try_parse_from([
+            "key-display",
+            "stats",
+            "export",
+            "--format",
+            "json",
+            "-o",
+            "out.json",
+        ])
+        .unwrap();
+        let Some(Command::Stats(args)) = cli.command else {
+            panic!("expected stats");
+        };
+        assert!(matches!(
+            args.command,
+            StatsCommand::Export {
+                format: ExportFormat::Json,
+                ..
+            }
+        ));
+
+        assert!(
+            Cli::try_parse_from(["key-display", "--headless"])
+                .unwrap()
+                .headless
+        );
+        assert!(Cli::try_parse_from([
+            "key-display",
+            "stats",
+            "import",
+            "a.db",
+            "--strategy",
+            "min"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_query_commands_do_not_migrate() {
+        let path = PathBuf::from("test_cli_read_only.db");
+        if path.exists() {
+            fs::remove_file(&path).unwrap();
+        }
+        // user_versionを使う前のデータベース
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        conn.execute_batch(crate::database::migration::MIGRATIONS[0])
+            .unwrap();
+        conn.execute(
+            "INSERT INTO statistics (date, notes_count) VALUES ('2025-01-01', 10)",
+            [],
+        )
+        .unwrap();
+
+        let db = open_read_only(Some(path.clone())).unwrap();
+        assert_eq!(db.get("2025-01-01").unwrap(), Some(10));
+        assert!(db.add_notes("2025-01-01", 1).is_err());
+        drop(db);
+
+        assert_eq!(crate::database::migration::user_version(&conn).unwrap(), 0);
+        drop(conn);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,266 @@
+use super::error::DatabaseError;
+use super::manager::DatabaseManager;
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, TS)]
+#[serde(rename_all = "lowercase")]
+#[ts(export)]
+pub enum Grouping {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl Grouping {
+    // 期間のラベル。週は月曜日の日付、月は"YYYY-MM"、年は"YYYY"
+    fn period_expr(&self) -> &'static str {
+        match self {
+            Grouping::Day => "date",
+            Grouping::Week => "date(date, 'weekday 0', '-6 days')",
+            Grouping::Month => "strftime('%Y-%m', date)",
+            Grouping::Year => "strftime('%Y', date)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct PeriodStatistics {
+    pub period: String,
+    #[ts(type = "number")]
+    pub notes_count: i64,
+    pub active_days: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct DailyCount {
+    pub date: String,
+    #[ts(type = "number")]
+    pub notes_count: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct RangeSummary {
+    #[ts(type = "number")]
+    pub total: i64,
+    pub active_days: u32,
+    pub average_per_active_day: f64,
+    pub best_day: Option<DailyCount>,
+}
+
+impl DatabaseManager {
+    // start_dateからend_dateまで（両端を含む）を期間ごとに集計する
+    pub fn get_range_statistics(
+        &self,
+        start_date: &str,
+        end_date: &str,
+        grouping: Grouping,
+    ) -> Result<Vec<PeriodStatistics>, DatabaseError> {
+        let sql = format!(
+            "SELECT {period} AS period, SUM(notes_count), COUNT(CASE WHEN notes_count > 0 THEN 1 END)
+             FROM statistics
+             WHERE date BETWEEN ?1 AND ?2
+             GROUP BY period
+             ORDER BY period ASC",
+            period = grouping.period_expr()
+        );
+        let mut stmt = self
+            .conn
+            .prepare(&sql)
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to prepare: {}", e)))?;
+
+        let rows = stmt
+            .query_map([start_date, end_date], |row| {
+                Ok(PeriodStatistics {
+                    period: row.get(0)?,
+                    notes_count: row.get(1)?,
+                    active_days: row.get(2)?,
+                })
+            })
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            results.push(
+                row.map_err(|e| DatabaseError::QueryError(format!("Failed to get row: {}", e)))?,
+            );
+        }
+
+        Ok(results)
+    }
+
+    pub fn get_range_summary(
+        &self,
+        start_date: &str,
+        end_date: &str,
+    ) -> Result<RangeSummary, DatabaseError> {
+        let (total, active_days): (i64, u32) = self
+            .conn
+            .query_row(
+                "SELECT COALESCE(SUM(notes_count), 0), COUNT(CASE WHEN notes_count > 0 THEN 1 END)
+                 FROM statistics
+                 WHERE date BETWEEN ?1 AND ?2",
+                [start_date, end_date],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+
+        let best_day = self
+            .conn
+            .query_row(
+                "SELECT date, notes_count FROM statistics
+                 WHERE date BETWEEN ?1 AND ?2 AND notes_count > 0
+                 ORDER BY notes_count DESC, date ASC
+                 LIMIT 1",
+                [start_date, end_date],
+                |row| {
+                    Ok(DailyCount {
+                        date: row.get(0)?,
+                        notes_count: row.get(1)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+
+        let average_per_active_day = if active_days == 0 {
+            0.0
+        } else {
+            total as f64 / active_days as f64
+        };
+
+        Ok(RangeSummary {
+            total,
+            active_days,
+            average_per_active_day,
+            best_day,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_util::{cleanup_test_db, setup_test_db};
+
+    fn insert_sample_data(db: &DatabaseManager) {
+        // 2025-01-05は日曜日、2025-01-06は月曜日
+        db.insert("2025-01-05", 100).unwrap();
+        db.insert("2025-01-06", 200).unwrap();
+        db.insert("2025-01-07", 0).unwrap();
+        db.insert("2025-01-12", 300).unwrap();
+        db.insert("2025-02-01", 400).unwrap();
+        db.insert("2026-01-01", 999).unwrap();
+    }
+
+    #[test]
+    fn test_group_by_day() {
+        let (db, path) = setup_test_db("aggregate_day");
+        insert_sample_data(&db);
+
+        let results = db
+            .get_range_statistics("2025-01-06", "2025-01-07", Grouping::Day)
+            .unwrap();
+        assert_eq!(
+            results,
+            vec![
+                PeriodStatistics {
+                    period: "2025-01-06".to_string(),
+                    notes_count: 200,
+                    active_days: 1,
+                },
+                PeriodStatistics {
+                    period: "2025-01-07".to_string(),
+                    notes_count: 0,
+                    active_days: 0,
+                },
+            ]
+        );
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_group_by_week() {
+        let (db, path) = setup_test_db("aggregate_week");
+        insert_sample_data(&db);
+
+        let results = db
+            .get_range_statistics("2025-01-01", "2025-01-31", Grouping::Week)
+            .unwrap();
+        let periods: Vec<(&str, i64, u32)> = results
+            .iter()
+            .map(|r| (r.period.as_str(), r.notes_count, r.active_days))
+            .collect();
+        assert_eq!(
+            periods,
+            vec![("2024-12-30", 100, 1), ("2025-01-06", 500, 2)]
+        );
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_group_by_month_and_year() {
+        let (db, path) = setup_test_db("aggregate_month_year");
+        insert_sample_data(&db);
+
+        let months = db
+            .get_range_statistics("2025-01-01", "2025-12-31", Grouping::Month)
+            .unwrap();
+        assert_eq!(months.len(), 2);
+        assert_eq!(months[0].period, "2025-01");
+        assert_eq!(months[0].notes_count, 600);
+        assert_eq!(months[1].period, "2025-02");
+
+        let years = db
+            .get_range_statistics("2000-01-01", "2099-12-31", Grouping::Year)
+            .unwrap();
+        assert_eq!(years.len(), 2);
+        assert_eq!(years[0].notes_count, 1000);
+        assert_eq!(years[0].active_days, 4);
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_range_summary() {
+        let (db, path) = setup_test_db("aggregate_summary");
+        insert_sample_data(&db);
+
+        let summary = db.get_range_summary("2025-01-01", "2025-12-31").unwrap();
+        assert_eq!(summary.total, 1000);
+        assert_eq!(summary.active_days, 4);
+        assert_eq!(summary.average_per_active_day, 250.0);
+        assert_eq!(
+            summary.best_day,
+            Some(DailyCount {
+                date: "2025-02-01".to_string(),
+                notes_count: 400,
+            })
+        );
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_range_summary_empty() {
+        let (db, path) = setup_test_db("aggregate_summary_empty");
+
+        let summary = db.get_range_summary("2025-01-01", "2025-12-31").unwrap();
+        assert_eq!(summary.total, 0);
+        assert_eq!(summary.active_days, 0);
+        assert_eq!(summary.average_per_active_day, 0.0);
+        assert_eq!(summary.best_day, None);
+
+        cleanup_test_db(path);
+    }
+}
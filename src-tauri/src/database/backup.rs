@@ -0,0 +1,504 @@
+use super::error::DatabaseError;
+use super::manager::DatabaseManager;
+use chrono::{NaiveDateTime, TimeDelta};
+use rusqlite::{Connection, ErrorCode, OpenFlags, MAIN_DB};
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use ts_rs::TS;
+
+pub const DEFAULT_KEEP: usize = 7;
+// 最新のバックアップからこれ以上経過していれば自動でバックアップする
+pub const BACKUP_INTERVAL: TimeDelta = TimeDelta::days(1);
+
+const BACKUP_PREFIX: &str = "statistics-";
+const BACKUP_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+// 整合性の確認で、別のプロセスのロックが外れるのを待つ時間
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+// データベースと一緒に退避するジャーナル
+const SIDECAR_SUFFIXES: [&str; 3] = ["-wal", "-shm", "-journal"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct BackupInfo {
+    pub file_name: String,
+    pub created_at: String,
+    #[ts(type = "number")]
+    pub size: u64,
+}
+
+// 起動時に壊れたデータベースを退避した結果
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+#[ts(export)]
+pub enum Recovery {
+    // 最新の正常なバックアップから復元した
+    #[serde(rename_all = "camelCase")]
+    Restored {
+        corrupt_path: String,
+        backup_path: String,
+    },
+    // 正常なバックアップがなく、空のデータベースで始めた
+    #[serde(rename_all = "camelCase")]
+    NoBackup { corrupt_path: String },
+}
+
+impl fmt::Display for Recovery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Recovery::Restored {
+                corrupt_path,
+                backup_path,
+            } => write!(
+                f,
+                "Database was corrupt and moved to {}; restored from {}",
+                corrupt_path, backup_path
+            ),
+            Recovery::NoBackup { corrupt_path } => write!(
+                f,
+                "Database was corrupt and moved to {}; no intact backup was found, started with an empty database",
+                corrupt_path
+            ),
+        }
+    }
+}
+
+// バックアップはデータベースと同じディレクトリのbackupsに置く
+pub fn backup_dir(db_path: &Path) -> PathBuf {
+    db_path
+        .parent()
+        .map(|parent| parent.join("backups"))
+        .unwrap_or_else(|| PathBuf::from("backups"))
+}
+
+fn parse_backup_time(file_name: &str) -> Option<NaiveDateTime> {
+    let time = file_name.strip_prefix(BACKUP_PREFIX)?.strip_suffix(".db")?;
+    NaiveDateTime::parse_from_str(time, BACKUP_TIME_FORMAT).ok()
+}
+
+// 新しい順にバックアップを返す
+pub fn list_backups(dir: &Path) -> Result<Vec<BackupInfo>, DatabaseError> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = fs::read_dir(dir).map_err(|e| {
+        DatabaseError::FileError(format!("Failed to read {}: {}", dir.display(), e))
+    })?;
+
+    let mut backups = Vec::new();
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if let Some(created_at) = parse_backup_time(&file_name) {
+            backups.push(BackupInfo {
+                file_name,
+                created_at: created_at.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                size: entry.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+            });
+        }
+    }
+    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(backups)
+}
+
+// 新しいものからkeep個を残して削除する
+pub fn rotate_backups(dir: &Path, keep: usize) -> Result<(), DatabaseError> {
+    for backup in list_backups(dir)?.into_iter().skip(keep) {
+        fs::remove_file(dir.join(&backup.file_name)).map_err(|e| {
+            DatabaseError::FileError(format!("Failed to remove {}: {}", backup.file_name, e))
+        })?;
+    }
+    Ok(())
+}
+
+// 壊れている・データベースではないことを示すエラー
+fn is_corruption(error: &rusqlite::Error) -> bool {
+    matches!(
+        error.sqlite_error_code(),
+        Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase)
+    )
+}
+
+// 壊れている場合はfalse。ロックや権限などのエラーは壊れているとはみなさず、そのまま返す
+pub fn is_intact(conn: &Connection) -> Result<bool, DatabaseError> {
+    match conn.query_row("PRAGMA integrity_check", [], |row| row.get::<_, String>(0)) {
+        Ok(result) => Ok(result == "ok"),
+        Err(e) if is_corruption(&e) => Ok(false),
+        Err(e) => Err(DatabaseError::QueryError(format!(
+            "Failed to check integrity: {}",
+            e
+        ))),
+    }
+}
+
+// 異常終了で残ったジャーナルを通常どおりロールバックできるよう、読み書きで開く（作成はしない）
+fn is_intact_file(path: &Path, busy_timeout: Duration) -> Result<bool, DatabaseError> {
+    let conn = match Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_WRITE) {
+        Ok(conn) => conn,
+        Err(e) if is_corruption(&e) => return Ok(false),
+        Err(e) => {
+            return Err(DatabaseError::ConnectionError(format!(
+                "Failed to open {}: {}",
+                path.display(),
+                e
+            )))
+        }
+    };
+    conn.busy_timeout(busy_timeout).map_err(|e| {
+        DatabaseError::ConnectionError(format!("Failed to set busy timeout: {}", e))
+    })?;
+    is_intact(&conn)
+}
+
+// pathのジャーナルがあれば、toに同じ接尾辞を付けた名前へ移す
+fn rename_sidecars(path: &Path, to: &Path) -> Result<(), DatabaseError> {
+    for suffix in SIDECAR_SUFFIXES {
+        let mut from_sidecar = path.as_os_str().to_owned();
+        from_sidecar.push(suffix);
+        if !Path::new(&from_sidecar).exists() {
+            continue;
+        }
+        let mut to_sidecar = to.as_os_str().to_owned();
+        to_sidecar.push(suffix);
+        fs::rename(&from_sidecar, &to_sidecar)
+            .map_err(|e| DatabaseError::FileError(format!("Failed to move {}: {}", suffix, e)))?;
+    }
+    Ok(())
+}
+
+// データベースを開き、壊れていれば退避して最新の正常なバックアップから復元する。
+// 正常なバックアップがない場合は空のデータベースで始め、この起動中は自動バックアップしない
+// （空のバックアップで古いバックアップを押し出さないため）。
+// ロックや権限のエラーで確認できない場合は、退避せずにエラーを返す
+pub fn open_with_recovery(
+    path: PathBuf,
+    now: NaiveDateTime,
+) -> Result<(DatabaseManager, Option<Recovery>), DatabaseError> {
+    if !path.exists() || is_intact_file(&path, BUSY_TIMEOUT)? {
+        return Ok((DatabaseManager::new(path)?, None));
+    }
+
+    // 古いジャーナルが復元したファイルに適用されないよう、一緒に退避する
+    let mut corrupt_path = path.as_os_str().to_owned();
+    corrupt_path.push(format!(".corrupt-{}", now.format(BACKUP_TIME_FORMAT)));
+    let corrupt_path = PathBuf::from(corrupt_path);
+    fs::rename(&path, &corrupt_path)
+        .map_err(|e| DatabaseError::FileError(format!("Failed to move corrupt database: {}", e)))?;
+    rename_sidecars(&path, &corrupt_path)?;
+
+    // 確認できないバックアップは使わない
+    let dir = backup_dir(&path);
+    let restored = list_backups(&dir)?
+        .into_iter()
+        .map(|backup| dir.join(backup.file_name))
+        .find(|backup_path| matches!(is_intact_file(backup_path, BUSY_TIMEOUT), Ok(true)));
+    let corrupt_path = corrupt_path.display().to_string();
+    let Some(backup_path) = restored else {
+        let mut db = DatabaseManager::new(path)?;
+        db.set_auto_backup(false);
+        return Ok((db, Some(Recovery::NoBackup { corrupt_path })));
+    };
+
+    fs::copy(&backup_path, &path)
+        .map_err(|e| DatabaseError::FileError(format!("Failed to restore backup: {}", e)))?;
+    let recovery = Recovery::Restored {
+        corrupt_path,
+        backup_path: backup_path.display().to_string(),
+    };
+    Ok((DatabaseManager::new(path)?, Some(recovery)))
+}
+
+impl DatabaseManager {
+    pub fn backup_dir(&self) -> PathBuf {
+        backup_dir(&self.path)
+    }
+
+    #[cfg(test)]
+    pub fn is_intact(&self) -> Result<bool, DatabaseError> {
+        is_intact(&self.conn)
+    }
+
+    // オンラインバックアップでbackupsにコピーし、古いものを削除する
+    pub fn create_backup(&self, now: NaiveDateTime, keep: usize) -> Result<PathBuf, DatabaseError> {
+        let backup_path = self.write_backup(now)?;
+        rotate_backups(&self.backup_dir(), keep)?;
+        Ok(backup_path)
+    }
+
+    fn write_backup(&self, now: NaiveDateTime) -> Result<PathBuf, DatabaseError> {
+        let dir = self.backup_dir();
+        fs::create_dir_all(&dir).map_err(|e| {
+            DatabaseError::FileError(format!("Failed to create {}: {}", dir.display(), e))
+        })?;
+
+        let file_name = format!("{}{}.db", BACKUP_PREFIX, now.format(BACKUP_TIME_FORMAT));
+        let backup_path = dir.join(&file_name);
+        // 途中で失敗したファイルがバックアップとして扱われないよう、一時ファイルに書いてから置き換える
+        let temp_path = dir.join(format!("{}.tmp", file_name));
+        let _ = fs::remove_file(&temp_path);
+        self.conn
+            .backup(MAIN_DB, &temp_path, None)
+            .map_err(|e| DatabaseError::FileError(format!("Failed to backup: {}", e)))?;
+        fs::rename(&temp_path, &backup_path)
+            .map_err(|e| DatabaseError::FileError(format!("Failed to move backup: {}", e)))?;
+
+        Ok(backup_path)
+    }
+
+    // 最新のバックアップからBACKUP_INTERVAL以上経過していればバックアップする
+    pub fn backup_if_due(
+        &self,
+        now: NaiveDateTime,
+        keep: usize,
+    ) -> Result<Option<PathBuf>, DatabaseError> {
+        if !self.auto_backup() {
+            return Ok(None);
+        }
+        let latest = list_backups(&self.backup_dir())?
+            .first()
+            .and_then(|backup| parse_backup_time(&backup.file_name));
+        match latest {
+            Some(latest) if now - latest < BACKUP_INTERVAL => Ok(None),
+            _ => self.create_backup(now, keep).map(Some),
+        }
+    }
+
+    // backupsにあるバックアップで現在のデータベースを置き換える。
+    // 置き換える前に現在の内容もバックアップしておく
+    pub fn restore_backup(
+        &mut self,
+        file_name: &str,
+        now: NaiveDateTime,
+        keep: usize,
+    ) -> Result<(), DatabaseError> {
+        // backups以外のファイルを指定できないよう、ファイル名の形式を確認する
+        if parse_backup_time(file_name).is_none() {
+            return Err(DatabaseError::FileError(format!(
+                "Invalid backup name {}",
+                file_name
+            )));
+        }
+        let backup_path = self.backup_dir().join(file_name);
+        if !backup_path.exists() || !is_intact_file(&backup_path, BUSY_TIMEOUT)? {
+            return Err(DatabaseError::FileError(format!(
+                "Backup {} is missing or corrupt",
+                file_name
+            )));
+        }
+
+        // 復元するバックアップを上書きしないよう、同じ時刻の場合は1秒ずらす
+        let pre_restore_time = match parse_backup_time(file_name) {
+            Some(time) if time == now => now + TimeDelta::seconds(1),
+            _ => now,
+        };
+        self.write_backup(pre_restore_time)?;
+
+        self.conn
+            .restore(
+                MAIN_DB,
+                &backup_path,
+                None::<fn(rusqlite::backup::Progress)>,
+            )
+            .map_err(|e| DatabaseError::FileError(format!("Failed to restore: {}", e)))?;
+        // 古いバージョンのバックアップの場合に備えてスキーマを移行する
+        self.initialize()?;
+        // 復元したデータは通常どおり自動でバックアップする
+        self.set_auto_backup(true);
+        rotate_backups(&self.backup_dir(), keep)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    // テストごとにディレクトリを分け、backupsも含めて削除できるようにする
+    fn setup_test_db(test_name: &str) -> (DatabaseManager, PathBuf) {
+        let dir = PathBuf::from(format!("test_backup_{}", test_name));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut db = DatabaseManager::new(dir.join("statistics.db")).unwrap();
+        db.initialize().unwrap();
+
+        (db, dir)
+    }
+
+    fn cleanup_test_dir(dir: PathBuf) {
+        if dir.exists() {
+            fs::remove_dir_all(dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_create_and_rotate_backups() {
+        let (db, dir) = setup_test_db("rotate");
+        db.insert("2025-01-01", 100).unwrap();
+
+        for second in 0..4 {
+            db.create_backup(datetime(&format!("2025-01-01 12:00:0{}", second)), 3)
+                .unwrap();
+        }
+
+        let backups = list_backups(&db.backup_dir()).unwrap();
+        assert_eq!(backups.len(), 3);
+        assert_eq!(backups[0].file_name, "statistics-20250101-120003.db");
+        assert_eq!(backups[2].file_name, "statistics-20250101-120001.db");
+
+        let backup = DatabaseManager::new(db.backup_dir().join(&backups[0].file_name)).unwrap();
+        assert_eq!(backup.get("2025-01-01").unwrap(), Some(100));
+
+        drop(backup);
+        drop(db);
+        cleanup_test_dir(dir);
+    }
+
+    #[test]
+    fn test_backup_if_due() {
+        let (db, dir) = setup_test_db("due");
+
+        assert!(db
+            .backup_if_due(datetime("2025-01-01 12:00:00"), DEFAULT_KEEP)
+            .unwrap()
+            .is_some());
+        assert!(db
+            .backup_if_due(datetime("2025-01-02 11:59:59"), DEFAULT_KEEP)
+            .unwrap()
+            .is_none());
+        assert!(db
+            .backup_if_due(datetime("2025-01-02 12:00:00"), DEFAULT_KEEP)
+            .unwrap()
+            .is_some());
+
+        drop(db);
+        cleanup_test_dir(dir);
+    }
+
+    #[test]
+    fn test_restore_backup() {
+        let (mut db, dir) = setup_test_db("restore");
+        db.insert("2025-01-01", 100).unwrap();
+        let backup_path = db
+            .create_backup(datetime("2025-01-01 12:00:00"), DEFAULT_KEEP)
+            .unwrap();
+        db.update("2025-01-01", 999999).unwrap();
+
+        let file_name = backup_path.file_name().unwrap().to_string_lossy();
+        db.restore_backup(&file_name, datetime("2025-01-02 12:00:00"), DEFAULT_KEEP)
+            .unwrap();
+        assert_eq!(db.get("2025-01-01").unwrap(), Some(100));
+
+        // 復元前の内容もバックアップされている
+        let backups = list_backups(&db.backup_dir()).unwrap();
+        assert_eq!(backups[0].file_name, "statistics-20250102-120000.db");
+
+        assert!(db
+            .restore_backup(
+                "../statistics.db",
+                datetime("2025-01-02 12:00:01"),
+                DEFAULT_KEEP
+            )
+            .is_err());
+
+        drop(db);
+        cleanup_test_dir(dir);
+    }
+
+    #[test]
+    fn test_open_with_recovery() {
+        let (db, dir) = setup_test_db("recovery");
+        db.insert("2025-01-01", 100).unwrap();
+        db.create_backup(datetime("2025-01-01 12:00:00"), DEFAULT_KEEP)
+            .unwrap();
+        drop(db);
+
+        // データベースを壊す
+        let path = dir.join("statistics.db");
+        fs::write(&path, b"this is not a database").unwrap();
+
+        let (db, recovery) = open_with_recovery(path, datetime("2025-01-02 12:00:00")).unwrap();
+        assert!(matches!(recovery, Some(Recovery::Restored { .. })));
+        assert!(db.is_intact().unwrap());
+        assert_eq!(db.get("2025-01-01").unwrap(), Some(100));
+        assert!(dir.join("statistics.db.corrupt-20250102-120000").exists());
+
+        drop(db);
+        cleanup_test_dir(dir);
+    }
+
+    #[test]
+    fn test_open_with_recovery_without_backup() {
+        let (db, dir) = setup_test_db("recovery_no_backup");
+        drop(db);
+        let path = dir.join("statistics.db");
+        fs::write(&path, b"this is not a database").unwrap();
+
+        let (mut db, recovery) = open_with_recovery(path, datetime("2025-01-02 12:00:00")).unwrap();
+        assert_eq!(
+            recovery,
+            Some(Recovery::NoBackup {
+                corrupt_path: dir
+                    .join("statistics.db.corrupt-20250102-120000")
+                    .display()
+                    .to_string()
+            })
+        );
+        db.initialize().unwrap();
+
+        // 空のデータベースは自動でバックアップしない
+        assert_eq!(
+            db.backup_if_due(datetime("2025-01-02 12:00:00"), DEFAULT_KEEP)
+                .unwrap(),
+            None
+        );
+        assert!(list_backups(&db.backup_dir()).unwrap().is_empty());
+
+        drop(db);
+        cleanup_test_dir(dir);
+    }
+
+    #[test]
+    fn test_rename_sidecars() {
+        let (db, dir) = setup_test_db("sidecars");
+        drop(db);
+        let path = dir.join("statistics.db");
+        fs::write(dir.join("statistics.db-wal"), b"stale wal").unwrap();
+        fs::write(dir.join("statistics.db-journal"), b"stale journal").unwrap();
+
+        rename_sidecars(&path, &dir.join("statistics.db.corrupt")).unwrap();
+        assert!(!dir.join("statistics.db-wal").exists());
+        assert!(!dir.join("statistics.db-journal").exists());
+        assert!(dir.join("statistics.db.corrupt-wal").exists());
+        assert!(dir.join("statistics.db.corrupt-journal").exists());
+        assert!(!dir.join("statistics.db.corrupt-shm").exists());
+
+        cleanup_test_dir(dir);
+    }
+
+    #[test]
+    fn test_locked_database_is_not_corrupt() {
+        let (db, dir) = setup_test_db("locked");
+        db.insert("2025-01-01", 100).unwrap();
+        let path = dir.join("statistics.db");
+
+        // 別のプロセスが書き込み中の場合は確認できないが、壊れているとはみなさない
+        let lock = Connection::open(&path).unwrap();
+        lock.execute_batch("BEGIN EXCLUSIVE").unwrap();
+        assert!(is_intact_file(&path, Duration::ZERO).is_err());
+
+        lock.execute_batch("COMMIT").unwrap();
+        assert!(is_intact_file(&path, Duration::ZERO).unwrap());
+
+        drop(lock);
+        drop(db);
+        cleanup_test_dir(dir);
+    }
+}
@@ -0,0 +1,555 @@
+use super::error::DatabaseError;
+use super::manager::DatabaseManager;
+use rusqlite::{params, OptionalExtension, Row, Transaction};
+use serde::Serialize;
+use ts_rs::TS;
+
+// 全期間を対象にするときの日付の範囲
+const MIN_DATE: &str = "0000-01-01";
+const MAX_DATE: &str = "9999-12-31";
+
+// 日別の統計はプレイ日付、時間帯別の統計とセッションは実際の日時で記録しているため、
+// 後の2つは日付変更時刻を引いてプレイ日付に直してから比べる。?3はrollover_modifierの値
+const HOURLY_PLAY_DATE: &str = "date(date, '+' || hour || ' hours', ?3)";
+const SESSION_PLAY_DATE: &str = "date(substr(start_time, 1, 19), ?3)";
+
+fn rollover_modifier(rollover_hour: u32) -> String {
+    format!("-{} hours", rollover_hour)
+}
+
+// 削除対象（削除済みの場合は削除した内容）の概要
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct DeletionSummary {
+    pub days: u32,
+    #[ts(type = "number")]
+    pub notes_count: i64,
+    pub sessions: u32,
+    pub deleted: bool,
+}
+
+// 手動修正の記録
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Correction {
+    #[ts(type = "number")]
+    pub id: i64,
+    pub date: String,
+    pub old_count: Option<i32>,
+    pub new_count: i32,
+    pub reason: String,
+    pub corrected_at: String,
+}
+
+impl Correction {
+    fn from_row(row: &Row) -> rusqlite::Result<Correction> {
+        Ok(Correction {
+            id: row.get(0)?,
+            date: row.get(1)?,
+            old_count: row.get(2)?,
+            new_count: row.get(3)?,
+            reason: row.get(4)?,
+            corrected_at: row.get(5)?,
+        })
+    }
+}
+
+fn summarize(
+    tx: &Transaction,
+    start_date: &str,
+    end_date: &str,
+    rollover_hour: u32,
+) -> Result<DeletionSummary, DatabaseError> {
+    let (days, notes_count) = tx
+        .query_row(
+            "SELECT COUNT(*), COALESCE(SUM(notes_count), 0) FROM statistics WHERE date BETWEEN ?1 AND ?2",
+            [start_date, end_date],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+    let sessions = tx
+        .query_row(
+            &format!(
+                "SELECT COUNT(*) FROM sessions WHERE {} BETWEEN ?1 AND ?2",
+                SESSION_PLAY_DATE
+            ),
+            params![start_date, end_date, rollover_modifier(rollover_hour)],
+            |row| row.get(0),
+        )
+        .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+
+    Ok(DeletionSummary {
+        days,
+        notes_count,
+        sessions,
+        deleted: false,
+    })
+}
+
+// プレイ日付がdateの時間帯別のノーツ数を、合計がnotes_countになるよう按分する。
+// 端数は切り捨てた分の大きい時間帯から配る
+fn scale_hourly(
+    tx: &Transaction,
+    date: &str,
+    notes_count: i32,
+    rollover_hour: u32,
+) -> Result<(), DatabaseError> {
+    let mut stmt = tx
+        .prepare(&format!(
+            "SELECT date, hour, notes_count FROM hourly_statistics
+             WHERE {} BETWEEN ?1 AND ?2 ORDER BY date, hour",
+            HOURLY_PLAY_DATE
+        ))
+        .map_err(|e| DatabaseError::QueryError(format!("Failed to prepare: {}", e)))?;
+    let rows = stmt
+        .query_map(
+            params![date, date, rollover_modifier(rollover_hour)],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, u32>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            },
+        )
+        .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| DatabaseError::QueryError(format!("Failed to get row: {}", e)))?;
+
+    let total: i64 = rows.iter().map(|(_, _, count)| count).sum();
+    if total <= 0 {
+        return Ok(());
+    }
+    let target = notes_count as i64;
+    let mut scaled: Vec<(&str, u32, i64, i64)> = rows
+        .iter()
+        .map(|(date, hour, count)| {
+            (
+                date.as_str(),
+                *hour,
+                count * target / total,
+                count * target % total,
+            )
+        })
+        .collect();
+    let remainder = target - scaled.iter().map(|(_, _, count, _)| count).sum::<i64>();
+    let mut order: Vec<usize> = (0..scaled.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(scaled[i].3));
+    for &i in order.iter().take(remainder as usize) {
+        scaled[i].2 += 1;
+    }
+
+    for (date, hour, count, _) in scaled {
+        if count == 0 {
+            tx.execute(
+                "DELETE FROM hourly_statistics WHERE date = ?1 AND hour = ?2",
+                params![date, hour],
+            )
+        } else {
+            tx.execute(
+                "UPDATE hourly_statistics SET notes_count = ?3 WHERE date = ?1 AND hour = ?2",
+                params![date, hour, count],
+            )
+        }
+        .map_err(|e| DatabaseError::QueryError(format!("Failed to update: {}", e)))?;
+    }
+    Ok(())
+}
+
+impl DatabaseManager {
+    // プレイ日付がstart_dateからend_date（両端を含む）のものを削除した場合の概要を返す。
+    // rollover_hourは日付変更時刻
+    pub fn preview_delete_range(
+        &self,
+        start_date: &str,
+        end_date: &str,
+        rollover_hour: u32,
+    ) -> Result<DeletionSummary, DatabaseError> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to begin: {}", e)))?;
+        summarize(&tx, start_date, end_date, rollover_hour)
+    }
+
+    // プレイ日付がstart_dateからend_date（両端を含む）の日別・時間帯別の統計と、
+    // その期間に開始したセッションを削除する
+    pub fn delete_range(
+        &self,
+        start_date: &str,
+        end_date: &str,
+        rollover_hour: u32,
+    ) -> Result<DeletionSummary, DatabaseError> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to begin: {}", e)))?;
+        let summary = summarize(&tx, start_date, end_date, rollover_hour)?;
+        let modifier = rollover_modifier(rollover_hour);
+
+        tx.execute(
+            "DELETE FROM statistics WHERE date BETWEEN ?1 AND ?2",
+            [start_date, end_date],
+        )
+        .and_then(|_| {
+            tx.execute(
+                &format!(
+                    "DELETE FROM hourly_statistics WHERE {} BETWEEN ?1 AND ?2",
+                    HOURLY_PLAY_DATE
+                ),
+                params![start_date, end_date, modifier],
+            )
+        })
+        .and_then(|_| {
+            tx.execute(
+                &format!(
+                    "DELETE FROM session_key_counts WHERE session_id IN
+                     (SELECT id FROM sessions WHERE {} BETWEEN ?1 AND ?2)",
+                    SESSION_PLAY_DATE
+                ),
+                params![start_date, end_date, modifier],
+            )
+        })
+        .and_then(|_| {
+            tx.execute(
+                &format!(
+                    "DELETE FROM sessions WHERE {} BETWEEN ?1 AND ?2",
+                    SESSION_PLAY_DATE
+                ),
+                params![start_date, end_date, modifier],
+            )
+        })
+        .map_err(|e| DatabaseError::QueryError(format!("Failed to delete: {}", e)))?;
+
+        tx.commit()
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to commit: {}", e)))?;
+        Ok(DeletionSummary {
+            deleted: true,
+            ..summary
+        })
+    }
+
+    #[cfg(test)]
+    pub fn delete(&self, date: &str, rollover_hour: u32) -> Result<DeletionSummary, DatabaseError> {
+        self.delete_range(date, date, rollover_hour)
+    }
+
+    // 全期間では日付変更時刻に関係なくすべて対象になるため、実際の日付のまま比べる
+    pub fn preview_delete_all(&self) -> Result<DeletionSummary, DatabaseError> {
+        self.preview_delete_range(MIN_DATE, MAX_DATE, 0)
+    }
+
+    // 修正履歴と目標は残す
+    pub fn delete_all(&self) -> Result<DeletionSummary, DatabaseError> {
+        self.delete_range(MIN_DATE, MAX_DATE, 0)
+    }
+
+    // 日付のノーツ数を手動で修正し、修正前後の値と理由を記録する。
+    // プレイ日付がdateの時間帯別の統計も同じ合計になるよう按分する（セッションの記録はそのまま残す）
+    pub fn correct_notes(
+        &self,
+        date: &str,
+        notes_count: i32,
+        reason: &str,
+        corrected_at: &str,
+        rollover_hour: u32,
+    ) -> Result<Correction, DatabaseError> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to begin: {}", e)))?;
+
+        let old_count: Option<i32> = tx
+            .query_row(
+                "SELECT notes_count FROM statistics WHERE date = ?1",
+                [date],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+
+        tx.execute(
+            "INSERT INTO statistics (date, notes_count) VALUES (?1, ?2)
+             ON CONFLICT(date) DO UPDATE SET notes_count = excluded.notes_count",
+            params![date, notes_count],
+        )
+        .map_err(|e| DatabaseError::QueryError(format!("Failed to update: {}", e)))?;
+        scale_hourly(&tx, date, notes_count, rollover_hour)?;
+        tx.execute(
+            "INSERT INTO corrections (date, old_count, new_count, reason, corrected_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![date, old_count, notes_count, reason, corrected_at],
+        )
+        .map_err(|e| DatabaseError::QueryError(format!("Failed to insert correction: {}", e)))?;
+        let id = tx.last_insert_rowid();
+
+        tx.commit()
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to commit: {}", e)))?;
+        Ok(Correction {
+            id,
+            date: date.to_string(),
+            old_count,
+            new_count: notes_count,
+            reason: reason.to_string(),
+            corrected_at: corrected_at.to_string(),
+        })
+    }
+
+    // 新しい順に修正履歴を返す
+    pub fn list_corrections(&self, limit: u32) -> Result<Vec<Correction>, DatabaseError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, date, old_count, new_count, reason, corrected_at
+                 FROM corrections ORDER BY id DESC LIMIT ?1",
+            )
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to prepare: {}", e)))?;
+
+        let rows = stmt
+            .query_map([limit], Correction::from_row)
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            results.push(
+                row.map_err(|e| DatabaseError::QueryError(format!("Failed to get row: {}", e)))?,
+            );
+        }
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_util::{self, cleanup_test_db};
+    use crate::database::SessionRecord;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    fn setup_test_db(test_name: &str) -> (DatabaseManager, PathBuf) {
+        let (db, test_db_path) = test_util::setup_test_db(&format!("edit_{}", test_name));
+
+        db.insert("2025-01-01", 100).unwrap();
+        db.insert("2025-01-02", 200).unwrap();
+        db.insert("2025-01-03", 300).unwrap();
+        db.add_hourly_notes("2025-01-02", 20, 200).unwrap();
+        db.insert_session(&SessionRecord {
+            id: 0,
+            start_time: "2025-01-02T20:00:00+09:00".to_string(),
+            end_time: "2025-01-02T21:00:00+09:00".to_string(),
+            notes_count: 200,
+            scratch_count: 0,
+            peak_nps: 10,
+            average_release_time: 50.0,
+            release_count: 0,
+            key_counts: BTreeMap::from([(1, 200)]),
+        })
+        .unwrap();
+
+        (db, test_db_path)
+    }
+
+    #[test]
+    fn test_preview_does_not_delete() {
+        let (db, path) = setup_test_db("preview");
+
+        let summary = db
+            .preview_delete_range("2025-01-02", "2025-01-03", 0)
+            .unwrap();
+        assert_eq!(
+            summary,
+            DeletionSummary {
+                days: 2,
+                notes_count: 500,
+                sessions: 1,
+                deleted: false,
+            }
+        );
+        assert_eq!(db.get("2025-01-02").unwrap(), Some(200));
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_delete_single_date() {
+        let (db, path) = setup_test_db("single");
+
+        let summary = db.delete("2025-01-02", 0).unwrap();
+        assert!(summary.deleted);
+        assert_eq!(summary.notes_count, 200);
+
+        assert_eq!(db.get("2025-01-01").unwrap(), Some(100));
+        assert_eq!(db.get("2025-01-02").unwrap(), None);
+        assert_eq!(db.get("2025-01-03").unwrap(), Some(300));
+        let hourly = db
+            .get_hourly_statistics("2025-01-01", "2025-01-31")
+            .unwrap();
+        assert!(hourly.iter().flatten().all(|&count| count == 0));
+        assert!(db.list_sessions(10, 0).unwrap().is_empty());
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_delete_all() {
+        let (db, path) = setup_test_db("all");
+
+        let summary = db.delete_all().unwrap();
+        assert_eq!(summary.days, 3);
+        assert_eq!(summary.notes_count, 600);
+        assert!(db.get_year_statistics(2025).unwrap().is_empty());
+        assert_eq!(db.preview_delete_all().unwrap().days, 0);
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_correct_notes_is_recorded() {
+        let (db, path) = setup_test_db("correct");
+
+        db.correct_notes(
+            "2025-01-02",
+            2000,
+            "stuck key",
+            "2025-01-05T10:00:00+09:00",
+            0,
+        )
+        .unwrap();
+        db.correct_notes(
+            "2025-01-04",
+            50,
+            "missed save",
+            "2025-01-05T10:01:00+09:00",
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(db.get("2025-01-02").unwrap(), Some(2000));
+        assert_eq!(db.get("2025-01-04").unwrap(), Some(50));
+
+        let corrections = db.list_corrections(10).unwrap();
+        assert_eq!(corrections.len(), 2);
+        assert_eq!(corrections[0].date, "2025-01-04");
+        assert_eq!(corrections[0].old_count, None);
+        assert_eq!(corrections[1].old_count, Some(200));
+        assert_eq!(corrections[1].new_count, 2000);
+        assert_eq!(corrections[1].reason, "stuck key");
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_correct_notes_scales_hourly_statistics() {
+        let (db, path) = setup_test_db("correct_hourly");
+        db.add_hourly_notes("2025-01-06", 10, 100_000).unwrap();
+        db.add_hourly_notes("2025-01-06", 11, 399_999).unwrap();
+        db.add_hourly_notes("2025-01-06", 12, 1).unwrap();
+        db.add_hourly_notes("2025-01-07", 10, 30).unwrap();
+
+        db.correct_notes(
+            "2025-01-06",
+            1000,
+            "stuck key",
+            "2025-01-07T10:00:00+09:00",
+            0,
+        )
+        .unwrap();
+
+        let hourly = db
+            .get_hourly_statistics("2025-01-06", "2025-01-06")
+            .unwrap();
+        // 2025-01-06は月曜日
+        assert_eq!(hourly[1][10], 200);
+        assert_eq!(hourly[1][11], 800);
+        assert_eq!(hourly[1][12], 0);
+        assert_eq!(hourly[1].iter().sum::<u32>(), 1000);
+
+        let next_day = db
+            .get_hourly_statistics("2025-01-07", "2025-01-07")
+            .unwrap();
+        assert_eq!(next_day[2][10], 30);
+
+        cleanup_test_db(path);
+    }
+
+    fn session(start_time: &str) -> SessionRecord {
+        SessionRecord {
+            id: 0,
+            start_time: start_time.to_string(),
+            end_time: start_time.to_string(),
+            notes_count: 10,
+            scratch_count: 0,
+            peak_nps: 1,
+            average_release_time: 0.0,
+            release_count: 0,
+            key_counts: BTreeMap::from([(1, 10)]),
+        }
+    }
+
+    #[test]
+    fn test_delete_range_uses_play_date() {
+        let (db, path) = test_util::setup_test_db("edit_rollover");
+        // 日付変更時刻が5時の場合、2025-01-03 02:00は2025-01-02のプレイ
+        db.insert("2025-01-02", 30).unwrap();
+        db.insert("2025-01-03", 5).unwrap();
+        db.add_hourly_notes("2025-01-02", 5, 10).unwrap();
+        db.add_hourly_notes("2025-01-03", 2, 20).unwrap();
+        db.add_hourly_notes("2025-01-03", 6, 5).unwrap();
+        db.add_hourly_notes("2025-01-02", 4, 7).unwrap();
+        db.insert_session(&session("2025-01-03T02:00:00+09:00"))
+            .unwrap();
+        db.insert_session(&session("2025-01-02T04:00:00+09:00"))
+            .unwrap();
+
+        let summary = db.delete_range("2025-01-02", "2025-01-02", 5).unwrap();
+        assert_eq!(summary.days, 1);
+        assert_eq!(summary.sessions, 1);
+
+        assert_eq!(db.get("2025-01-03").unwrap(), Some(5));
+        let hourly = db
+            .get_hourly_statistics("2025-01-01", "2025-01-31")
+            .unwrap();
+        // 2025-01-02は木曜日、2025-01-03は金曜日
+        assert_eq!(hourly[4][4], 7);
+        assert_eq!(hourly[4][5], 0);
+        assert_eq!(hourly[5][2], 0);
+        assert_eq!(hourly[5][6], 5);
+        let sessions = db.list_sessions(10, 0).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].start_time, "2025-01-02T04:00:00+09:00");
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_correct_notes_scales_hourly_by_play_date() {
+        let (db, path) = test_util::setup_test_db("edit_correct_rollover");
+        db.insert("2025-01-02", 40).unwrap();
+        db.add_hourly_notes("2025-01-02", 22, 10).unwrap();
+        db.add_hourly_notes("2025-01-03", 1, 30).unwrap();
+        db.add_hourly_notes("2025-01-03", 12, 8).unwrap();
+
+        db.correct_notes(
+            "2025-01-02",
+            20,
+            "stuck key",
+            "2025-01-05T10:00:00+09:00",
+            5,
+        )
+        .unwrap();
+
+        let hourly = db
+            .get_hourly_statistics("2025-01-01", "2025-01-31")
+            .unwrap();
+        assert_eq!(hourly[4][22], 5);
+        assert_eq!(hourly[5][1], 15);
+        // 2025-01-03のプレイはそのまま
+        assert_eq!(hourly[5][12], 8);
+
+        cleanup_test_db(path);
+    }
+}
@@ -5,6 +5,9 @@ pub enum DatabaseError {
     ConnectionError(String),
     InitializeError(String),
     QueryError(String),
+    FileError(String),
+    // 呼び出し側が指定した値が不正
+    InvalidInput(String),
 }
 
 impl std::error::Error for DatabaseError {}
@@ -15,6 +18,8 @@ impl fmt::Display for DatabaseError {
             DatabaseError::ConnectionError(msg) => write!(f, "Connection error: {}", msg),
             DatabaseError::InitializeError(msg) => write!(f, "Initialize error: {}", msg),
             DatabaseError::QueryError(msg) => write!(f, "Query error: {}", msg),
+            DatabaseError::FileError(msg) => write!(f, "File error: {}", msg),
+            DatabaseError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
         }
     }
 }
@@ -0,0 +1,324 @@
+use super::aggregate::DailyCount;
+use super::error::DatabaseError;
+use super::manager::DatabaseManager;
+use super::migration;
+use super::session::SessionRecord;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use ts_rs::TS;
+
+// エクスポート形式のバージョン。列やフィールドを変更する場合のみ上げる
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+
+pub(super) const DAILY_HEADER: &str = "date,notes_count";
+pub(super) const HOURLY_HEADER: &str = "date,hour,notes_count";
+pub(super) const SESSIONS_HEADER: &str = "start_time,end_time,notes_count,scratch_count,peak_nps,average_release_time_ms,release_count,key_counts";
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, TS, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+#[ts(export)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct HourlyCount {
+    pub date: String,
+    pub hour: u32,
+    pub notes_count: u32,
+}
+
+// JSON形式でエクスポートされる内容
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ExportData {
+    pub format_version: u32,
+    pub exported_at: String,
+    pub daily: Vec<DailyCount>,
+    pub hourly: Vec<HourlyCount>,
+    pub sessions: Vec<SessionRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ExportSummary {
+    pub days: usize,
+    pub sessions: usize,
+    pub files: Vec<String>,
+}
+
+// CSV形式では時間帯別とセッションを別ファイルにする（例: stats.csv → stats.hourly.csv）
+pub fn csv_sibling_path(path: &Path, kind: &str) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}.{}.csv", stem, kind))
+}
+
+// セッションのキーごとの回数は「ボタン:回数」をセミコロンでつなげる（例: 0:30;3:60）
+fn format_key_counts(session: &SessionRecord) -> String {
+    session
+        .key_counts
+        .iter()
+        .map(|(button, count)| format!("{}:{}", button, count))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn create_file(path: &Path) -> Result<BufWriter<File>, DatabaseError> {
+    let file = File::create(path).map_err(|e| {
+        DatabaseError::FileError(format!("Failed to create {}: {}", path.display(), e))
+    })?;
+    Ok(BufWriter::new(file))
+}
+
+fn write_lines(path: &Path, header: &str, lines: Vec<String>) -> Result<(), DatabaseError> {
+    let mut writer = create_file(path)?;
+    writeln!(writer, "{}", header)
+        .and_then(|_| {
+            lines
+                .iter()
+                .try_for_each(|line| writeln!(writer, "{}", line))
+        })
+        .and_then(|_| writer.flush())
+        .map_err(|e| DatabaseError::FileError(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+// 値はすべて日付・時刻・数値のみでカンマを含まないため、引用符で囲まない
+pub fn write_csv(data: &ExportData, path: &Path) -> Result<Vec<PathBuf>, DatabaseError> {
+    let hourly_path = csv_sibling_path(path, "hourly");
+    let sessions_path = csv_sibling_path(path, "sessions");
+
+    write_lines(
+        path,
+        DAILY_HEADER,
+        data.daily
+            .iter()
+            .map(|day| format!("{},{}", day.date, day.notes_count))
+            .collect(),
+    )?;
+    write_lines(
+        &hourly_path,
+        HOURLY_HEADER,
+        data.hourly
+            .iter()
+            .map(|hour| format!("{},{},{}", hour.date, hour.hour, hour.notes_count))
+            .collect(),
+    )?;
+    write_lines(
+        &sessions_path,
+        SESSIONS_HEADER,
+        data.sessions
+            .iter()
+            .map(|session| {
+                format!(
+                    "{},{},{},{},{},{},{},{}",
+                    session.start_time,
+                    session.end_time,
+                    session.notes_count,
+                    session.scratch_count,
+                    session.peak_nps,
+                    session.average_release_time,
+                    session.release_count,
+                    format_key_counts(session)
+                )
+            })
+            .collect(),
+    )?;
+
+    Ok(vec![path.to_path_buf(), hourly_path, sessions_path])
+}
+
+pub fn write_json(data: &ExportData, path: &Path) -> Result<(), DatabaseError> {
+    let mut writer = create_file(path)?;
+    serde_json::to_writer_pretty(&mut writer, data)
+        .map_err(|e| DatabaseError::FileError(format!("Failed to write JSON: {}", e)))?;
+    writer
+        .flush()
+        .map_err(|e| DatabaseError::FileError(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+impl DatabaseManager {
+    // すべての統計を日付順（セッションは開始時刻順）で返す
+    pub fn export_data(&self, exported_at: &str) -> Result<ExportData, DatabaseError> {
+        let daily = self.query_all(
+            "SELECT date, notes_count FROM statistics ORDER BY date ASC",
+            |row| {
+                Ok(DailyCount {
+                    date: row.get(0)?,
+                    notes_count: row.get(1)?,
+                })
+            },
+        )?;
+        // 古いバージョンのデータベースには後から追加されたテーブルがない
+        let hourly = if migration::has_table(&self.conn, "hourly_statistics")? {
+            self.query_all(
+                "SELECT date, hour, notes_count FROM hourly_statistics ORDER BY date ASC, hour ASC",
+                |row| {
+                    Ok(HourlyCount {
+                        date: row.get(0)?,
+                        hour: row.get(1)?,
+                        notes_count: row.get(2)?,
+                    })
+                },
+            )?
+        } else {
+            Vec::new()
+        };
+
+        let sessions = if migration::has_table(&self.conn, "sessions")? {
+            let mut sessions = self.list_sessions(u32::MAX, 0)?;
+            sessions.reverse();
+            sessions
+        } else {
+            Vec::new()
+        };
+
+        Ok(ExportData {
+            format_version: EXPORT_FORMAT_VERSION,
+            exported_at: exported_at.to_string(),
+            daily,
+            hourly,
+            sessions,
+        })
+    }
+
+    pub fn export_to_file(
+        &self,
+        path: &Path,
+        format: ExportFormat,
+        exported_at: &str,
+    ) -> Result<ExportSummary, DatabaseError> {
+        let data = self.export_data(exported_at)?;
+        let files = match format {
+            ExportFormat::Csv => write_csv(&data, path)?,
+            ExportFormat::Json => {
+                write_json(&data, path)?;
+                vec![path.to_path_buf()]
+            }
+        };
+
+        Ok(ExportSummary {
+            days: data.daily.len(),
+            sessions: data.sessions.len(),
+            files: files
+                .iter()
+                .map(|file| file.to_string_lossy().into_owned())
+                .collect(),
+        })
+    }
+
+    pub(super) fn query_all<T>(
+        &self,
+        sql: &str,
+        f: impl FnMut(&rusqlite::Row) -> rusqlite::Result<T>,
+    ) -> Result<Vec<T>, DatabaseError> {
+        let mut stmt = self
+            .conn
+            .prepare(sql)
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to prepare: {}", e)))?;
+
+        let rows = stmt
+            .query_map([], f)
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            results.push(
+                row.map_err(|e| DatabaseError::QueryError(format!("Failed to get row: {}", e)))?,
+            );
+        }
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_util::{self, cleanup_test_db};
+    use std::collections::BTreeMap;
+    use std::fs;
+
+    fn setup_test_db(test_name: &str) -> (DatabaseManager, PathBuf) {
+        let (db, test_db_path) = test_util::setup_test_db(&format!("export_{}", test_name));
+
+        db.insert("2025-01-02", 200).unwrap();
+        db.insert("2025-01-01", 100).unwrap();
+        db.add_hourly_notes("2025-01-01", 21, 100).unwrap();
+        db.insert_session(&SessionRecord {
+            id: 0,
+            start_time: "2025-01-01T21:00:00+09:00".to_string(),
+            end_time: "2025-01-01T21:30:00+09:00".to_string(),
+            notes_count: 100,
+            scratch_count: 10,
+            peak_nps: 12,
+            average_release_time: 45.5,
+            release_count: 90,
+            key_counts: BTreeMap::from([(0, 30), (3, 60)]),
+        })
+        .unwrap();
+
+        (db, test_db_path)
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let (db, path) = setup_test_db("csv");
+        let csv_path = PathBuf::from("test_export_csv.csv");
+
+        let summary = db
+            .export_to_file(&csv_path, ExportFormat::Csv, "2025-01-03T00:00:00+09:00")
+            .unwrap();
+        assert_eq!(summary.days, 2);
+        assert_eq!(summary.sessions, 1);
+        assert_eq!(summary.files.len(), 3);
+
+        assert_eq!(
+            fs::read_to_string(&csv_path).unwrap(),
+            "date,notes_count\n2025-01-01,100\n2025-01-02,200\n"
+        );
+        assert_eq!(
+            fs::read_to_string(csv_sibling_path(&csv_path, "hourly")).unwrap(),
+            "date,hour,notes_count\n2025-01-01,21,100\n"
+        );
+        let sessions = fs::read_to_string(csv_sibling_path(&csv_path, "sessions")).unwrap();
+        assert_eq!(
+            sessions.lines().nth(1),
+            Some("2025-01-01T21:00:00+09:00,2025-01-01T21:30:00+09:00,100,10,12,45.5,90,0:30;3:60")
+        );
+
+        for file in summary.files {
+            cleanup_test_db(PathBuf::from(file));
+        }
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_export_json() {
+        let (db, path) = setup_test_db("json");
+        let json_path = PathBuf::from("test_export_json.json");
+
+        db.export_to_file(&json_path, ExportFormat::Json, "2025-01-03T00:00:00+09:00")
+            .unwrap();
+
+        let data: ExportData =
+            serde_json::from_str(&fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(data, db.export_data("2025-01-03T00:00:00+09:00").unwrap());
+        assert_eq!(data.format_version, EXPORT_FORMAT_VERSION);
+        assert_eq!(data.daily[0].date, "2025-01-01");
+        assert_eq!(data.sessions[0].key_counts.get(&3), Some(&60));
+
+        cleanup_test_db(json_path);
+        cleanup_test_db(path);
+    }
+}
@@ -0,0 +1,405 @@
+use super::error::DatabaseError;
+use super::manager::DatabaseManager;
+use chrono::{Datelike, NaiveDate, TimeDelta};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::iter;
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, TS)]
+#[serde(rename_all = "lowercase")]
+#[ts(export)]
+pub enum GoalKind {
+    Daily,
+    Weekly,
+}
+
+impl GoalKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            GoalKind::Daily => "daily",
+            GoalKind::Weekly => "weekly",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Goals {
+    pub daily: Option<u32>,
+    pub weekly: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Streaks {
+    pub current: u32,
+    pub longest: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct GoalProgress {
+    // 日なら日付、週なら月曜日の日付
+    pub period: String,
+    #[ts(type = "number")]
+    pub notes_count: i64,
+    pub goal: Option<u32>,
+    pub completed: bool,
+}
+
+// 達成状況を取得できる期間の上限（約10年）
+const MAX_PROGRESS_DAYS: i64 = 3660;
+
+fn check_span(start: NaiveDate, end: NaiveDate) -> Result<(), DatabaseError> {
+    if (end - start).num_days() >= MAX_PROGRESS_DAYS {
+        return Err(DatabaseError::InvalidInput(format!(
+            "Range must be at most {} days",
+            MAX_PROGRESS_DAYS
+        )));
+    }
+    Ok(())
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, DatabaseError> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|e| DatabaseError::QueryError(format!("Invalid date {}: {}", date, e)))
+}
+
+impl DatabaseManager {
+    // notes_countがNoneの場合は目標を削除する
+    pub fn set_goal(&self, kind: GoalKind, notes_count: Option<u32>) -> Result<(), DatabaseError> {
+        match notes_count {
+            Some(notes_count) => self.conn.execute(
+                "INSERT INTO goals (kind, notes_count) VALUES (?1, ?2)
+                 ON CONFLICT(kind) DO UPDATE SET notes_count = excluded.notes_count",
+                params![kind.as_str(), notes_count],
+            ),
+            None => self
+                .conn
+                .execute("DELETE FROM goals WHERE kind = ?1", [kind.as_str()]),
+        }
+        .map_err(|e| DatabaseError::QueryError(format!("Failed to set goal: {}", e)))?;
+        Ok(())
+    }
+
+    pub fn get_goals(&self) -> Result<Goals, DatabaseError> {
+        let get_goal = |kind: GoalKind| {
+            self.conn
+                .query_row(
+                    "SELECT notes_count FROM goals WHERE kind = ?1",
+                    [kind.as_str()],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))
+        };
+
+        Ok(Goals {
+            daily: get_goal(GoalKind::Daily)?,
+            weekly: get_goal(GoalKind::Weekly)?,
+        })
+    }
+
+    // 日の目標を達成した日（目標がなければ1ノーツ以上叩いた日）の連続日数。
+    // 今日がまだ未達成でも昨日まで続いていれば現在のストリークとして数える
+    pub fn get_streaks(&self, today: &str) -> Result<Streaks, DatabaseError> {
+        let today = parse_date(today)?;
+        let threshold = self.get_goals()?.daily.unwrap_or(1).max(1);
+
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT date FROM statistics WHERE notes_count >= ?1 AND date <= ?2 ORDER BY date ASC",
+            )
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to prepare: {}", e)))?;
+        let rows = stmt
+            .query_map(
+                params![threshold, today.format("%Y-%m-%d").to_string()],
+                |row| row.get::<_, String>(0),
+            )
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+
+        let mut longest = 0;
+        let mut streak = 0;
+        let mut last_date: Option<NaiveDate> = None;
+        for row in rows {
+            let date =
+                parse_date(&row.map_err(|e| {
+                    DatabaseError::QueryError(format!("Failed to get row: {}", e))
+                })?)?;
+            streak = match last_date {
+                Some(last) if date - last == TimeDelta::days(1) => streak + 1,
+                _ => 1,
+            };
+            longest = longest.max(streak);
+            last_date = Some(date);
+        }
+
+        let current = match last_date {
+            Some(last) if today - last <= TimeDelta::days(1) => streak,
+            _ => 0,
+        };
+
+        Ok(Streaks { current, longest })
+    }
+
+    // start_dateからend_dateまでの各日の目標達成状況
+    pub fn get_daily_goal_progress(
+        &self,
+        start_date: &str,
+        end_date: &str,
+    ) -> Result<Vec<GoalProgress>, DatabaseError> {
+        let goal = self.get_goals()?.daily;
+        let (start, end) = (parse_date(start_date)?, parse_date(end_date)?);
+        check_span(start, end)?;
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT date, notes_count FROM statistics WHERE date BETWEEN ?1 AND ?2")
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to prepare: {}", e)))?;
+        let rows = stmt
+            .query_map([start_date, end_date], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+        let mut counts = HashMap::new();
+        for row in rows {
+            let (date, notes_count) =
+                row.map_err(|e| DatabaseError::QueryError(format!("Failed to get row: {}", e)))?;
+            counts.insert(date, notes_count);
+        }
+
+        // 記録のない日は0として埋める
+        let results = iter::successors(Some(start), |date| date.succ_opt())
+            .take_while(|date| *date <= end)
+            .map(|date| {
+                let period = date.format("%Y-%m-%d").to_string();
+                let notes_count = counts.get(&period).copied().unwrap_or(0);
+                GoalProgress {
+                    period,
+                    notes_count,
+                    goal,
+                    completed: goal.is_some_and(|goal| notes_count >= goal as i64),
+                }
+            })
+            .collect();
+
+        Ok(results)
+    }
+
+    // start_dateからend_dateまでに含まれる各週（月曜日始まり）の目標達成状況
+    pub fn get_weekly_goal_progress(
+        &self,
+        start_date: &str,
+        end_date: &str,
+    ) -> Result<Vec<GoalProgress>, DatabaseError> {
+        let goal = self.get_goals()?.weekly;
+        let start = parse_date(start_date)?;
+        let end = parse_date(end_date)?;
+        check_span(start, end)?;
+
+        let mut results = Vec::new();
+        let mut week_start = start
+            .checked_sub_signed(TimeDelta::days(
+                start.weekday().num_days_from_monday() as i64
+            ))
+            .unwrap_or(NaiveDate::MIN);
+        while week_start <= end {
+            let week_end = week_start
+                .checked_add_signed(TimeDelta::days(6))
+                .unwrap_or(NaiveDate::MAX);
+            let notes_count: i64 = self
+                .conn
+                .query_row(
+                    "SELECT COALESCE(SUM(notes_count), 0) FROM statistics WHERE date BETWEEN ?1 AND ?2",
+                    [
+                        week_start.format("%Y-%m-%d").to_string(),
+                        week_end.format("%Y-%m-%d").to_string(),
+                    ],
+                    |row| row.get(0),
+                )
+                .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+
+            results.push(GoalProgress {
+                period: week_start.format("%Y-%m-%d").to_string(),
+                notes_count,
+                goal,
+                completed: goal.is_some_and(|goal| notes_count >= goal as i64),
+            });
+            match week_start.checked_add_signed(TimeDelta::days(7)) {
+                Some(next) => week_start = next,
+                None => break,
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_util::{cleanup_test_db, setup_test_db};
+
+    #[test]
+    fn test_set_and_get_goals() {
+        let (db, path) = setup_test_db("goal_set_and_get");
+
+        assert_eq!(db.get_goals().unwrap(), Goals::default());
+
+        db.set_goal(GoalKind::Daily, Some(1000)).unwrap();
+        db.set_goal(GoalKind::Weekly, Some(5000)).unwrap();
+        db.set_goal(GoalKind::Daily, Some(2000)).unwrap();
+        assert_eq!(
+            db.get_goals().unwrap(),
+            Goals {
+                daily: Some(2000),
+                weekly: Some(5000),
+            }
+        );
+
+        db.set_goal(GoalKind::Weekly, None).unwrap();
+        assert_eq!(db.get_goals().unwrap().weekly, None);
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_streaks_without_goal() {
+        let (db, path) = setup_test_db("goal_streaks_without_goal");
+
+        db.insert("2025-01-01", 10).unwrap();
+        db.insert("2025-01-02", 10).unwrap();
+        db.insert("2025-01-03", 10).unwrap();
+        db.insert("2025-01-05", 10).unwrap();
+        db.insert("2025-01-06", 10).unwrap();
+
+        // 今日(01-07)は未プレイでも昨日まで続いていれば継続中
+        let streaks = db.get_streaks("2025-01-07").unwrap();
+        assert_eq!(
+            streaks,
+            Streaks {
+                current: 2,
+                longest: 3
+            }
+        );
+
+        let streaks = db.get_streaks("2025-01-08").unwrap();
+        assert_eq!(
+            streaks,
+            Streaks {
+                current: 0,
+                longest: 3
+            }
+        );
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_streaks_with_daily_goal() {
+        let (db, path) = setup_test_db("goal_streaks_with_goal");
+
+        db.set_goal(GoalKind::Daily, Some(100)).unwrap();
+        db.insert("2025-01-01", 100).unwrap();
+        db.insert("2025-01-02", 99).unwrap();
+        db.insert("2025-01-03", 150).unwrap();
+        db.insert("2025-01-04", 200).unwrap();
+
+        let streaks = db.get_streaks("2025-01-04").unwrap();
+        assert_eq!(
+            streaks,
+            Streaks {
+                current: 2,
+                longest: 2
+            }
+        );
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_daily_goal_progress() {
+        let (db, path) = setup_test_db("goal_daily_progress");
+
+        db.set_goal(GoalKind::Daily, Some(100)).unwrap();
+        db.insert("2025-01-01", 100).unwrap();
+        db.insert("2025-01-03", 50).unwrap();
+
+        let progress = db
+            .get_daily_goal_progress("2025-01-01", "2025-01-03")
+            .unwrap();
+        let completed: Vec<(&str, i64, bool)> = progress
+            .iter()
+            .map(|p| (p.period.as_str(), p.notes_count, p.completed))
+            .collect();
+        assert_eq!(
+            completed,
+            vec![
+                ("2025-01-01", 100, true),
+                ("2025-01-02", 0, false),
+                ("2025-01-03", 50, false),
+            ]
+        );
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_weekly_goal_progress() {
+        let (db, path) = setup_test_db("goal_weekly_progress");
+
+        db.set_goal(GoalKind::Weekly, Some(300)).unwrap();
+        // 2025-01-06は月曜日
+        db.insert("2025-01-05", 500).unwrap();
+        db.insert("2025-01-06", 200).unwrap();
+        db.insert("2025-01-12", 100).unwrap();
+        db.insert("2025-01-13", 100).unwrap();
+
+        let progress = db
+            .get_weekly_goal_progress("2025-01-06", "2025-01-19")
+            .unwrap();
+        assert_eq!(progress.len(), 2);
+        assert_eq!(progress[0].period, "2025-01-06");
+        assert_eq!(progress[0].notes_count, 300);
+        assert!(progress[0].completed);
+        assert_eq!(progress[1].notes_count, 100);
+        assert!(!progress[1].completed);
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_goal_progress_range_is_limited() {
+        let (db, path) = setup_test_db("goal_progress_limit");
+
+        let start = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let last_allowed = start + TimeDelta::days(MAX_PROGRESS_DAYS - 1);
+        let format = |date: NaiveDate| date.format("%Y-%m-%d").to_string();
+
+        let progress = db
+            .get_daily_goal_progress(&format(start), &format(last_allowed))
+            .unwrap();
+        assert_eq!(progress.len(), MAX_PROGRESS_DAYS as usize);
+
+        let too_long = last_allowed + TimeDelta::days(1);
+        for result in [
+            db.get_daily_goal_progress(&format(start), &format(too_long)),
+            db.get_weekly_goal_progress(&format(start), &format(too_long)),
+        ] {
+            assert!(matches!(result, Err(DatabaseError::InvalidInput(_))));
+        }
+
+        // 日付の上限でもパニックしない
+        let max = format(NaiveDate::MAX);
+        assert_eq!(db.get_daily_goal_progress(&max, &max).unwrap().len(), 1);
+        assert_eq!(db.get_weekly_goal_progress(&max, &max).unwrap().len(), 1);
+
+        cleanup_test_db(path);
+    }
+}
@@ -0,0 +1,101 @@
+use super::error::DatabaseError;
+use super::manager::DatabaseManager;
+
+// 曜日(0=日曜日 ... 6=土曜日) × 時(0-23)のノーツ数
+pub type HourlyMatrix = [[u32; 24]; 7];
+
+impl DatabaseManager {
+    #[cfg(test)]
+    pub fn add_hourly_notes(&self, date: &str, hour: u32, delta: u32) -> Result<(), DatabaseError> {
+        self.conn
+            .execute(
+                "INSERT INTO hourly_statistics (date, hour, notes_count) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(date, hour) DO UPDATE SET notes_count = notes_count + excluded.notes_count",
+                rusqlite::params![date, hour, delta],
+            )
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to insert: {}", e)))?;
+        Ok(())
+    }
+
+    // start_dateからend_dateまで（両端を含む）を曜日×時間帯で集計する
+    pub fn get_hourly_statistics(
+        &self,
+        start_date: &str,
+        end_date: &str,
+    ) -> Result<HourlyMatrix, DatabaseError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT CAST(strftime('%w', date) AS INTEGER), hour, SUM(notes_count)
+                 FROM hourly_statistics
+                 WHERE date BETWEEN ?1 AND ?2
+                 GROUP BY 1, 2",
+            )
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to prepare: {}", e)))?;
+
+        let rows = stmt
+            .query_map([start_date, end_date], |row| {
+                Ok((
+                    row.get::<_, usize>(0)?,
+                    row.get::<_, usize>(1)?,
+                    row.get(2)?,
+                ))
+            })
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+
+        let mut matrix = [[0; 24]; 7];
+        for row in rows {
+            let (weekday, hour, count) =
+                row.map_err(|e| DatabaseError::QueryError(format!("Failed to get row: {}", e)))?;
+            if weekday < 7 && hour < 24 {
+                matrix[weekday][hour] = count;
+            }
+        }
+
+        Ok(matrix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::test_util::{cleanup_test_db, setup_test_db};
+
+    #[test]
+    fn test_add_hourly_notes_accumulates() {
+        let (db, path) = setup_test_db("hourly_accumulate");
+
+        db.add_hourly_notes("2025-01-01", 21, 100).unwrap();
+        db.add_hourly_notes("2025-01-01", 21, 50).unwrap();
+        db.add_hourly_notes("2025-01-01", 22, 10).unwrap();
+
+        // 2025-01-01は水曜日
+        let matrix = db
+            .get_hourly_statistics("2025-01-01", "2025-01-01")
+            .unwrap();
+        assert_eq!(matrix[3][21], 150);
+        assert_eq!(matrix[3][22], 10);
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_get_hourly_statistics() {
+        let (db, path) = setup_test_db("hourly_matrix");
+
+        // 2025-01-05は日曜日、2025-01-06は月曜日
+        db.add_hourly_notes("2025-01-05", 21, 100).unwrap();
+        db.add_hourly_notes("2025-01-06", 8, 30).unwrap();
+        db.add_hourly_notes("2025-01-13", 8, 20).unwrap();
+        // 範囲外
+        db.add_hourly_notes("2025-02-01", 8, 999).unwrap();
+
+        let matrix = db
+            .get_hourly_statistics("2025-01-01", "2025-01-31")
+            .unwrap();
+        assert_eq!(matrix[0][21], 100);
+        assert_eq!(matrix[1][8], 50);
+        assert_eq!(matrix.iter().flatten().sum::<u32>(), 150);
+
+        cleanup_test_db(path);
+    }
+}
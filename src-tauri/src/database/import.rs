@@ -0,0 +1,609 @@
+use super::aggregate::DailyCount;
+use super::error::DatabaseError;
+use super::export::{
+    csv_sibling_path, ExportData, HourlyCount, DAILY_HEADER, EXPORT_FORMAT_VERSION, HOURLY_HEADER,
+    SESSIONS_HEADER,
+};
+use super::manager::DatabaseManager;
+use super::session::{insert_session_rows, SessionRecord};
+use chrono::{DateTime, NaiveDate};
+use clap::ValueEnum;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+use ts_rs::TS;
+
+// 同じ日付（時間帯）のデータが既にある場合の扱い
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, TS, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+#[ts(export)]
+pub enum MergeStrategy {
+    Sum,
+    Max,
+    Overwrite,
+}
+
+impl MergeStrategy {
+    fn merge(&self, current: Option<i64>, incoming: i64) -> i64 {
+        match (self, current) {
+            (_, None) | (MergeStrategy::Overwrite, _) => incoming,
+            (MergeStrategy::Sum, Some(current)) => current + incoming,
+            (MergeStrategy::Max, Some(current)) => current.max(incoming),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct DayChange {
+    pub date: String,
+    #[ts(type = "number | null")]
+    pub before: Option<i64>,
+    #[ts(type = "number")]
+    pub after: i64,
+}
+
+// 取り込みで変わる内容。dry runの場合はappliedがfalseで、データベースは変更されない
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ImportPreview {
+    pub days: Vec<DayChange>,
+    pub hourly_changes: usize,
+    pub new_sessions: usize,
+    pub applied: bool,
+}
+
+#[derive(Debug, Default)]
+struct ImportPlan {
+    days: Vec<DayChange>,
+    hourly: Vec<(String, u32, i64)>,
+    sessions: Vec<SessionRecord>,
+}
+
+// locationはCSVなら"line 3"、JSONなら"hourly[2]"のような位置
+fn invalid_entry(path: &Path, location: &str, message: &str) -> DatabaseError {
+    DatabaseError::FileError(format!(
+        "Invalid {} in {}: {}",
+        location,
+        path.display(),
+        message
+    ))
+}
+
+fn invalid_line(path: &Path, line_number: usize, message: &str) -> DatabaseError {
+    invalid_entry(path, &format!("line {}", line_number), message)
+}
+
+fn parse_field<T: std::str::FromStr>(
+    value: &str,
+    path: &Path,
+    line_number: usize,
+) -> Result<T, DatabaseError> {
+    value
+        .parse()
+        .map_err(|_| invalid_line(path, line_number, &format!("invalid value {}", value)))
+}
+
+fn parse_date(value: &str, path: &Path, line_number: usize) -> Result<String, DatabaseError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .map_err(|_| invalid_line(path, line_number, &format!("invalid date {}", value)))
+}
+
+fn validate_date(value: &str) -> Result<(), String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|_| ())
+        .map_err(|_| format!("invalid date {}", value))
+}
+
+fn validate_time(value: &str) -> Result<(), String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|_| ())
+        .map_err(|_| format!("invalid time {}", value))
+}
+
+// CSVとJSONで同じ検証をする
+fn validate_daily(day: &DailyCount) -> Result<(), String> {
+    validate_date(&day.date)?;
+    if day.notes_count < 0 {
+        return Err(format!("negative notes_count {}", day.notes_count));
+    }
+    Ok(())
+}
+
+fn validate_hourly(hour: &HourlyCount) -> Result<(), String> {
+    validate_date(&hour.date)?;
+    if hour.hour > 23 {
+        return Err("hour out of range".to_string());
+    }
+    Ok(())
+}
+
+fn validate_session(session: &SessionRecord) -> Result<(), String> {
+    validate_time(&session.start_time)?;
+    validate_time(&session.end_time)?;
+    if !session.average_release_time.is_finite() || session.average_release_time < 0.0 {
+        return Err(format!(
+            "invalid average_release_time {}",
+            session.average_release_time
+        ));
+    }
+    Ok(())
+}
+
+// ヘッダーを確認し、データ行を(行番号, 列)の形で返す
+fn read_csv(path: &Path, header: &str) -> Result<Vec<(usize, Vec<String>)>, DatabaseError> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        DatabaseError::FileError(format!("Failed to read {}: {}", path.display(), e))
+    })?;
+    let mut lines = content.lines().enumerate();
+
+    match lines.next() {
+        Some((_, first)) if first.trim_start_matches('\u{feff}').trim() == header => {}
+        _ => {
+            return Err(invalid_line(
+                path,
+                1,
+                &format!("expected header {}", header),
+            ))
+        }
+    }
+
+    let column_count = header.split(',').count();
+    let mut rows = Vec::new();
+    for (index, line) in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let columns: Vec<String> = line.split(',').map(|c| c.trim().to_string()).collect();
+        if columns.len() != column_count {
+            return Err(invalid_line(path, index + 1, "wrong number of columns"));
+        }
+        rows.push((index + 1, columns));
+    }
+    Ok(rows)
+}
+
+fn parse_key_counts(
+    value: &str,
+    path: &Path,
+    line_number: usize,
+) -> Result<BTreeMap<u32, u32>, DatabaseError> {
+    let mut key_counts = BTreeMap::new();
+    for pair in value.split(';').filter(|pair| !pair.is_empty()) {
+        let (button, count) = pair
+            .split_once(':')
+            .ok_or_else(|| invalid_line(path, line_number, "invalid key_counts"))?;
+        key_counts.insert(
+            parse_field(button, path, line_number)?,
+            parse_field(count, path, line_number)?,
+        );
+    }
+    Ok(key_counts)
+}
+
+// エクスポートしたCSVを読み込む。時間帯別とセッションのファイルはあれば読み込む
+pub fn read_csv_export(path: &Path) -> Result<ExportData, DatabaseError> {
+    let mut daily = Vec::new();
+    for (line_number, columns) in read_csv(path, DAILY_HEADER)? {
+        let day = DailyCount {
+            date: parse_date(&columns[0], path, line_number)?,
+            notes_count: parse_field(&columns[1], path, line_number)?,
+        };
+        validate_daily(&day).map_err(|message| invalid_line(path, line_number, &message))?;
+        daily.push(day);
+    }
+
+    let mut hourly = Vec::new();
+    let hourly_path = csv_sibling_path(path, "hourly");
+    if hourly_path.exists() {
+        for (line_number, columns) in read_csv(&hourly_path, HOURLY_HEADER)? {
+            let hour = HourlyCount {
+                date: parse_date(&columns[0], &hourly_path, line_number)?,
+                hour: parse_field(&columns[1], &hourly_path, line_number)?,
+                notes_count: parse_field(&columns[2], &hourly_path, line_number)?,
+            };
+            validate_hourly(&hour)
+                .map_err(|message| invalid_line(&hourly_path, line_number, &message))?;
+            hourly.push(hour);
+        }
+    }
+
+    let mut sessions = Vec::new();
+    let sessions_path = csv_sibling_path(path, "sessions");
+    if sessions_path.exists() {
+        for (line_number, columns) in read_csv(&sessions_path, SESSIONS_HEADER)? {
+            let field = |index: usize| parse_field(&columns[index], &sessions_path, line_number);
+            let session = SessionRecord {
+                id: 0,
+                start_time: columns[0].clone(),
+                end_time: columns[1].clone(),
+                notes_count: field(2)?,
+                scratch_count: field(3)?,
+                peak_nps: field(4)?,
+                average_release_time: parse_field(&columns[5], &sessions_path, line_number)?,
+                release_count: field(6)?,
+                key_counts: parse_key_counts(&columns[7], &sessions_path, line_number)?,
+            };
+            validate_session(&session)
+                .map_err(|message| invalid_line(&sessions_path, line_number, &message))?;
+            sessions.push(session);
+        }
+    }
+
+    Ok(ExportData {
+        format_version: EXPORT_FORMAT_VERSION,
+        exported_at: String::new(),
+        daily,
+        hourly,
+        sessions,
+    })
+}
+
+pub fn read_json_export(path: &Path) -> Result<ExportData, DatabaseError> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        DatabaseError::FileError(format!("Failed to read {}: {}", path.display(), e))
+    })?;
+    let data: ExportData = serde_json::from_str(&content)
+        .map_err(|e| DatabaseError::FileError(format!("Invalid JSON: {}", e)))?;
+    if data.format_version > EXPORT_FORMAT_VERSION {
+        return Err(DatabaseError::FileError(format!(
+            "Unsupported format version {}",
+            data.format_version
+        )));
+    }
+    // JSONには行番号がないので配列の位置で報告する
+    let at = |name: &str, index: usize, message: String| {
+        invalid_entry(path, &format!("{}[{}]", name, index), &message)
+    };
+    for (index, day) in data.daily.iter().enumerate() {
+        validate_daily(day).map_err(|message| at("daily", index, message))?;
+    }
+    for (index, hour) in data.hourly.iter().enumerate() {
+        validate_hourly(hour).map_err(|message| at("hourly", index, message))?;
+    }
+    for (index, session) in data.sessions.iter().enumerate() {
+        validate_session(session).map_err(|message| at("sessions", index, message))?;
+    }
+    Ok(data)
+}
+
+// 拡張子で形式を判定する（.csv, .json, それ以外はSQLiteのデータベース）
+pub fn read_import_source(path: &Path) -> Result<ExportData, DatabaseError> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("csv") => read_csv_export(path),
+        Some("json") => read_json_export(path),
+        _ => DatabaseManager::open_read_only(path.to_path_buf())?.export_data(""),
+    }
+}
+
+impl DatabaseManager {
+    // 取り込み元のデータをstrategyで統合する。dry_runの場合は変更内容だけを返す
+    pub fn import_data(
+        &self,
+        data: &ExportData,
+        strategy: MergeStrategy,
+        dry_run: bool,
+    ) -> Result<ImportPreview, DatabaseError> {
+        let plan = self.plan_import(data, strategy)?;
+        let preview = ImportPreview {
+            days: plan.days.clone(),
+            hourly_changes: plan.hourly.len(),
+            new_sessions: plan.sessions.len(),
+            applied: false,
+        };
+        if dry_run {
+            return Ok(preview);
+        }
+
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to begin: {}", e)))?;
+
+        for day in &plan.days {
+            tx.execute(
+                "INSERT INTO statistics (date, notes_count) VALUES (?1, ?2)
+                 ON CONFLICT(date) DO UPDATE SET notes_count = excluded.notes_count",
+                params![day.date, day.after],
+            )
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to import: {}", e)))?;
+        }
+        for (date, hour, notes_count) in &plan.hourly {
+            tx.execute(
+                "INSERT INTO hourly_statistics (date, hour, notes_count) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(date, hour) DO UPDATE SET notes_count = excluded.notes_count",
+                params![date, hour, notes_count],
+            )
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to import: {}", e)))?;
+        }
+        for session in &plan.sessions {
+            insert_session_rows(&tx, session)?;
+        }
+
+        tx.commit()
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to commit: {}", e)))?;
+        Ok(ImportPreview {
+            applied: true,
+            ..preview
+        })
+    }
+
+    fn plan_import(
+        &self,
+        data: &ExportData,
+        strategy: MergeStrategy,
+    ) -> Result<ImportPlan, DatabaseError> {
+        let mut plan = ImportPlan::default();
+
+        // 同じファイル内の重複は先に合算しておく
+        let mut daily: BTreeMap<&str, i64> = BTreeMap::new();
+        for day in &data.daily {
+            *daily.entry(day.date.as_str()).or_insert(0) += day.notes_count;
+        }
+        for (date, incoming) in daily {
+            let before: Option<i64> = self
+                .conn
+                .query_row(
+                    "SELECT notes_count FROM statistics WHERE date = ?1",
+                    [date],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+            let after = strategy.merge(before, incoming);
+            if before != Some(after) {
+                plan.days.push(DayChange {
+                    date: date.to_string(),
+                    before,
+                    after,
+                });
+            }
+        }
+
+        let mut hourly: BTreeMap<(&str, u32), i64> = BTreeMap::new();
+        for hour in &data.hourly {
+            *hourly.entry((hour.date.as_str(), hour.hour)).or_insert(0) += hour.notes_count as i64;
+        }
+        for ((date, hour), incoming) in hourly {
+            let before: Option<i64> = self
+                .conn
+                .query_row(
+                    "SELECT notes_count FROM hourly_statistics WHERE date = ?1 AND hour = ?2",
+                    params![date, hour],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+            let after = strategy.merge(before, incoming);
+            if before != Some(after) {
+                plan.hourly.push((date.to_string(), hour, after));
+            }
+        }
+
+        // セッションは開始時刻が同じものを同一とみなし、未登録のものだけを追加する
+        let mut known: HashSet<String> = self
+            .query_all("SELECT start_time FROM sessions", |row| row.get(0))?
+            .into_iter()
+            .collect();
+        for session in &data.sessions {
+            if known.insert(session.start_time.clone()) {
+                plan.sessions.push(session.clone());
+            }
+        }
+
+        Ok(plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_util::{cleanup_test_db, setup_test_db};
+    use crate::database::ExportFormat;
+    use std::path::PathBuf;
+
+    fn sample_data() -> ExportData {
+        ExportData {
+            format_version: EXPORT_FORMAT_VERSION,
+            exported_at: String::new(),
+            daily: vec![
+                DailyCount {
+                    date: "2025-01-01".to_string(),
+                    notes_count: 50,
+                },
+                DailyCount {
+                    date: "2025-01-02".to_string(),
+                    notes_count: 300,
+                },
+            ],
+            hourly: vec![HourlyCount {
+                date: "2025-01-01".to_string(),
+                hour: 20,
+                notes_count: 50,
+            }],
+            sessions: vec![SessionRecord {
+                id: 0,
+                start_time: "2025-01-01T20:00:00+09:00".to_string(),
+                end_time: "2025-01-01T20:30:00+09:00".to_string(),
+                notes_count: 50,
+                scratch_count: 0,
+                peak_nps: 8,
+                average_release_time: 40.0,
+                release_count: 0,
+                key_counts: BTreeMap::from([(1, 50)]),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_merge_strategies() {
+        for (strategy, expected) in [
+            (MergeStrategy::Sum, 150),
+            (MergeStrategy::Max, 100),
+            (MergeStrategy::Overwrite, 50),
+        ] {
+            let (db, path) = setup_test_db(&format!("import_{:?}", strategy));
+            db.insert("2025-01-01", 100).unwrap();
+
+            db.import_data(&sample_data(), strategy, false).unwrap();
+
+            assert_eq!(db.get("2025-01-01").unwrap(), Some(expected));
+            assert_eq!(db.get("2025-01-02").unwrap(), Some(300));
+
+            cleanup_test_db(path);
+        }
+    }
+
+    #[test]
+    fn test_dry_run_does_not_change_database() {
+        let (db, path) = setup_test_db("import_dry_run");
+        db.insert("2025-01-01", 100).unwrap();
+
+        let preview = db
+            .import_data(&sample_data(), MergeStrategy::Max, true)
+            .unwrap();
+        assert!(!preview.applied);
+        // Maxでは2025-01-01は変わらない
+        assert_eq!(
+            preview.days,
+            vec![DayChange {
+                date: "2025-01-02".to_string(),
+                before: None,
+                after: 300,
+            }]
+        );
+        assert_eq!(preview.hourly_changes, 1);
+        assert_eq!(preview.new_sessions, 1);
+        assert_eq!(db.get("2025-01-02").unwrap(), None);
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_sessions_are_not_imported_twice() {
+        let (db, path) = setup_test_db("import_sessions");
+
+        db.import_data(&sample_data(), MergeStrategy::Sum, false)
+            .unwrap();
+        let preview = db
+            .import_data(&sample_data(), MergeStrategy::Sum, false)
+            .unwrap();
+
+        assert_eq!(preview.new_sessions, 0);
+        assert_eq!(db.list_sessions(10, 0).unwrap().len(), 1);
+        assert_eq!(db.get("2025-01-01").unwrap(), Some(100));
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_import_from_exported_files_and_database() {
+        let (source, source_path) = setup_test_db("import_source");
+        source
+            .import_data(&sample_data(), MergeStrategy::Sum, false)
+            .unwrap();
+        let csv_path = PathBuf::from("test_import_source.csv");
+        let json_path = PathBuf::from("test_import_source.json");
+        let csv_files = source
+            .export_to_file(&csv_path, ExportFormat::Csv, "")
+            .unwrap()
+            .files;
+        source
+            .export_to_file(&json_path, ExportFormat::Json, "")
+            .unwrap();
+
+        for import_path in [&csv_path, &json_path, &source_path] {
+            let data = read_import_source(import_path).unwrap();
+            assert_eq!(data.daily, sample_data().daily);
+            assert_eq!(data.hourly, sample_data().hourly);
+            assert_eq!(
+                data.sessions[0].key_counts,
+                sample_data().sessions[0].key_counts
+            );
+        }
+
+        for file in csv_files {
+            cleanup_test_db(PathBuf::from(file));
+        }
+        cleanup_test_db(json_path);
+        drop(source);
+        cleanup_test_db(source_path);
+    }
+
+    #[test]
+    fn test_invalid_csv_is_rejected() {
+        let csv_path = PathBuf::from("test_import_invalid.csv");
+        fs::write(&csv_path, "date,notes_count\n2025-13-01,100\n").unwrap();
+
+        assert!(read_import_source(&csv_path).is_err());
+
+        cleanup_test_db(csv_path);
+    }
+
+    #[test]
+    fn test_negative_counts_and_invalid_times_are_rejected_in_csv() {
+        let csv_path = PathBuf::from("test_import_negative.csv");
+        fs::write(&csv_path, "date,notes_count\n2025-01-01,-5\n").unwrap();
+        let error = read_import_source(&csv_path).unwrap_err().to_string();
+        assert!(error.contains("line 2"), "{}", error);
+
+        fs::write(&csv_path, "date,notes_count\n2025-01-01,5\n").unwrap();
+        let sessions_path = csv_sibling_path(&csv_path, "sessions");
+        fs::write(
+            &sessions_path,
+            format!(
+                "{}\nyesterday,2025-01-01T20:30:00+09:00,5,0,1,40,0,\n",
+                SESSIONS_HEADER
+            ),
+        )
+        .unwrap();
+        assert!(read_import_source(&csv_path).is_err());
+
+        cleanup_test_db(sessions_path);
+        cleanup_test_db(csv_path);
+    }
+
+    #[test]
+    fn test_invalid_json_entries_report_array_index() {
+        type Corrupt = fn(&mut ExportData);
+        let json_path = PathBuf::from("test_import_invalid.json");
+        let cases: [(Corrupt, &str); 5] = [
+            (|data| data.daily[1].notes_count = -1, "daily[1]"),
+            (
+                |data| data.hourly[0].date = "2025-02-30".to_string(),
+                "hourly[0]",
+            ),
+            (|data| data.hourly[0].hour = 24, "hourly[0]"),
+            (
+                |data| data.sessions[0].end_time = "20:30".to_string(),
+                "sessions[0]",
+            ),
+            (
+                |data| data.sessions[0].average_release_time = -1.0,
+                "sessions[0]",
+            ),
+        ];
+        for (corrupt, location) in cases {
+            let mut data = sample_data();
+            corrupt(&mut data);
+            fs::write(&json_path, serde_json::to_string(&data).unwrap()).unwrap();
+
+            let error = read_import_source(&json_path).unwrap_err().to_string();
+            assert!(error.contains(location), "{}", error);
+            assert!(!error.contains("line 0"), "{}", error);
+        }
+
+        fs::write(&json_path, serde_json::to_string(&sample_data()).unwrap()).unwrap();
+        assert_eq!(read_import_source(&json_path).unwrap(), sample_data());
+
+        cleanup_test_db(json_path);
+    }
+}
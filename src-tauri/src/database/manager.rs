@@ -1,11 +1,16 @@
+use super::backup;
 use super::error::DatabaseError;
-use rusqlite::{Connection, OptionalExtension, Result};
-use std::path::PathBuf;
+use super::migration;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Result};
+use std::path::{Path, PathBuf};
 
 #[derive(Debug)]
 pub struct DatabaseManager {
-    path: PathBuf,
-    conn: Connection,
+    pub(super) path: PathBuf,
+    pub(super) conn: Connection,
+    backup_keep: usize,
+    // falseの場合はbackup_if_dueでバックアップしない
+    auto_backup: bool,
 }
 
 impl DatabaseManager {
@@ -13,25 +18,66 @@ impl DatabaseManager {
         let conn = Connection::open(&path_buf).map_err(|e| {
             DatabaseError::ConnectionError(format!("Failed to open database {}", e))
         })?;
+        // SQLiteの既定では外部キーが無効で、session_key_countsのON DELETE CASCADEが働かない
+        conn.pragma_update(None, "foreign_keys", true)
+            .map_err(|e| {
+                DatabaseError::ConnectionError(format!("Failed to enable foreign keys: {}", e))
+            })?;
         Ok(DatabaseManager {
             path: path_buf,
             conn,
+            backup_keep: backup::DEFAULT_KEEP,
+            auto_backup: true,
         })
     }
 
+    // 取り込み元など、変更しないデータベースを開く
+    pub fn open_read_only(path_buf: PathBuf) -> Result<DatabaseManager, DatabaseError> {
+        let conn = Connection::open_with_flags(&path_buf, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| {
+                DatabaseError::ConnectionError(format!("Failed to open database {}", e))
+            })?;
+        Ok(DatabaseManager {
+            path: path_buf,
+            conn,
+            backup_keep: backup::DEFAULT_KEEP,
+            auto_backup: true,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // 自動バックアップで保持する数
+    pub fn backup_keep(&self) -> usize {
+        self.backup_keep
+    }
+
+    pub fn set_backup_keep(&mut self, keep: usize) {
+        self.backup_keep = keep;
+    }
+
+    pub fn auto_backup(&self) -> bool {
+        self.auto_backup
+    }
+
+    pub fn set_auto_backup(&mut self, enabled: bool) {
+        self.auto_backup = enabled;
+    }
+
+    // スキーマを最新のバージョンへ移行する。既存のデータベースを移行する場合は事前にバックアップを作成する
     pub fn initialize(&mut self) -> Result<(), DatabaseError> {
-        let sql = "CREATE TABLE IF NOT EXISTS statistics(
-      id INTEGER PRIMARY KEY AUTOINCREMENT,
-      date TEXT NOT NULL UNIQUE,
-      notes_count INTEGER NOT NULL
-    )";
-
-        self.conn.execute(sql, []).map_err(|e| {
-            DatabaseError::InitializeError(format!("Failed to create database{}", e))
-        })?;
+        let version = migration::user_version(&self.conn)?;
+        if version < migration::latest_version() && !migration::is_empty(&self.conn)? {
+            migration::backup(&self.conn, &migration::backup_path(&self.path, version))?;
+        }
+
+        migration::migrate(&mut self.conn, migration::MIGRATIONS)?;
         Ok(())
     }
 
+    #[cfg(test)]
     pub fn insert(&self, date: &str, notes_count: i32) -> Result<(), DatabaseError> {
         self.conn
             .execute(
@@ -80,6 +126,7 @@ impl DatabaseManager {
         Ok(results)
     }
 
+    #[cfg(test)]
     pub fn update(&self, date: &str, notes_count: i32) -> Result<(), DatabaseError> {
         self.conn
             .execute(
@@ -90,40 +137,132 @@ impl DatabaseManager {
         Ok(())
     }
 
-    pub fn delete(&self) -> Result<(), DatabaseError> {
+    // 日付のノーツ数にdeltaを加算し、加算後の合計を返す。行がなければ作成する
+    #[cfg(test)]
+    pub fn add_notes(&self, date: &str, delta: i32) -> Result<i32, DatabaseError> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to begin: {}", e)))?;
+
+        tx.execute(
+            "INSERT INTO statistics (date, notes_count) VALUES (?1, ?2)
+             ON CONFLICT(date) DO UPDATE SET notes_count = notes_count + excluded.notes_count",
+            params![date, delta],
+        )
+        .map_err(|e| DatabaseError::QueryError(format!("Failed to add notes: {}", e)))?;
+        let total = tx
+            .query_row(
+                "SELECT notes_count FROM statistics WHERE date = ?1",
+                [date],
+                |row| row.get(0),
+            )
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+
+        tx.commit()
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to commit: {}", e)))?;
+        Ok(total)
+    }
+
+    // 日別（日付, 加算数）と時間帯別（日付, 時, 加算数）のノーツ数を1つのトランザクションで加算する。
+    // 途中で失敗した場合はどちらも加算しない
+    pub fn add_counts(
+        &self,
+        daily: &[(String, u32)],
+        hourly: &[(String, u32, u32)],
+    ) -> Result<(), DatabaseError> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to begin: {}", e)))?;
+
+        for (date, delta) in daily {
+            tx.execute(
+                "INSERT INTO statistics (date, notes_count) VALUES (?1, ?2)
+                 ON CONFLICT(date) DO UPDATE SET notes_count = notes_count + excluded.notes_count",
+                params![date, delta],
+            )
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to add notes: {}", e)))?;
+        }
+        for (date, hour, delta) in hourly {
+            tx.execute(
+                "INSERT INTO hourly_statistics (date, hour, notes_count) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(date, hour) DO UPDATE SET notes_count = notes_count + excluded.notes_count",
+                params![date, hour, delta],
+            )
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to add hourly notes: {}", e)))?;
+        }
+
+        tx.commit()
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to commit: {}", e)))
+    }
+
+    // 日付のノーツ数を上書きする。行がなければ作成する
+    pub fn set_notes(&self, date: &str, notes_count: i32) -> Result<(), DatabaseError> {
+        self.conn
+            .execute(
+                "INSERT INTO statistics (date, notes_count) VALUES (?1, ?2)
+                 ON CONFLICT(date) DO UPDATE SET notes_count = excluded.notes_count",
+                params![date, notes_count],
+            )
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to set notes: {}", e)))?;
         Ok(())
     }
+
+    // 削除で空いた領域を詰めてファイルを小さくする
+    pub fn vacuum(&self) -> Result<(), DatabaseError> {
+        self.conn
+            .execute_batch("VACUUM")
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to vacuum: {}", e)))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
-    // テスト用の一時データベースを作成
-    fn setup_test_db(test_name: &str) -> (DatabaseManager, PathBuf) {
-        let test_db_path = PathBuf::from(format!("test_{}.db", test_name));
-
-        // 既存のテストDBがあれば削除
-        if test_db_path.exists() {
-            fs::remove_file(&test_db_path).unwrap();
+    use crate::database::test_util::{cleanup_test_db, setup_test_db};
+    #[test]
+    fn test_backup_before_migration() {
+        let path = PathBuf::from("test_backup_before_migration.db");
+        let backup_path = migration::backup_path(&path, 0);
+        cleanup_test_db(path.clone());
+        cleanup_test_db(backup_path.clone());
+
+        // user_versionを使う前のデータベースを作成
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE statistics(
+                  id INTEGER PRIMARY KEY AUTOINCREMENT,
+                  date TEXT NOT NULL UNIQUE,
+                  notes_count INTEGER NOT NULL
+                );
+                INSERT INTO statistics (date, notes_count) VALUES ('2025-01-01', 100);",
+            )
+            .unwrap();
         }
 
-        let mut db = DatabaseManager::new(test_db_path.clone()).unwrap();
+        let mut db = DatabaseManager::new(path.clone()).unwrap();
         db.initialize().unwrap();
+        assert_eq!(db.get("2025-01-01").unwrap(), Some(100));
+        assert!(backup_path.exists());
 
-        (db, test_db_path)
-    }
+        let backup = DatabaseManager::new(backup_path.clone()).unwrap();
+        assert_eq!(backup.get("2025-01-01").unwrap(), Some(100));
 
-    // テスト後のクリーンアップ
-    fn cleanup_test_db(path: PathBuf) {
-        if path.exists() {
-            fs::remove_file(path).unwrap();
-        }
+        // 最新のデータベースではバックアップを作らない
+        cleanup_test_db(backup_path.clone());
+        drop(db);
+        let mut db = DatabaseManager::new(path.clone()).unwrap();
+        db.initialize().unwrap();
+        assert!(!backup_path.exists());
+
+        cleanup_test_db(path);
     }
 
     #[test]
     fn test_database_creation() {
-        let (db, path) = setup_test_db("creation");
+        let (_db, path) = setup_test_db("creation");
         assert!(path.exists());
         cleanup_test_db(path);
     }
@@ -161,6 +300,96 @@ mod tests {
         cleanup_test_db(path);
     }
 
+    #[test]
+    fn test_add_notes() {
+        let (db, path) = setup_test_db("add_notes");
+
+        // 行がなければ作成される
+        assert_eq!(db.add_notes("2025-01-01", 100).unwrap(), 100);
+        // 既存の行には加算される
+        assert_eq!(db.add_notes("2025-01-01", 50).unwrap(), 150);
+        assert_eq!(db.get("2025-01-01").unwrap(), Some(150));
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_add_counts_is_atomic() {
+        let (db, path) = setup_test_db("add_counts");
+
+        db.add_counts(
+            &[("2025-01-01".to_string(), 100)],
+            &[("2025-01-01".to_string(), 10, 100)],
+        )
+        .unwrap();
+        assert_eq!(db.get("2025-01-01").unwrap(), Some(100));
+        assert_eq!(
+            db.get_hourly_statistics("2025-01-01", "2025-01-01")
+                .unwrap()[3][10],
+            100
+        );
+
+        // 時間帯別の加算に失敗した場合は日別も加算しない
+        db.conn
+            .execute_batch("DROP TABLE hourly_statistics")
+            .unwrap();
+        assert!(db
+            .add_counts(
+                &[("2025-01-01".to_string(), 50)],
+                &[("2025-01-01".to_string(), 10, 50)],
+            )
+            .is_err());
+        assert_eq!(db.get("2025-01-01").unwrap(), Some(100));
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_add_notes_concurrently() {
+        let path = PathBuf::from("test_add_notes_concurrently.db");
+        cleanup_test_db(path.clone());
+        DatabaseManager::new(path.clone())
+            .unwrap()
+            .initialize()
+            .unwrap();
+
+        // 別々の接続から同時に加算しても失われない
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let db = DatabaseManager::new(path).unwrap();
+                    db.conn
+                        .busy_timeout(std::time::Duration::from_secs(5))
+                        .unwrap();
+                    for _ in 0..25 {
+                        db.add_notes("2025-01-01", 1).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let db = DatabaseManager::new(path.clone()).unwrap();
+        assert_eq!(db.get("2025-01-01").unwrap(), Some(100));
+
+        drop(db);
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_set_notes() {
+        let (db, path) = setup_test_db("set_notes");
+
+        db.set_notes("2025-01-01", 100).unwrap();
+        db.set_notes("2025-01-01", 30).unwrap();
+        assert_eq!(db.get("2025-01-01").unwrap(), Some(30));
+
+        cleanup_test_db(path);
+    }
+
     #[test]
     fn test_get_nonexistent_data() {
         let (db, path) = setup_test_db("nonexistent");
@@ -239,4 +468,19 @@ mod tests {
 
         cleanup_test_db(path);
     }
+
+    #[test]
+    fn test_vacuum_keeps_data() {
+        let (db, path) = setup_test_db("vacuum");
+
+        db.insert("2025-01-01", 100).unwrap();
+        db.insert("2025-01-02", 200).unwrap();
+        db.delete("2025-01-02", 0).unwrap();
+        db.vacuum().unwrap();
+
+        assert_eq!(db.get("2025-01-01").unwrap(), Some(100));
+        assert_eq!(db.get("2025-01-02").unwrap(), None);
+
+        cleanup_test_db(path);
+    }
 }
@@ -0,0 +1,224 @@
+use super::error::DatabaseError;
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+
+// スキーマの変更履歴。n番目の要素を適用するとuser_versionがn+1になる。
+// 一度リリースした要素は変更せず、必ず末尾に追加すること
+pub const MIGRATIONS: &[&str] = &[
+    // 1: 日ごとの統計（user_versionを使う前から存在するテーブル）
+    "CREATE TABLE IF NOT EXISTS statistics(
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      date TEXT NOT NULL UNIQUE,
+      notes_count INTEGER NOT NULL
+    );",
+    // 2: プレイセッション
+    "CREATE TABLE IF NOT EXISTS sessions(
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      start_time TEXT NOT NULL,
+      end_time TEXT NOT NULL,
+      notes_count INTEGER NOT NULL,
+      scratch_count INTEGER NOT NULL,
+      peak_nps INTEGER NOT NULL,
+      average_release_time REAL NOT NULL,
+      release_count INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS session_key_counts(
+      session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+      button INTEGER NOT NULL,
+      count INTEGER NOT NULL,
+      PRIMARY KEY (session_id, button)
+    );",
+    // 3: 時間帯ごとの統計
+    "CREATE TABLE IF NOT EXISTS hourly_statistics(
+      date TEXT NOT NULL,
+      hour INTEGER NOT NULL,
+      notes_count INTEGER NOT NULL,
+      PRIMARY KEY (date, hour)
+    );",
+    // 4: 目標
+    "CREATE TABLE IF NOT EXISTS goals(
+      kind TEXT PRIMARY KEY,
+      notes_count INTEGER NOT NULL
+    );",
+    // 5: 手動修正の履歴
+    "CREATE TABLE IF NOT EXISTS corrections(
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      date TEXT NOT NULL,
+      old_count INTEGER,
+      new_count INTEGER NOT NULL,
+      reason TEXT NOT NULL,
+      corrected_at TEXT NOT NULL
+    );",
+];
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.len() as u32
+}
+
+pub fn user_version(conn: &Connection) -> Result<u32, DatabaseError> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|e| DatabaseError::InitializeError(format!("Failed to get user_version: {}", e)))
+}
+
+// テーブルが一つもなければ新規作成のデータベース
+pub fn is_empty(conn: &Connection) -> Result<bool, DatabaseError> {
+    let count: u32 = conn
+        .query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get(0))
+        .map_err(|e| DatabaseError::InitializeError(format!("Failed to query schema: {}", e)))?;
+    Ok(count == 0)
+}
+
+pub fn has_table(conn: &Connection, name: &str) -> Result<bool, DatabaseError> {
+    let count: u32 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [name],
+            |row| row.get(0),
+        )
+        .map_err(|e| DatabaseError::InitializeError(format!("Failed to query schema: {}", e)))?;
+    Ok(count > 0)
+}
+
+// 移行前のバックアップのパス（例: statistics.db.v1.bak）
+pub fn backup_path(db_path: &Path, version: u32) -> PathBuf {
+    let mut path = db_path.as_os_str().to_owned();
+    path.push(format!(".v{}.bak", version));
+    PathBuf::from(path)
+}
+
+pub fn backup(conn: &Connection, path: &Path) -> Result<(), DatabaseError> {
+    if path.exists() {
+        std::fs::remove_file(path).map_err(|e| {
+            DatabaseError::InitializeError(format!("Failed to remove old backup: {}", e))
+        })?;
+    }
+    conn.execute("VACUUM INTO ?1", [path.to_string_lossy()])
+        .map_err(|e| DatabaseError::InitializeError(format!("Failed to backup: {}", e)))?;
+    Ok(())
+}
+
+// 未適用のマイグレーションを1つずつトランザクション内で適用し、適用後のバージョンを返す
+pub fn migrate(conn: &mut Connection, migrations: &[&str]) -> Result<u32, DatabaseError> {
+    let current = user_version(conn)?;
+    if current as usize > migrations.len() {
+        return Err(DatabaseError::InitializeError(format!(
+            "Database version {} is newer than this application supports ({})",
+            current,
+            migrations.len()
+        )));
+    }
+
+    for (index, sql) in migrations.iter().enumerate().skip(current as usize) {
+        let version = index as u32 + 1;
+        let tx = conn
+            .transaction()
+            .map_err(|e| DatabaseError::InitializeError(format!("Failed to begin: {}", e)))?;
+        tx.execute_batch(sql).map_err(|e| {
+            DatabaseError::InitializeError(format!(
+                "Failed to migrate to version {}: {}",
+                version, e
+            ))
+        })?;
+        tx.pragma_update(None, "user_version", version)
+            .map_err(|e| {
+                DatabaseError::InitializeError(format!("Failed to set user_version: {}", e))
+            })?;
+        tx.commit()
+            .map_err(|e| DatabaseError::InitializeError(format!("Failed to commit: {}", e)))?;
+    }
+
+    user_version(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_db() -> Connection {
+        Connection::open_in_memory().unwrap()
+    }
+
+    #[test]
+    fn test_migrate_fresh_database() {
+        let mut conn = memory_db();
+        assert!(is_empty(&conn).unwrap());
+
+        let version = migrate(&mut conn, MIGRATIONS).unwrap();
+        assert_eq!(version, latest_version());
+        assert_eq!(user_version(&conn).unwrap(), latest_version());
+        assert!(!is_empty(&conn).unwrap());
+    }
+
+    #[test]
+    fn test_migrate_is_idempotent() {
+        let mut conn = memory_db();
+        migrate(&mut conn, MIGRATIONS).unwrap();
+        let version = migrate(&mut conn, MIGRATIONS).unwrap();
+        assert_eq!(version, latest_version());
+    }
+
+    #[test]
+    fn test_migrate_legacy_database_keeps_data() {
+        // user_versionを使う前のデータベース
+        let mut conn = memory_db();
+        conn.execute_batch(
+            "CREATE TABLE statistics(
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              date TEXT NOT NULL UNIQUE,
+              notes_count INTEGER NOT NULL
+            );
+            INSERT INTO statistics (date, notes_count) VALUES ('2025-01-01', 100);",
+        )
+        .unwrap();
+        assert_eq!(user_version(&conn).unwrap(), 0);
+
+        migrate(&mut conn, MIGRATIONS).unwrap();
+
+        let count: i32 = conn
+            .query_row(
+                "SELECT notes_count FROM statistics WHERE date = '2025-01-01'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 100);
+    }
+
+    #[test]
+    fn test_failed_migration_is_rolled_back() {
+        let mut conn = memory_db();
+        let migrations = [
+            "CREATE TABLE a(id INTEGER);",
+            "CREATE TABLE b(id INTEGER); CREATE TABLE broken(;",
+        ];
+
+        assert!(migrate(&mut conn, &migrations).is_err());
+        assert_eq!(user_version(&conn).unwrap(), 1);
+
+        let b_exists: u32 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'b'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(b_exists, 0);
+    }
+
+    #[test]
+    fn test_newer_database_is_rejected() {
+        let mut conn = memory_db();
+        conn.pragma_update(None, "user_version", latest_version() + 1)
+            .unwrap();
+
+        assert!(migrate(&mut conn, MIGRATIONS).is_err());
+    }
+
+    #[test]
+    fn test_backup_path() {
+        assert_eq!(
+            backup_path(Path::new("/data/statistics.db"), 1),
+            PathBuf::from("/data/statistics.db.v1.bak")
+        );
+    }
+}
@@ -1,5 +1,24 @@
-pub mod manager;
+pub mod aggregate;
+pub mod backup;
+pub mod edit;
 pub mod error;
+pub mod export;
+pub mod goal;
+pub mod hourly;
+pub mod import;
+pub mod manager;
+pub mod migration;
+pub mod session;
+#[cfg(test)]
+pub mod test_util;
 
+pub use aggregate::{Grouping, PeriodStatistics, RangeSummary};
+pub use backup::{BackupInfo, Recovery};
+pub use edit::{Correction, DeletionSummary};
 pub use error::DatabaseError;
+pub use export::{ExportFormat, ExportSummary};
+pub use goal::{GoalKind, GoalProgress, Goals, Streaks};
+pub use hourly::HourlyMatrix;
+pub use import::{ImportPreview, MergeStrategy};
 pub use manager::DatabaseManager;
+pub use session::SessionRecord;
@@ -0,0 +1,321 @@
+use super::error::DatabaseError;
+use super::manager::DatabaseManager;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use ts_rs::TS;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SessionRecord {
+    #[ts(type = "number")]
+    pub id: i64,
+    pub start_time: String,
+    pub end_time: String,
+    pub notes_count: u32,
+    pub scratch_count: u32,
+    pub peak_nps: u32,
+    pub average_release_time: f64,
+    pub release_count: u32,
+    pub key_counts: BTreeMap<u32, u32>,
+}
+
+impl SessionRecord {
+    fn from_row(row: &Row) -> rusqlite::Result<SessionRecord> {
+        Ok(SessionRecord {
+            id: row.get(0)?,
+            start_time: row.get(1)?,
+            end_time: row.get(2)?,
+            notes_count: row.get(3)?,
+            scratch_count: row.get(4)?,
+            peak_nps: row.get(5)?,
+            average_release_time: row.get(6)?,
+            release_count: row.get(7)?,
+            key_counts: BTreeMap::new(),
+        })
+    }
+}
+
+// キーごとの合計回数
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct KeyCount {
+    pub button: u32,
+    #[ts(type = "number")]
+    pub count: i64,
+}
+
+const SELECT_SESSION: &str = "SELECT id, start_time, end_time, notes_count, scratch_count, peak_nps, average_release_time, release_count FROM sessions";
+
+// トランザクション内でセッションとキーごとの回数を挿入する
+pub(super) fn insert_session_rows(
+    conn: &Connection,
+    session: &SessionRecord,
+) -> Result<i64, DatabaseError> {
+    conn.execute(
+        "INSERT INTO sessions (start_time, end_time, notes_count, scratch_count, peak_nps, average_release_time, release_count)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            session.start_time,
+            session.end_time,
+            session.notes_count,
+            session.scratch_count,
+            session.peak_nps,
+            session.average_release_time,
+            session.release_count,
+        ],
+    )
+    .map_err(|e| DatabaseError::QueryError(format!("Failed to insert session: {}", e)))?;
+    let id = conn.last_insert_rowid();
+
+    for (button, count) in &session.key_counts {
+        conn.execute(
+            "INSERT INTO session_key_counts (session_id, button, count) VALUES (?1, ?2, ?3)",
+            params![id, button, count],
+        )
+        .map_err(|e| DatabaseError::QueryError(format!("Failed to insert key count: {}", e)))?;
+    }
+
+    Ok(id)
+}
+
+impl DatabaseManager {
+    // idは無視され、挿入後に採番されたidを返す
+    pub fn insert_session(&self, session: &SessionRecord) -> Result<i64, DatabaseError> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to begin: {}", e)))?;
+        let id = insert_session_rows(&tx, session)?;
+        tx.commit()
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to commit: {}", e)))?;
+        Ok(id)
+    }
+
+    pub fn get_session(&self, id: i64) -> Result<Option<SessionRecord>, DatabaseError> {
+        let session = self
+            .conn
+            .query_row(
+                &format!("{} WHERE id = ?1", SELECT_SESSION),
+                [id],
+                SessionRecord::from_row,
+            )
+            .optional()
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+
+        match session {
+            Some(mut session) => {
+                session.key_counts = self.get_session_key_counts(session.id)?;
+                Ok(Some(session))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // 新しい順にセッションを返す
+    pub fn list_sessions(
+        &self,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<SessionRecord>, DatabaseError> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "{} ORDER BY start_time DESC, id DESC LIMIT ?1 OFFSET ?2",
+                SELECT_SESSION
+            ))
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to prepare: {}", e)))?;
+
+        let rows = stmt
+            .query_map([limit, offset], SessionRecord::from_row)
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            let mut session =
+                row.map_err(|e| DatabaseError::QueryError(format!("Failed to get row: {}", e)))?;
+            session.key_counts = self.get_session_key_counts(session.id)?;
+            results.push(session);
+        }
+
+        Ok(results)
+    }
+
+    // start_dateからend_dateまで（両端を含む）に開始したセッションのキーごとの合計
+    pub fn get_key_breakdown(
+        &self,
+        start_date: &str,
+        end_date: &str,
+    ) -> Result<Vec<KeyCount>, DatabaseError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT k.button, SUM(k.count) FROM session_key_counts k
+                 JOIN sessions s ON s.id = k.session_id
+                 WHERE substr(s.start_time, 1, 10) BETWEEN ?1 AND ?2
+                 GROUP BY k.button
+                 ORDER BY k.button ASC",
+            )
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to prepare: {}", e)))?;
+
+        let rows = stmt
+            .query_map([start_date, end_date], |row| {
+                Ok(KeyCount {
+                    button: row.get(0)?,
+                    count: row.get(1)?,
+                })
+            })
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            results.push(
+                row.map_err(|e| DatabaseError::QueryError(format!("Failed to get row: {}", e)))?,
+            );
+        }
+
+        Ok(results)
+    }
+
+    fn get_session_key_counts(&self, session_id: i64) -> Result<BTreeMap<u32, u32>, DatabaseError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT button, count FROM session_key_counts WHERE session_id = ?1")
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to prepare: {}", e)))?;
+
+        let rows = stmt
+            .query_map([session_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| DatabaseError::QueryError(format!("Failed to query: {}", e)))?;
+
+        let mut key_counts = BTreeMap::new();
+        for row in rows {
+            let (button, count) =
+                row.map_err(|e| DatabaseError::QueryError(format!("Failed to get row: {}", e)))?;
+            key_counts.insert(button, count);
+        }
+
+        Ok(key_counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_util::{cleanup_test_db, setup_test_db};
+
+    fn sample_session(start_time: &str, notes_count: u32) -> SessionRecord {
+        SessionRecord {
+            id: 0,
+            start_time: start_time.to_string(),
+            end_time: start_time.to_string(),
+            notes_count,
+            scratch_count: 10,
+            peak_nps: 12,
+            average_release_time: 45.5,
+            release_count: 80,
+            key_counts: BTreeMap::from([(0, 30), (3, 60)]),
+        }
+    }
+
+    #[test]
+    fn test_insert_and_get_session() {
+        let (db, path) = setup_test_db("session_insert_and_get");
+
+        let session = sample_session("2025-01-01T20:00:00+09:00", 100);
+        let id = db.insert_session(&session).unwrap();
+
+        let stored = db.get_session(id).unwrap().unwrap();
+        assert_eq!(stored, SessionRecord { id, ..session });
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_deleting_session_cascades_to_key_counts() {
+        let (db, path) = setup_test_db("session_cascade");
+
+        let id = db
+            .insert_session(&sample_session("2025-01-01T20:00:00+09:00", 100))
+            .unwrap();
+        db.conn
+            .execute("DELETE FROM sessions WHERE id = ?1", [id])
+            .unwrap();
+
+        let remaining: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM session_key_counts", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(remaining, 0);
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_get_nonexistent_session() {
+        let (db, path) = setup_test_db("session_nonexistent");
+
+        assert_eq!(db.get_session(42).unwrap(), None);
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_list_sessions_newest_first() {
+        let (db, path) = setup_test_db("session_list");
+
+        db.insert_session(&sample_session("2025-01-01T20:00:00+09:00", 100))
+            .unwrap();
+        db.insert_session(&sample_session("2025-01-03T20:00:00+09:00", 300))
+            .unwrap();
+        db.insert_session(&sample_session("2025-01-02T20:00:00+09:00", 200))
+            .unwrap();
+
+        let sessions = db.list_sessions(2, 0).unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].notes_count, 300);
+        assert_eq!(sessions[1].notes_count, 200);
+        assert_eq!(sessions[0].key_counts.get(&3), Some(&60));
+
+        let sessions = db.list_sessions(2, 2).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].notes_count, 100);
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_key_breakdown_in_range() {
+        let (db, path) = setup_test_db("session_key_breakdown");
+
+        db.insert_session(&sample_session("2025-01-01T20:00:00+09:00", 90))
+            .unwrap();
+        db.insert_session(&sample_session("2025-01-02T20:00:00+09:00", 90))
+            .unwrap();
+        db.insert_session(&sample_session("2025-01-05T20:00:00+09:00", 90))
+            .unwrap();
+
+        assert_eq!(
+            db.get_key_breakdown("2025-01-01", "2025-01-02").unwrap(),
+            vec![
+                KeyCount {
+                    button: 0,
+                    count: 60
+                },
+                KeyCount {
+                    button: 3,
+                    count: 120
+                },
+            ]
+        );
+        assert!(db
+            .get_key_breakdown("2025-02-01", "2025-02-28")
+            .unwrap()
+            .is_empty());
+
+        cleanup_test_db(path);
+    }
+}
@@ -0,0 +1,26 @@
+use super::DatabaseManager;
+use std::fs;
+use std::path::PathBuf;
+
+// テスト用の一時データベースをカレントディレクトリにtest_{name}.dbとして作成する。
+// テストは並列に動くため、nameはモジュール名を付けるなどしてテスト全体で一意にする
+pub fn setup_test_db(name: &str) -> (DatabaseManager, PathBuf) {
+    let test_db_path = PathBuf::from(format!("test_{}.db", name));
+
+    // 既存のテストDBがあれば削除
+    if test_db_path.exists() {
+        fs::remove_file(&test_db_path).unwrap();
+    }
+
+    let mut db = DatabaseManager::new(test_db_path.clone()).unwrap();
+    db.initialize().unwrap();
+
+    (db, test_db_path)
+}
+
+// テスト後のクリーンアップ
+pub fn cleanup_test_db(path: PathBuf) {
+    if path.exists() {
+        fs::remove_file(path).unwrap();
+    }
+}
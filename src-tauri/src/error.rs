@@ -0,0 +1,142 @@
+use crate::database::DatabaseError;
+use crate::gamepad::GamepadError;
+use crate::server::ServerError;
+use crate::settings::SettingsError;
+use serde::Serialize;
+use std::fmt;
+use std::sync::PoisonError;
+use ts_rs::TS;
+
+// コマンドがフロントエンドへ返すエラー。{ kind, message }の形でシリアライズされる
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+#[ts(export)]
+pub enum AppError {
+    // Mutexがpoisonedになっている
+    Lock(String),
+    // データベースを開けない
+    DatabaseConnection(String),
+    // スキーマの作成・移行に失敗した
+    DatabaseInitialize(String),
+    Database(String),
+    File(String),
+    Gamepad(String),
+    // WebSocketなどのサーバーを起動できない
+    Server(String),
+    InvalidInput(String),
+    // 指定したものが存在しない
+    NotFound(String),
+    Internal(String),
+}
+
+impl std::error::Error for AppError {}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AppError::Lock(msg) => write!(f, "Lock error: {}", msg),
+            AppError::DatabaseConnection(msg) => write!(f, "Database connection error: {}", msg),
+            AppError::DatabaseInitialize(msg) => write!(f, "Database initialize error: {}", msg),
+            AppError::Database(msg) => write!(f, "Database error: {}", msg),
+            AppError::File(msg) => write!(f, "File error: {}", msg),
+            AppError::Gamepad(msg) => write!(f, "Gamepad error: {}", msg),
+            AppError::Server(msg) => write!(f, "Server error: {}", msg),
+            AppError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
+            AppError::NotFound(msg) => write!(f, "Not found: {}", msg),
+            AppError::Internal(msg) => write!(f, "Internal error: {}", msg),
+        }
+    }
+}
+
+impl From<DatabaseError> for AppError {
+    fn from(error: DatabaseError) -> AppError {
+        match error {
+            DatabaseError::ConnectionError(msg) => AppError::DatabaseConnection(msg),
+            DatabaseError::InitializeError(msg) => AppError::DatabaseInitialize(msg),
+            DatabaseError::QueryError(msg) => AppError::Database(msg),
+            DatabaseError::FileError(msg) => AppError::File(msg),
+            DatabaseError::InvalidInput(msg) => AppError::InvalidInput(msg),
+        }
+    }
+}
+
+impl From<GamepadError> for AppError {
+    fn from(error: GamepadError) -> AppError {
+        match error {
+            GamepadError::Gilrs(msg) | GamepadError::Osc(msg) | GamepadError::Replay(msg) => {
+                AppError::Gamepad(msg)
+            }
+            GamepadError::Recording(msg) => AppError::File(msg),
+            GamepadError::Lock(msg) => AppError::Lock(msg),
+            GamepadError::Emit(msg) | GamepadError::Thread(msg) => AppError::Internal(msg),
+        }
+    }
+}
+
+impl From<ServerError> for AppError {
+    fn from(error: ServerError) -> AppError {
+        match error {
+            ServerError::BindError(msg) | ServerError::ConnectionError(msg) => {
+                AppError::Server(msg)
+            }
+        }
+    }
+}
+
+impl From<SettingsError> for AppError {
+    fn from(error: SettingsError) -> AppError {
+        match error {
+            SettingsError::FileError(msg) | SettingsError::ParseError(msg) => AppError::File(msg),
+            SettingsError::InvalidValue(msg) => AppError::InvalidInput(msg),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(error: rusqlite::Error) -> AppError {
+        AppError::Database(error.to_string())
+    }
+}
+
+impl<T> From<PoisonError<T>> for AppError {
+    fn from(error: PoisonError<T>) -> AppError {
+        AppError::Lock(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_serialize_kind_and_message() {
+        let error = AppError::from(DatabaseError::ConnectionError("missing".to_string()));
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({ "kind": "databaseConnection", "message": "missing" })
+        );
+    }
+
+    #[test]
+    fn test_from_gamepad_error() {
+        assert_eq!(
+            AppError::from(GamepadError::Lock("poisoned".to_string())),
+            AppError::Lock("poisoned".to_string())
+        );
+    }
+
+    #[test]
+    fn test_from_poisoned_mutex() {
+        let mutex = Arc::new(Mutex::new(0));
+        let poisoned = Arc::clone(&mutex);
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoned.lock().unwrap();
+            panic!("poison the mutex");
+        })
+        .join();
+
+        let error: AppError = mutex.lock().unwrap_err().into();
+        assert!(matches!(error, AppError::Lock(_)));
+    }
+}
@@ -0,0 +1,112 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::{Arc, RwLock};
+use tauri::{AppHandle, Emitter};
+
+// イベントの送り先（Webview、WebSocketなど）
+pub trait EventSink: Send + Sync {
+    fn send(&self, event: &str, payload: &Value) -> Result<(), String>;
+}
+
+// Webviewへ送る
+pub struct TauriSink {
+    app_handle: AppHandle,
+}
+
+impl TauriSink {
+    pub fn new(app_handle: AppHandle) -> TauriSink {
+        TauriSink { app_handle }
+    }
+}
+
+impl EventSink for TauriSink {
+    fn send(&self, event: &str, payload: &Value) -> Result<(), String> {
+        self.app_handle
+            .emit(event, payload)
+            .map_err(|e| e.to_string())
+    }
+}
+
+// 登録されたすべての送り先へ同じイベントを送る
+#[derive(Clone, Default)]
+pub struct EventHub {
+    sinks: Arc<RwLock<Vec<Arc<dyn EventSink>>>>,
+}
+
+impl EventHub {
+    pub fn new() -> EventHub {
+        EventHub::default()
+    }
+
+    pub fn add_sink(&self, sink: Arc<dyn EventSink>) {
+        if let Ok(mut sinks) = self.sinks.write() {
+            sinks.push(sink);
+        }
+    }
+
+    // 送り先の一つが失敗しても残りには送る。失敗した場合は最初のエラーを返す
+    pub fn emit<S: Serialize>(&self, event: &str, payload: &S) -> Result<(), String> {
+        let payload = serde_json::to_value(payload).map_err(|e| e.to_string())?;
+        let sinks = self.sinks.read().map_err(|e| e.to_string())?;
+
+        let mut result = Ok(());
+        for sink in sinks.iter() {
+            if let Err(e) = sink.send(event, &payload) {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingSink {
+        events: Mutex<Vec<(String, Value)>>,
+    }
+
+    impl EventSink for RecordingSink {
+        fn send(&self, event: &str, payload: &Value) -> Result<(), String> {
+            self.events
+                .lock()
+                .unwrap()
+                .push((event.to_string(), payload.clone()));
+            Ok(())
+        }
+    }
+
+    struct FailingSink;
+
+    impl EventSink for FailingSink {
+        fn send(&self, _event: &str, _payload: &Value) -> Result<(), String> {
+            Err("closed".to_string())
+        }
+    }
+
+    #[test]
+    fn test_emit_reaches_every_sink() {
+        let hub = EventHub::new();
+        let first = Arc::new(RecordingSink::default());
+        let second = Arc::new(RecordingSink::default());
+        hub.add_sink(first.clone());
+        hub.add_sink(Arc::new(FailingSink));
+        hub.add_sink(second.clone());
+
+        let result = hub.emit("test", &serde_json::json!({ "count": 1 }));
+        assert_eq!(result, Err("closed".to_string()));
+
+        for sink in [first, second] {
+            let events = sink.events.lock().unwrap();
+            assert_eq!(
+                *events,
+                vec![("test".to_string(), serde_json::json!({ "count": 1 }))]
+            );
+        }
+    }
+}
@@ -1,10 +1,11 @@
 use super::error::GamepadError;
-use std::time::{Duration, SystemTime};
+use chrono::{DateTime, Local};
+use std::time::Duration;
 
 #[derive(Debug)]
 struct ButtonEvent {
     pressed_count: u32,
-    pressed_time: Option<SystemTime>,
+    pressed_time: Option<DateTime<Local>>,
 }
 
 impl ButtonEvent {
@@ -15,15 +16,15 @@ impl ButtonEvent {
         }
     }
 
-    fn on_press(&mut self) -> Result<(), GamepadError> {
+    fn on_press(&mut self, now: DateTime<Local>) -> Result<(), GamepadError> {
         self.pressed_count += 1;
-        self.pressed_time = Some(SystemTime::now());
+        self.pressed_time = Some(now);
         Ok(())
     }
 
-    fn on_release(&mut self) -> Result<Option<Duration>, GamepadError> {
+    fn on_release(&mut self, now: DateTime<Local>) -> Result<Option<Duration>, GamepadError> {
         if let Some(press_time) = self.pressed_time.take() {
-            if let Ok(duration) = press_time.elapsed() {
+            if let Ok(duration) = (now - press_time).to_std() {
                 if duration < Duration::from_millis(200) {
                     return Ok(Some(duration));
                 } else {
@@ -31,7 +32,7 @@ impl ButtonEvent {
                 }
             }
         }
-        Err(GamepadError::GilrsError(String::from(
+        Err(GamepadError::Gilrs(String::from(
             "You released the button, but pressed time does not exit",
         )))
     }
@@ -49,17 +50,22 @@ impl ButtonManager {
         }
     }
 
-    pub fn on_press(&mut self, button_code: u32) -> Result<(), GamepadError> {
+    // nowは入力の時刻。リプレイでは記録した時刻を渡す
+    pub fn on_press(&mut self, button_code: u32, now: DateTime<Local>) -> Result<(), GamepadError> {
         let button_event = self
             .buttons
             .entry(button_code)
             .or_insert_with(ButtonEvent::new);
-        button_event.on_press()
+        button_event.on_press(now)
     }
-    pub fn on_release(&mut self, button_code: u32) -> Result<Option<Duration>, GamepadError> {
+    pub fn on_release(
+        &mut self,
+        button_code: u32,
+        now: DateTime<Local>,
+    ) -> Result<Option<Duration>, GamepadError> {
         self.buttons
             .get_mut(&button_code)
-            .ok_or_else(|| GamepadError::GilrsError(format!("Button {} not found", button_code)))?
-            .on_release()
+            .ok_or_else(|| GamepadError::Gilrs(format!("Button {} not found", button_code)))?
+            .on_release(now)
     }
 }
@@ -2,10 +2,13 @@ use std::fmt;
 
 #[derive(Debug)]
 pub enum GamepadError {
-    GilrsError(String),
-    LockError(String),
-    EmitError(String),
-    ThreadError(String),
+    Gilrs(String),
+    Lock(String),
+    Emit(String),
+    Thread(String),
+    Osc(String),
+    Recording(String),
+    Replay(String),
 }
 
 impl std::error::Error for GamepadError {}
@@ -13,10 +16,13 @@ impl std::error::Error for GamepadError {}
 impl fmt::Display for GamepadError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            GamepadError::GilrsError(msg) => write!(f, "Gilrs error: {}", msg),
-            GamepadError::LockError(msg) => write!(f, "Lock error: {}", msg),
-            GamepadError::EmitError(msg) => write!(f, "Emit error: {}", msg),
-            GamepadError::ThreadError(msg) => write!(f, "Thread error: {}", msg),
+            GamepadError::Gilrs(msg) => write!(f, "Gilrs error: {}", msg),
+            GamepadError::Lock(msg) => write!(f, "Lock error: {}", msg),
+            GamepadError::Emit(msg) => write!(f, "Emit error: {}", msg),
+            GamepadError::Thread(msg) => write!(f, "Thread error: {}", msg),
+            GamepadError::Osc(msg) => write!(f, "OSC error: {}", msg),
+            GamepadError::Recording(msg) => write!(f, "Recording error: {}", msg),
+            GamepadError::Replay(msg) => write!(f, "Replay error: {}", msg),
         }
     }
 }
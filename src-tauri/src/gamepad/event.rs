@@ -0,0 +1,129 @@
+use super::scratch::Direction;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use ts_rs::TS;
+
+// フロントエンドへ送るイベント名
+pub const GAMEPAD_INPUT: &str = "gamepad-input";
+pub const GOAL_REACHED: &str = "goal-reached";
+pub const STATS: &str = "stats";
+pub const REPLAY_FINISHED: &str = "replay-finished";
+
+// gamepad-inputイベントの内容。typeで種類を判別する
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(
+    tag = "type",
+    rename_all = "lowercase",
+    rename_all_fields = "camelCase"
+)]
+#[ts(export)]
+pub enum GamepadInputEvent {
+    Button {
+        // ボタンのコード
+        button: u32,
+        // キーレイアウトのレーン。レイアウトにないボタンは省略
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[ts(optional)]
+        lane: Option<u32>,
+        pressed: bool,
+        count: u32,
+        // 離したときのみ。直近の平均（ミリ秒）
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[ts(optional)]
+        average_release_time: Option<u32>,
+    },
+    Scratch {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[ts(optional)]
+        lane: Option<u32>,
+        axis: i32,
+        direction: Direction,
+        count: u32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct GoalReachedEvent {
+    pub date: String,
+    pub notes_count: u32,
+    pub goal: u32,
+}
+
+// 集計値。変化があったときに送られる
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct StatsEvent {
+    // 日付変更時刻を考慮した今日の日付
+    pub date: String,
+    // 今日の保存済みと未保存の合計
+    pub today_count: u32,
+    // 起動してからのノーツ数
+    pub count: u32,
+    pub average_release_time: u32,
+}
+
+// リプレイの終了時に送る。リプレイした入力から計算し直した統計
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ReplayFinishedEvent {
+    // 再生した入力の数
+    pub inputs: u32,
+    pub notes_count: u32,
+    pub scratch_count: u32,
+    pub key_counts: BTreeMap<u32, u32>,
+    pub peak_nps: u32,
+    // ミリ秒
+    pub average_release_time: u32,
+    // 途中で止めた場合はtrue
+    pub stopped: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_button_event_payload() {
+        let press = GamepadInputEvent::Button {
+            button: 3,
+            lane: Some(4),
+            pressed: true,
+            count: 10,
+            average_release_time: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&press).unwrap(),
+            serde_json::json!({ "type": "button", "button": 3, "lane": 4, "pressed": true, "count": 10 })
+        );
+
+        let release = GamepadInputEvent::Button {
+            button: 3,
+            lane: None,
+            pressed: false,
+            count: 10,
+            average_release_time: Some(45),
+        };
+        assert_eq!(
+            serde_json::to_value(&release).unwrap()["averageReleaseTime"],
+            45
+        );
+    }
+
+    #[test]
+    fn test_scratch_event_payload() {
+        let scratch = GamepadInputEvent::Scratch {
+            lane: Some(0),
+            axis: -120,
+            direction: Direction::Left,
+            count: 11,
+        };
+        assert_eq!(
+            serde_json::to_value(&scratch).unwrap(),
+            serde_json::json!({ "type": "scratch", "lane": 0, "axis": -120, "direction": "left", "count": 11 })
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use chrono::NaiveDate;
+
+// 今日の保存済みノーツ数と未保存分の合計が日の目標に達したかを判定する
+#[derive(Debug)]
+pub struct GoalTracker {
+    daily_goal: Option<u32>,
+    saved_date: Option<NaiveDate>,
+    saved_count: u32,
+    reached_date: Option<NaiveDate>,
+}
+
+impl GoalTracker {
+    pub fn new() -> GoalTracker {
+        GoalTracker {
+            daily_goal: None,
+            saved_date: None,
+            saved_count: 0,
+            reached_date: None,
+        }
+    }
+
+    pub fn daily_goal(&self) -> Option<u32> {
+        self.daily_goal
+    }
+
+    pub fn set_daily_goal(&mut self, daily_goal: Option<u32>) {
+        self.daily_goal = daily_goal;
+        self.reached_date = None;
+    }
+
+    // 保存済みの分だけで既に達成している場合は通知済みとして扱う
+    pub fn set_saved_count(&mut self, date: NaiveDate, saved_count: u32) {
+        self.saved_date = Some(date);
+        self.saved_count = saved_count;
+        if let Some(goal) = self.daily_goal {
+            if saved_count >= goal {
+                self.reached_date = Some(date);
+            }
+        }
+    }
+
+    // 保存済みのその日のノーツ数。別の日の値しか持っていない場合は0
+    pub fn saved_count(&self, date: NaiveDate) -> u32 {
+        if self.saved_date == Some(date) {
+            self.saved_count
+        } else {
+            0
+        }
+    }
+
+    // その日初めて目標に達したときだけ合計ノーツ数を返す
+    pub fn check(&mut self, today: NaiveDate, pending_count: u32) -> Option<u32> {
+        let goal = self.daily_goal?;
+        if self.reached_date == Some(today) {
+            return None;
+        }
+
+        let total = self.saved_count(today) + pending_count;
+        if total >= goal {
+            self.reached_date = Some(today);
+            Some(total)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_no_goal() {
+        let mut tracker = GoalTracker::new();
+        assert_eq!(tracker.check(date("2025-01-01"), 1000), None);
+    }
+
+    #[test]
+    fn test_goal_reached_once_per_day() {
+        let mut tracker = GoalTracker::new();
+        tracker.set_daily_goal(Some(100));
+        tracker.set_saved_count(date("2025-01-01"), 60);
+
+        assert_eq!(tracker.check(date("2025-01-01"), 39), None);
+        assert_eq!(tracker.check(date("2025-01-01"), 40), Some(100));
+        assert_eq!(tracker.check(date("2025-01-01"), 41), None);
+
+        // 日付が変わると保存済み分は数えない
+        assert_eq!(tracker.check(date("2025-01-02"), 99), None);
+        assert_eq!(tracker.check(date("2025-01-02"), 100), Some(100));
+    }
+
+    #[test]
+    fn test_already_reached_before_start() {
+        let mut tracker = GoalTracker::new();
+        tracker.set_daily_goal(Some(100));
+        tracker.set_saved_count(date("2025-01-01"), 150);
+
+        assert_eq!(tracker.check(date("2025-01-01"), 1), None);
+    }
+}
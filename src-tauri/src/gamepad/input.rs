@@ -0,0 +1,28 @@
+use gilrs_core::EventType;
+
+// コントローラーからの入力。gilrsのイベントから作り、記録にも使う
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RawInput {
+    ButtonPressed(u32),
+    ButtonReleased(u32),
+    AxisChanged { code: u32, value: i32 },
+    Connected,
+    Disconnected,
+}
+
+impl RawInput {
+    // gilrsが今後追加する種類のイベントは扱わない
+    pub fn from_event(event: EventType) -> Option<RawInput> {
+        Some(match event {
+            EventType::ButtonPressed(code) => RawInput::ButtonPressed(code.into_u32()),
+            EventType::ButtonReleased(code) => RawInput::ButtonReleased(code.into_u32()),
+            EventType::AxisValueChanged(value, code) => RawInput::AxisChanged {
+                code: code.into_u32(),
+                value,
+            },
+            EventType::Connected => RawInput::Connected,
+            EventType::Disconnected => RawInput::Disconnected,
+            _ => return None,
+        })
+    }
+}
@@ -0,0 +1,309 @@
+use super::input::RawInput;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use LaneColor::{Blue, Green, Red, White, Yellow};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub enum KeyLayoutId {
+    // beatmania IIDX SP（7鍵+皿）
+    IidxSp,
+    // beatmania IIDX DP（14鍵+皿2つ）
+    IidxDp,
+    // 5鍵+皿
+    FiveKey,
+    // pop'n music 9ボタン
+    Popn9,
+    Mania4k,
+    Mania6k,
+    Mania7k,
+    Djmax4b,
+    Djmax5b,
+    Djmax6b,
+    // 6ボタン+サイドトラックの左右
+    Djmax8b,
+}
+
+impl KeyLayoutId {
+    pub const ALL: [KeyLayoutId; 11] = [
+        KeyLayoutId::IidxSp,
+        KeyLayoutId::IidxDp,
+        KeyLayoutId::FiveKey,
+        KeyLayoutId::Popn9,
+        KeyLayoutId::Mania4k,
+        KeyLayoutId::Mania6k,
+        KeyLayoutId::Mania7k,
+        KeyLayoutId::Djmax4b,
+        KeyLayoutId::Djmax5b,
+        KeyLayoutId::Djmax6b,
+        KeyLayoutId::Djmax8b,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TS)]
+#[serde(rename_all = "lowercase")]
+#[ts(export)]
+pub enum LaneKind {
+    Key,
+    Scratch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TS)]
+#[serde(rename_all = "lowercase")]
+#[ts(export)]
+pub enum LaneColor {
+    White,
+    Blue,
+    Red,
+    Yellow,
+    Green,
+}
+
+// レーンに対応する入力
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TS)]
+#[serde(tag = "type", rename_all = "lowercase")]
+#[ts(export)]
+pub enum LaneInput {
+    Button {
+        code: u32,
+    },
+    // codeがNoneの場合はどの軸でもよい
+    Axis {
+        #[ts(optional)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        code: Option<u32>,
+    },
+}
+
+// 位置と大きさは鍵盤の半分の幅・高さを1とした単位。yは上から
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Lane {
+    pub index: u32,
+    pub kind: LaneKind,
+    pub color: LaneColor,
+    pub input: LaneInput,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct KeyLayout {
+    pub id: KeyLayoutId,
+    pub name: String,
+    // 左から順に並ぶ。イベントのlaneはこの添字
+    pub lanes: Vec<Lane>,
+}
+
+// 1P側のIIDXのような互い違いの鍵盤。奇数番目（0始まり）が上の段
+fn staggered_keys(codes: std::ops::Range<u32>, x: u32, colors: &[LaneColor]) -> Vec<Lane> {
+    codes
+        .enumerate()
+        .map(|(i, code)| Lane {
+            index: 0,
+            kind: LaneKind::Key,
+            color: colors[i],
+            input: LaneInput::Button { code },
+            x: x + i as u32,
+            y: if i % 2 == 1 { 0 } else { 2 },
+            width: 2,
+            height: 2,
+        })
+        .collect()
+}
+
+// 横一列に並ぶ鍵盤
+fn flat_keys(codes: std::ops::Range<u32>, colors: &[LaneColor]) -> Vec<Lane> {
+    codes
+        .enumerate()
+        .map(|(i, code)| Lane {
+            index: 0,
+            kind: LaneKind::Key,
+            color: colors[i],
+            input: LaneInput::Button { code },
+            x: 2 * i as u32,
+            y: 0,
+            width: 2,
+            height: 2,
+        })
+        .collect()
+}
+
+fn scratch(code: Option<u32>, x: u32) -> Lane {
+    Lane {
+        index: 0,
+        kind: LaneKind::Scratch,
+        color: Red,
+        input: LaneInput::Axis { code },
+        x,
+        y: 0,
+        width: 3,
+        height: 4,
+    }
+}
+
+fn side_track(code: u32, x: u32) -> Lane {
+    Lane {
+        index: 0,
+        kind: LaneKind::Key,
+        color: Red,
+        input: LaneInput::Button { code },
+        x,
+        y: 2,
+        width: 6,
+        height: 1,
+    }
+}
+
+const IIDX_COLORS: [LaneColor; 7] = [White, Blue, White, Blue, White, Blue, White];
+
+impl KeyLayout {
+    // 鍵盤はボタン0から順に割り当てる。皿が1つのレイアウトはどの軸でも皿とする
+    pub fn new(id: KeyLayoutId) -> KeyLayout {
+        let (name, mut lanes) = match id {
+            KeyLayoutId::IidxSp => {
+                let mut lanes = vec![scratch(None, 0)];
+                lanes.extend(staggered_keys(0..7, 4, &IIDX_COLORS));
+                ("IIDX SP (7+1)", lanes)
+            }
+            KeyLayoutId::IidxDp => {
+                let mut lanes = vec![scratch(Some(0), 0)];
+                lanes.extend(staggered_keys(0..7, 4, &IIDX_COLORS));
+                lanes.extend(staggered_keys(7..14, 14, &IIDX_COLORS));
+                lanes.push(scratch(Some(1), 23));
+                ("IIDX DP (14+2)", lanes)
+            }
+            KeyLayoutId::FiveKey => {
+                let mut lanes = vec![scratch(None, 0)];
+                lanes.extend(staggered_keys(0..5, 4, &IIDX_COLORS[..5]));
+                ("5 keys (5+1)", lanes)
+            }
+            KeyLayoutId::Popn9 => (
+                "pop'n 9 buttons",
+                staggered_keys(
+                    0..9,
+                    0,
+                    &[White, Yellow, Green, Blue, Red, Blue, Green, Yellow, White],
+                ),
+            ),
+            KeyLayoutId::Mania4k => ("4K", flat_keys(0..4, &[White, Blue, Blue, White])),
+            KeyLayoutId::Mania6k => (
+                "6K",
+                flat_keys(0..6, &[White, Blue, White, White, Blue, White]),
+            ),
+            KeyLayoutId::Mania7k => (
+                "7K",
+                flat_keys(0..7, &[White, Blue, White, Yellow, White, Blue, White]),
+            ),
+            KeyLayoutId::Djmax4b => ("DJMAX 4B", flat_keys(0..4, &[White, Yellow, Yellow, White])),
+            KeyLayoutId::Djmax5b => (
+                "DJMAX 5B",
+                flat_keys(0..5, &[White, Yellow, Blue, Yellow, White]),
+            ),
+            KeyLayoutId::Djmax6b => (
+                "DJMAX 6B",
+                flat_keys(0..6, &[White, Yellow, White, White, Yellow, White]),
+            ),
+            KeyLayoutId::Djmax8b => {
+                let mut lanes = flat_keys(0..6, &[White, Yellow, White, White, Yellow, White]);
+                lanes.extend([side_track(6, 0), side_track(7, 6)]);
+                ("DJMAX 8B", lanes)
+            }
+        };
+
+        for (index, lane) in lanes.iter_mut().enumerate() {
+            lane.index = index as u32;
+        }
+        KeyLayout {
+            id,
+            name: name.to_string(),
+            lanes,
+        }
+    }
+
+    pub fn all() -> Vec<KeyLayout> {
+        KeyLayoutId::ALL.into_iter().map(KeyLayout::new).collect()
+    }
+
+    fn find(&self, input: LaneInput) -> Option<u32> {
+        self.lanes
+            .iter()
+            .find(|lane| lane.input == input)
+            .map(|lane| lane.index)
+    }
+
+    // 入力が対応するレーン。軸はコードが一致するレーンを優先する
+    pub fn lane_of(&self, input: &RawInput) -> Option<u32> {
+        match *input {
+            RawInput::ButtonPressed(code) | RawInput::ButtonReleased(code) => {
+                self.find(LaneInput::Button { code })
+            }
+            RawInput::AxisChanged { code, .. } => self
+                .find(LaneInput::Axis { code: Some(code) })
+                .or_else(|| self.find(LaneInput::Axis { code: None })),
+            RawInput::Connected | RawInput::Disconnected => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lane_of() {
+        let sp = KeyLayout::new(KeyLayoutId::IidxSp);
+        assert_eq!(sp.lanes.len(), 8);
+        assert_eq!(sp.lane_of(&RawInput::ButtonPressed(0)), Some(1));
+        assert_eq!(sp.lane_of(&RawInput::ButtonReleased(6)), Some(7));
+        assert_eq!(sp.lane_of(&RawInput::ButtonPressed(7)), None);
+        assert_eq!(
+            sp.lane_of(&RawInput::AxisChanged { code: 5, value: 1 }),
+            Some(0)
+        );
+
+        let dp = KeyLayout::new(KeyLayoutId::IidxDp);
+        assert_eq!(dp.lanes.len(), 16);
+        assert_eq!(dp.lane_of(&RawInput::ButtonPressed(7)), Some(8));
+        assert_eq!(
+            dp.lane_of(&RawInput::AxisChanged { code: 1, value: 1 }),
+            Some(15)
+        );
+        assert_eq!(
+            dp.lane_of(&RawInput::AxisChanged { code: 2, value: 1 }),
+            None
+        );
+
+        let mania = KeyLayout::new(KeyLayoutId::Mania4k);
+        assert_eq!(mania.lane_of(&RawInput::ButtonPressed(3)), Some(3));
+        assert_eq!(
+            mania.lane_of(&RawInput::AxisChanged { code: 0, value: 1 }),
+            None
+        );
+    }
+
+    #[test]
+    fn test_all_layouts() {
+        let layouts = KeyLayout::all();
+        assert_eq!(layouts.len(), KeyLayoutId::ALL.len());
+        for layout in &layouts {
+            for (index, lane) in layout.lanes.iter().enumerate() {
+                assert_eq!(lane.index, index as u32);
+            }
+        }
+        assert_eq!(
+            serde_json::to_value(&layouts[0].lanes[1]).unwrap(),
+            serde_json::json!({
+                "index": 1, "kind": "key", "color": "white",
+                "input": { "type": "button", "code": 0 },
+                "x": 4, "y": 2, "width": 2, "height": 2
+            })
+        );
+    }
+}
@@ -1,48 +1,98 @@
-use gilrs_core::{Event, EventType, Gilrs};
-use std::fmt::format;
+use chrono::{DateTime, Local, NaiveDate};
+use gilrs_core::{Event, Gilrs};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::thread;
+use std::thread::{self, JoinHandle};
 use std::time::Duration;
-use tauri::{AppHandle, Emitter};
+use std::time::Instant;
 
 use super::error::GamepadError;
+use super::event::{
+    GamepadInputEvent, GoalReachedEvent, ReplayFinishedEvent, StatsEvent, GAMEPAD_INPUT,
+    GOAL_REACHED, REPLAY_FINISHED, STATS,
+};
+use super::input::RawInput;
+use super::layout::KeyLayoutId;
+use super::osc::{OscConfig, OscSender};
+use super::recorder::{Recorder, Recording};
+use super::replay::{self, ReplayOptions};
 use super::scratch::Direction;
+use super::session::PlaySession;
+use super::statistics::PendingCounts;
 use super::status::GamepadStatus;
+use crate::event_hub::EventHub;
 
 pub struct GamepadManager {
     gilrs: Arc<Mutex<Gilrs>>,
     active_gamepad: Arc<Mutex<Option<usize>>>,
     status: Arc<Mutex<GamepadStatus>>,
     is_running: Arc<AtomicBool>,
+    save_requested: Arc<AtomicBool>,
+    handles: Mutex<Vec<JoinHandle<()>>>,
+    event_hub: EventHub,
+    outputs: Arc<Outputs>,
+    replay: Mutex<Option<ReplayHandle>>,
 }
 
+// 再生中のリプレイ
+struct ReplayHandle {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+// 入力の処理とは別に、有効な場合だけ入力を渡す先
+#[derive(Debug, Default)]
+struct Outputs {
+    // 入力をOSCでも送る
+    osc: Mutex<Option<OscSender>>,
+    // すべての入力をファイルに記録する
+    recorder: Mutex<Option<Recorder>>,
+}
+
+// statsイベントを送る間隔
+const STATS_INTERVAL: Duration = Duration::from_millis(250);
+
 impl GamepadManager {
-    pub fn new() -> Result<GamepadManager, GamepadError> {
+    pub fn new(event_hub: EventHub) -> Result<GamepadManager, GamepadError> {
         let gilrs = Gilrs::new()
-            .map_err(|e| GamepadError::GilrsError(format!("Failed to initialize Gilrs: {}", e)))?;
+            .map_err(|e| GamepadError::Gilrs(format!("Failed to initialize Gilrs: {}", e)))?;
 
         Ok(GamepadManager {
             gilrs: Arc::new(Mutex::new(gilrs)),
             active_gamepad: Arc::new(Mutex::new(None)),
             status: Arc::new(Mutex::new(GamepadStatus::new())),
             is_running: Arc::new(AtomicBool::new(true)),
+            save_requested: Arc::new(AtomicBool::new(false)),
+            handles: Mutex::new(Vec::new()),
+            event_hub,
+            outputs: Arc::new(Outputs::default()),
+            replay: Mutex::new(None),
         })
     }
 
-    pub fn start_event_loop(&self, app_handle: AppHandle) -> Result<(), GamepadError> {
+    pub fn start_event_loop(&self) -> Result<(), GamepadError> {
         let gilrs = Arc::clone(&self.gilrs);
         let active_gamepad = Arc::clone(&self.active_gamepad);
         let status = Arc::clone(&self.status);
         let is_running = Arc::clone(&self.is_running);
+        let save_requested = Arc::clone(&self.save_requested);
 
-        let main_handle = app_handle.clone();
-        let sub_handle = app_handle.clone();
+        let main_hub = self.event_hub.clone();
+        let sub_hub = self.event_hub.clone();
+        let main_outputs = Arc::clone(&self.outputs);
+        let sub_outputs = Arc::clone(&self.outputs);
 
-        thread::spawn(move || {
-            if let Err(e) =
-                Self::run_main_loop(gilrs, active_gamepad, status, main_handle, is_running)
-            {
+        let main_thread = thread::spawn(move || {
+            if let Err(e) = Self::run_main_loop(
+                gilrs,
+                active_gamepad,
+                status,
+                main_hub,
+                main_outputs,
+                is_running,
+                save_requested,
+            ) {
                 eprintln!("Main loop error: {}", e);
             }
         });
@@ -50,11 +100,19 @@ impl GamepadManager {
         // スクラッチタイムアウト用のスレッド
         let status = Arc::clone(&self.status);
         let is_running = Arc::clone(&self.is_running);
-        thread::spawn(move || {
-            if let Err(e) = Self::run_scratch_timeout_loop(status, sub_handle, is_running) {
+        let timeout_thread = thread::spawn(move || {
+            if let Err(e) = Self::run_scratch_timeout_loop(status, sub_hub, sub_outputs, is_running)
+            {
                 eprintln!("Scratch timeout loop error: {}", e);
             }
         });
+
+        let mut handles = self
+            .handles
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock handles: {}", e)))?;
+        handles.push(main_thread);
+        handles.push(timeout_thread);
         Ok(())
     }
 
@@ -62,153 +120,886 @@ impl GamepadManager {
         gilrs: Arc<Mutex<Gilrs>>,
         active_gamepad: Arc<Mutex<Option<usize>>>,
         status: Arc<Mutex<GamepadStatus>>,
-        app_handle: AppHandle,
+        event_hub: EventHub,
+        outputs: Arc<Outputs>,
         is_running: Arc<AtomicBool>,
+        save_requested: Arc<AtomicBool>,
     ) -> Result<(), GamepadError> {
-        while is_running.load(Ordering::Relaxed) {
+        loop {
+            // 停止後も、それまでに届いていたイベントは処理してから終了する
+            let stopping = !is_running.load(Ordering::Relaxed);
             let mut gilrs = gilrs
                 .lock()
-                .map_err(|e| GamepadError::LockError(format!("Failed to lock gilrs: {}", e)))?;
+                .map_err(|e| GamepadError::Lock(format!("Failed to lock gilrs: {}", e)))?;
 
-            while let Some(Event { id, event, .. }) = gilrs.next_event() {
-                if !is_running.load(Ordering::Relaxed) {
-                    break;
-                }
+            while let Some(Event { id, event, time }) = gilrs.next_event() {
+                let Some(input) = RawInput::from_event(event) else {
+                    continue;
+                };
 
                 // アクティブゲームパッドを更新
                 if let Ok(mut active) = active_gamepad.lock() {
                     *active = Some(id);
                 }
 
-                if let Err(e) = Self::handle_gamepad_event(event, &status, &app_handle) {
+                // 切断時は未保存の統計を保存してもらう
+                if input == RawInput::Disconnected {
+                    save_requested.store(true, Ordering::Relaxed);
+                }
+
+                let time = DateTime::from(time);
+                let lane = status
+                    .lock()
+                    .ok()
+                    .and_then(|status| status.layout().lane_of(&input));
+                Self::record_input(&outputs.recorder, time, id, input, lane);
+
+                if let Err(e) =
+                    Self::handle_gamepad_event(input, time, &status, &event_hub, &outputs.osc)
+                {
                     eprintln!("Event handling error: {}", e);
                 }
             }
 
+            if stopping {
+                break;
+            }
+            drop(gilrs);
             thread::sleep(Duration::from_millis(8));
         }
         Ok(())
     }
 
+    // OSCが有効なら送る。送信の失敗で入力の処理は止めない
+    fn send_osc(
+        osc: &Mutex<Option<OscSender>>,
+        send: impl FnOnce(&OscSender) -> Result<(), GamepadError>,
+    ) {
+        if let Ok(osc) = osc.lock() {
+            if let Some(Err(e)) = osc.as_ref().map(send) {
+                eprintln!("Failed to send OSC: {}", e);
+            }
+        }
+    }
+
+    // 記録が有効なら書き込む。書き込みの失敗で入力の処理は止めない
+    fn record_input(
+        recorder: &Mutex<Option<Recorder>>,
+        time: DateTime<Local>,
+        device: usize,
+        input: RawInput,
+        lane: Option<u32>,
+    ) {
+        if let Ok(mut recorder) = recorder.lock() {
+            if let Some(Err(e)) = recorder
+                .as_mut()
+                .map(|recorder| recorder.record(time, device, input, lane))
+            {
+                eprintln!("Failed to record input: {}", e);
+            }
+        }
+    }
+
+    // timeは入力の時刻。リリースタイム・NPS・セッション・日付はこの時刻で計算する
     fn handle_gamepad_event(
-        event: EventType,
+        input: RawInput,
+        time: DateTime<Local>,
         status: &Arc<Mutex<GamepadStatus>>,
-        app_handle: &AppHandle,
+        event_hub: &EventHub,
+        osc: &Mutex<Option<OscSender>>,
     ) -> Result<(), GamepadError> {
         let mut status = status
             .lock()
-            .map_err(|e| GamepadError::LockError(format!("Failed to lock status: {}", e)))?;
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+        let lane = status.layout().lane_of(&input);
 
-        match event {
-            EventType::ButtonPressed(button) => {
-                let button_code = button.into_u32();
-                let count = status.on_button_press(button_code)?;
+        match input {
+            RawInput::ButtonPressed(button_code) => {
+                let count = status.on_button_press(button_code, time)?;
+                if let Some(lane) = lane {
+                    Self::send_osc(osc, |osc| osc.send_lane(lane, true));
+                }
 
-                let event = serde_json::json!({
-                    "type": "button",
-                    "button": button_code,
-                    "pressed": true,
-                    "count": count,
-                });
+                let event = GamepadInputEvent::Button {
+                    button: button_code,
+                    lane,
+                    pressed: true,
+                    count,
+                    average_release_time: None,
+                };
 
-                app_handle.emit("gamepad-input", &event).map_err(|e| {
-                    GamepadError::EmitError(format!("Failed to emit button press: {}", e))
+                event_hub.emit(GAMEPAD_INPUT, &event).map_err(|e| {
+                    GamepadError::Emit(format!("Failed to emit button press: {}", e))
                 })?;
             }
-            EventType::ButtonReleased(button) => {
-                let button_code = button.into_u32();
-                let (count, avg_release_time) = status.on_button_release(button_code)?;
+            RawInput::ButtonReleased(button_code) => {
+                let (count, avg_release_time) = status.on_button_release(button_code, time)?;
+                if let Some(lane) = lane {
+                    Self::send_osc(osc, |osc| osc.send_lane(lane, false));
+                }
 
-                let event = serde_json::json!({
-                    "type": "button",
-                    "button": button_code,
-                    "pressed": false,
-                    "count": count,
-                    "averageReleaseTime": avg_release_time.as_millis(),
-                });
+                let event = GamepadInputEvent::Button {
+                    button: button_code,
+                    lane,
+                    pressed: false,
+                    count,
+                    average_release_time: Some(avg_release_time.as_millis() as u32),
+                };
 
-                app_handle.emit("gamepad-input", &event).map_err(|e| {
-                    GamepadError::EmitError(format!("Failed to emit button release: {}", e))
+                event_hub.emit(GAMEPAD_INPUT, &event).map_err(|e| {
+                    GamepadError::Emit(format!("Failed to emit button release: {}", e))
                 })?;
             }
-            EventType::AxisValueChanged(axis, _) => {
-                let (direction, count) = status.on_scratch_spin(axis)?;
+            // レイアウトで皿に割り当てていない軸は数えない
+            RawInput::AxisChanged { value: axis, .. } => {
+                let Some(lane) = lane else {
+                    return Ok(());
+                };
+                let (direction, count) = status.on_scratch_spin(axis, lane, time)?;
+                // 速さは向きが変わらなくても送る
+                if let Some(scratch) = status.scratch_event(lane) {
+                    Self::send_osc(osc, |osc| {
+                        osc.send_scratch(scratch.direction(), scratch.velocity())
+                    });
+                }
 
                 if let Some(direction) = direction {
-                    let event = serde_json::json!({
-                        "type": "scratch",
-                        "axis": axis,
-                        "direction": direction.as_str(),
-                        "count": count,
-                    });
+                    let event = GamepadInputEvent::Scratch {
+                        lane: Some(lane),
+                        axis,
+                        direction,
+                        count,
+                    };
 
-                    app_handle.emit("gamepad-input", &event).map_err(|e| {
-                        GamepadError::EmitError(format!("Failed to emit scratch: {}", e))
+                    event_hub.emit(GAMEPAD_INPUT, &event).map_err(|e| {
+                        GamepadError::Emit(format!("Failed to emit scratch: {}", e))
                     })?;
                 }
             }
             _ => {}
         }
+
+        // 今日の目標を達成したら通知する
+        if let Some((date, notes_count, goal)) = status.check_daily_goal(time) {
+            let event = GoalReachedEvent {
+                date: date.format("%Y-%m-%d").to_string(),
+                notes_count,
+                goal,
+            };
+
+            event_hub
+                .emit(GOAL_REACHED, &event)
+                .map_err(|e| GamepadError::Emit(format!("Failed to emit goal reached: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    // 入力が途切れた皿をそれぞれニュートラルに戻す
+    fn reset_scratch_if_timeout(
+        status: &mut GamepadStatus,
+        now: DateTime<Local>,
+        event_hub: &EventHub,
+        osc: &Mutex<Option<OscSender>>,
+    ) -> Result<(), GamepadError> {
+        for (lane, axis) in status.reset_timed_out_scratches(now) {
+            Self::send_osc(osc, |osc| osc.send_scratch(&Direction::Neutral, 0.0));
+            let event = GamepadInputEvent::Scratch {
+                lane: Some(lane),
+                axis,
+                direction: Direction::Neutral,
+                count: status.notes_count(),
+            };
+
+            event_hub
+                .emit(GAMEPAD_INPUT, &event)
+                .map_err(|e| GamepadError::Emit(format!("Failed to emit timeout: {}", e)))?;
+        }
         Ok(())
     }
 
     fn run_scratch_timeout_loop(
         status: Arc<Mutex<GamepadStatus>>,
-        app_handle: AppHandle,
+        event_hub: EventHub,
+        outputs: Arc<Outputs>,
         is_running: Arc<AtomicBool>,
     ) -> Result<(), GamepadError> {
+        let mut last_stats: Option<StatsEvent> = None;
+        let mut last_nps: Option<u32> = None;
+        let mut last_stats_time = Instant::now();
+
         while is_running.load(Ordering::Relaxed) {
             thread::sleep(Duration::from_millis(10));
 
             let mut status = status.lock().map_err(|e| {
-                GamepadError::LockError(format!("Failed to lock status in timeout: {}", e))
+                GamepadError::Lock(format!("Failed to lock status in timeout: {}", e))
             })?;
 
-            if status.scratch_event().is_timeout() {
-                if status.scratch_event_mut().reset_to_neutral() {
-                    let event = serde_json::json!({
-                        "type": "scratch",
-                        "axis": status.scratch_event().axis(),
-                        "direction": "neutral",
-                        "count": status.notes_count(),
-                    });
+            let now = Local::now();
+            status.session_tracker_mut().close_if_idle(now);
+            if let Ok(mut recorder) = outputs.recorder.lock() {
+                if let Some(Err(e)) = recorder
+                    .as_mut()
+                    .map(|recorder| recorder.close_if_idle(now))
+                {
+                    eprintln!("Failed to write recording: {}", e);
+                }
+            }
 
-                    app_handle.emit("gamepad-input", &event).map_err(|e| {
-                        GamepadError::EmitError(format!("Failed to emit timeout: {}", e))
-                    })?;
+            Self::reset_scratch_if_timeout(&mut status, now, &event_hub, &outputs.osc)?;
+
+            // NPSは入力がなくても時間とともに下がるため、ここで変化を確認する
+            let nps = status.nps();
+            if last_nps != Some(nps) {
+                Self::send_osc(&outputs.osc, |osc| osc.send_nps(nps));
+                last_nps = Some(nps);
+            }
+
+            // 集計値が変わっていればstatsイベントを送る
+            if last_stats_time.elapsed() >= STATS_INTERVAL {
+                last_stats_time = Instant::now();
+                let stats = StatsEvent {
+                    date: status.today().format("%Y-%m-%d").to_string(),
+                    today_count: status.today_count(),
+                    count: status.notes_count(),
+                    average_release_time: status.average_release_time().as_millis() as u32,
+                };
+
+                if last_stats.as_ref() != Some(&stats) {
+                    if let Err(e) = event_hub.emit(STATS, &stats) {
+                        eprintln!("Failed to emit stats: {}", e);
+                    }
+                    last_stats = Some(stats);
                 }
             }
         }
         Ok(())
     }
 
-    pub fn notes_count(&self) -> Result<u32, GamepadError> {
+    // 記録した時刻の間隔をspeedで割った間隔で入力を処理する。再生した入力の数を返す。
+    // 統計は再生した時刻ではなく記録した時刻で計算するため、速さによらず記録時と同じになる
+    fn run_replay_loop(
+        recording: &Recording,
+        speed: f64,
+        status: &Arc<Mutex<GamepadStatus>>,
+        event_hub: &EventHub,
+        osc: &Mutex<Option<OscSender>>,
+        stop: &AtomicBool,
+    ) -> Result<u32, GamepadError> {
+        let start = Instant::now();
+        let mut replayed = 0;
+
+        for recorded in &recording.inputs {
+            let due = recorded.offset.div_f64(speed);
+            loop {
+                if stop.load(Ordering::Relaxed) {
+                    return Ok(replayed);
+                }
+                let elapsed = start.elapsed();
+                if elapsed >= due {
+                    break;
+                }
+                thread::sleep((due - elapsed).min(Duration::from_millis(10)));
+
+                // 待っている間も記録上の時刻で皿のタイムアウトを確認する
+                let now = recording.time_at(start.elapsed().mul_f64(speed).min(recorded.offset));
+                let mut status = status.lock().map_err(|e| {
+                    GamepadError::Lock(format!("Failed to lock status in replay: {}", e))
+                })?;
+                Self::reset_scratch_if_timeout(&mut status, now, event_hub, osc)?;
+            }
+
+            let time = recording.time_at(recorded.offset);
+            if let Err(e) = Self::handle_gamepad_event(recorded.input, time, status, event_hub, osc)
+            {
+                eprintln!("Event handling error: {}", e);
+            }
+            replayed += 1;
+        }
+        Ok(replayed)
+    }
+
+    // statusで計算し直した統計をreplay-finishedイベントで送る。
+    // 記録した入力は記録時にすでに数えているため、データベースには保存しない
+    fn run_replay(
+        recording: Recording,
+        options: ReplayOptions,
+        status: GamepadStatus,
+        event_hub: &EventHub,
+        osc: &Mutex<Option<OscSender>>,
+        stop: &AtomicBool,
+    ) -> Result<ReplayFinishedEvent, GamepadError> {
+        let status = Arc::new(Mutex::new(status));
+        let inputs =
+            Self::run_replay_loop(&recording, options.speed, &status, event_hub, osc, stop)?;
+
+        let mut status = status
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+        status.session_tracker_mut().finish();
+        let sessions = status.take_finished_sessions();
+        let summary = replay::summarize(&sessions, inputs, stop.load(Ordering::Relaxed));
+
+        event_hub
+            .emit(REPLAY_FINISHED, &summary)
+            .map_err(|e| GamepadError::Emit(format!("Failed to emit replay finished: {}", e)))?;
+        Ok(summary)
+    }
+
+    // 記録した入力をライブの入力と同じ処理で再生し、表示やオーバーレイを動かす。
+    // 統計は同じ設定の別の状態で計算するため、ライブの入力のカウントとは混ざらない
+    pub fn start_replay(
+        &self,
+        recording: Recording,
+        options: ReplayOptions,
+    ) -> Result<(), GamepadError> {
+        let mut replay = self
+            .replay
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock replay: {}", e)))?;
+        if replay
+            .as_ref()
+            .is_some_and(|replay| !replay.handle.is_finished())
+        {
+            return Err(GamepadError::Replay(
+                "A replay is already running".to_string(),
+            ));
+        }
+        if let Some(finished) = replay.take() {
+            let _ = finished.handle.join();
+        }
+
+        let status = {
+            let live_status = self
+                .status
+                .lock()
+                .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+            live_status.with_same_settings()
+        };
+        let event_hub = self.event_hub.clone();
+        let outputs = Arc::clone(&self.outputs);
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+
+        let handle = thread::spawn(move || {
+            if let Err(e) = Self::run_replay(
+                recording,
+                options,
+                status,
+                &event_hub,
+                &outputs.osc,
+                &thread_stop,
+            ) {
+                eprintln!("Replay error: {}", e);
+            }
+        });
+        *replay = Some(ReplayHandle { stop, handle });
+        Ok(())
+    }
+
+    // 再生中のリプレイを止め、終了を待つ
+    pub fn stop_replay(&self) -> Result<(), GamepadError> {
+        let replay = self
+            .replay
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock replay: {}", e)))?
+            .take();
+        if let Some(replay) = replay {
+            replay.stop.store(true, Ordering::Relaxed);
+            replay
+                .handle
+                .join()
+                .map_err(|_| GamepadError::Thread("Replay thread panicked".to_string()))?;
+        }
+        Ok(())
+    }
+
+    pub fn set_day_rollover_hour(&self, rollover_hour: u32) -> Result<(), GamepadError> {
+        let mut status = self
+            .status
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+        status.statistics_mut().set_rollover_hour(rollover_hour);
+        Ok(())
+    }
+
+    pub fn set_session_idle_gap(&self, idle_gap: Duration) -> Result<(), GamepadError> {
+        let mut status = self
+            .status
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+        status.session_tracker_mut().set_idle_gap(idle_gap);
+        drop(status);
+
+        let mut recorder = self
+            .outputs
+            .recorder
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock recorder: {}", e)))?;
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.set_idle_gap(idle_gap);
+        }
+        Ok(())
+    }
+
+    // Noneの場合はOSCの送信を止める。送信先とアドレスが同じならそのまま使う
+    pub fn set_osc(&self, config: Option<OscConfig>) -> Result<(), GamepadError> {
+        let mut osc = self
+            .outputs
+            .osc
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock osc: {}", e)))?;
+        if osc.as_ref().map(|sender| sender.config()) == config.as_ref() {
+            return Ok(());
+        }
+        *osc = config.map(OscSender::new).transpose()?;
+        Ok(())
+    }
+
+    // Noneの場合は記録を止める。記録先が同じならそのまま記録を続ける
+    pub fn set_recording(&self, dir: Option<PathBuf>) -> Result<(), GamepadError> {
+        let idle_gap = {
+            let status = self
+                .status
+                .lock()
+                .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+            status.session_tracker().idle_gap()
+        };
+        let mut recorder = self
+            .outputs
+            .recorder
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock recorder: {}", e)))?;
+        if recorder.as_ref().map(|recorder| recorder.dir()) == dir.as_deref() {
+            return Ok(());
+        }
+        if let Some(mut previous) = recorder.take() {
+            previous.finish()?;
+        }
+        *recorder = dir.map(|dir| {
+            let mut recorder = Recorder::new(dir);
+            recorder.set_idle_gap(idle_gap);
+            recorder
+        });
+        Ok(())
+    }
+
+    pub fn set_key_layout(&self, id: KeyLayoutId) -> Result<(), GamepadError> {
+        let mut status = self
+            .status
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+        status.set_layout(id);
+        Ok(())
+    }
+
+    pub fn set_scratch_timeout(&self, timeout: Duration) -> Result<(), GamepadError> {
+        let mut status = self
+            .status
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+        status.set_scratch_timeout(timeout);
+        Ok(())
+    }
+
+    // 日付変更時刻を考慮した今日の日付
+    pub fn today(&self) -> Result<NaiveDate, GamepadError> {
         let status = self
             .status
             .lock()
-            .map_err(|e| GamepadError::LockError(format!("Failed to lock status: {}", e)))?;
-        Ok(status.notes_count())
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+        Ok(status.today())
+    }
+
+    // 今日の日付と、今日の未保存のノーツ数
+    pub fn today_pending_count(&self) -> Result<(NaiveDate, u32), GamepadError> {
+        let status = self
+            .status
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+        let today = status.today();
+        Ok((today, status.pending_count(today)))
+    }
+
+    pub fn set_daily_goal(&self, daily_goal: Option<u32>) -> Result<(), GamepadError> {
+        let mut status = self
+            .status
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+        status.goal_tracker_mut().set_daily_goal(daily_goal);
+        Ok(())
+    }
+
+    // データベースに保存済みのその日のノーツ数を目標判定に反映する
+    pub fn set_saved_count(&self, date: NaiveDate, saved_count: u32) -> Result<(), GamepadError> {
+        let mut status = self
+            .status
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+        status.goal_tracker_mut().set_saved_count(date, saved_count);
+        Ok(())
+    }
+
+    pub fn take_pending_counts(&self) -> Result<PendingCounts, GamepadError> {
+        let mut status = self
+            .status
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+        Ok(status.statistics_mut().take_pending_counts())
+    }
+
+    // 保存に失敗した分を次回の保存まで保持する
+    pub fn restore_pending_counts(&self, counts: PendingCounts) -> Result<(), GamepadError> {
+        let mut status = self
+            .status
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+        status.statistics_mut().restore_pending_counts(counts);
+        Ok(())
     }
 
-    pub fn difference_notes_count(&self) -> Result<u32, GamepadError> {
+    // 削除・修正した日付の未保存のノーツ数を捨てる。残すと次の自動保存で加算されてしまう
+    pub fn discard_pending_counts(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<(), GamepadError> {
+        let mut status = self
+            .status
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+        status.statistics_mut().discard_pending_counts(start, end);
+        Ok(())
+    }
+
+    // 削除した日付に開始した未保存のセッションを捨てる
+    pub fn discard_finished_sessions(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<(), GamepadError> {
+        let mut status = self
+            .status
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+        let rollover_hour = status.statistics().rollover_hour();
+        status
+            .session_tracker_mut()
+            .discard_finished(start, end, rollover_hour);
+        Ok(())
+    }
+
+    pub fn day_rollover_hour(&self) -> Result<u32, GamepadError> {
         let status = self
             .status
             .lock()
-            .map_err(|e| GamepadError::LockError(format!("Failed to lock status: {}", e)))?;
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+        Ok(status.statistics().rollover_hour())
+    }
+
+    // 進行中のセッションを終了させる（アプリ終了時など）
+    pub fn end_session(&self) -> Result<(), GamepadError> {
+        let mut status = self
+            .status
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+        status.session_tracker_mut().finish();
+        drop(status);
+
+        let mut recorder = self
+            .outputs
+            .recorder
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock recorder: {}", e)))?;
+        match recorder.as_mut() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
+        }
+    }
 
-        Ok(status.difference_notes_count())
+    pub fn take_finished_sessions(&self) -> Result<Vec<PlaySession>, GamepadError> {
+        let mut status = self
+            .status
+            .lock()
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+        Ok(status.take_finished_sessions())
     }
 
-    pub fn update_last_saved_count(&mut self) -> Result<(), GamepadError> {
+    // 保存に失敗したセッションを次回の保存まで保持する
+    pub fn requeue_sessions(&self, sessions: Vec<PlaySession>) -> Result<(), GamepadError> {
         let mut status = self
             .status
             .lock()
-            .map_err(|e| GamepadError::LockError(format!("Failed to lock status: {}", e)))?;
-        status.set_last_saved_count();
+            .map_err(|e| GamepadError::Lock(format!("Failed to lock status: {}", e)))?;
+        status.session_tracker_mut().requeue(sessions);
         Ok(())
     }
 
-    pub fn shutdown(&self) {
+    pub fn take_save_request(&self) -> bool {
+        self.save_requested.swap(false, Ordering::Relaxed)
+    }
+
+    // 入力の処理を止め、スレッドの終了を待つ。以降はGamepadStatusが変更されないため、この後に保存すれば取りこぼしも二重計上もない
+    pub fn shutdown(&self) -> Result<(), GamepadError> {
         self.is_running.store(false, Ordering::Relaxed);
+        self.stop_replay()?;
+
+        let handles = {
+            let mut handles = self
+                .handles
+                .lock()
+                .map_err(|e| GamepadError::Lock(format!("Failed to lock handles: {}", e)))?;
+            std::mem::take(&mut *handles)
+        };
+
+        let mut panicked = 0;
+        for handle in handles {
+            if handle.join().is_err() {
+                panicked += 1;
+            }
+        }
+        if panicked > 0 {
+            return Err(GamepadError::Thread(format!(
+                "{} input thread(s) panicked",
+                panicked
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_hub::EventSink;
+    use crate::gamepad::recorder::RecordedInput;
+    use serde_json::{json, Value};
+    use std::collections::BTreeMap;
+
+    #[derive(Default)]
+    struct CollectingSink {
+        events: Mutex<Vec<(String, Value)>>,
+    }
+
+    impl EventSink for CollectingSink {
+        fn send(&self, event: &str, payload: &Value) -> Result<(), String> {
+            self.events
+                .lock()
+                .unwrap()
+                .push((event.to_string(), payload.clone()));
+            Ok(())
+        }
+    }
+
+    fn recorded(offset_ms: u64, input: RawInput) -> RecordedInput {
+        RecordedInput {
+            offset: Duration::from_millis(offset_ms),
+            device: 0,
+            input,
+            lane: None,
+        }
+    }
+
+    fn test_recording() -> Recording {
+        Recording {
+            version: 1,
+            started_at: Local::now(),
+            inputs: vec![
+                recorded(0, RawInput::ButtonPressed(0)),
+                recorded(40, RawInput::ButtonReleased(0)),
+                recorded(80, RawInput::ButtonPressed(3)),
+                recorded(100, RawInput::AxisChanged { code: 0, value: 10 }),
+                recorded(120, RawInput::ButtonReleased(3)),
+            ],
+        }
+    }
+
+    fn replay(options: ReplayOptions, live_status: &Mutex<GamepadStatus>) -> ReplayFinishedEvent {
+        replay_recording(test_recording(), options, live_status)
+    }
+
+    fn replay_recording(
+        recording: Recording,
+        options: ReplayOptions,
+        live_status: &Mutex<GamepadStatus>,
+    ) -> ReplayFinishedEvent {
+        let sink = Arc::new(CollectingSink::default());
+        let event_hub = EventHub::new();
+        event_hub.add_sink(sink.clone());
+
+        let status = live_status.lock().unwrap().with_same_settings();
+        let summary = GamepadManager::run_replay(
+            recording,
+            options,
+            status,
+            &event_hub,
+            &Mutex::new(None),
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+
+        let events = sink.events.lock().unwrap();
+        let inputs = events
+            .iter()
+            .filter(|(event, _)| event == GAMEPAD_INPUT)
+            .count();
+        assert!(inputs >= 4);
+        assert_eq!(events.last().unwrap().0, REPLAY_FINISHED);
+        summary
+    }
+
+    #[test]
+    fn test_replay_recomputes_statistics_without_saving() {
+        let live_status = Mutex::new(GamepadStatus::new());
+        let summary = replay(ReplayOptions { speed: 16.0 }, &live_status);
+
+        assert_eq!(summary.inputs, 5);
+        assert_eq!(summary.notes_count, 3);
+        assert_eq!(summary.scratch_count, 1);
+        assert_eq!(summary.key_counts, BTreeMap::from([(0, 1), (3, 1)]));
+        assert!(!summary.stopped);
+
+        let mut live_status = live_status.lock().unwrap();
+        assert_eq!(live_status.notes_count(), 0);
+        assert!(live_status
+            .statistics_mut()
+            .take_pending_counts()
+            .daily
+            .is_empty());
+        assert!(live_status.take_finished_sessions().is_empty());
+    }
+
+    #[test]
+    fn test_replay_speed_does_not_change_statistics() {
+        // リリースタイムは40msと150ms。0.5倍で再生しても150msは200msの上限に入る
+        let recording = Recording {
+            version: 1,
+            started_at: Local::now(),
+            inputs: vec![
+                recorded(0, RawInput::ButtonPressed(0)),
+                recorded(40, RawInput::ButtonReleased(0)),
+                recorded(100, RawInput::ButtonPressed(1)),
+                recorded(250, RawInput::ButtonReleased(1)),
+            ],
+        };
+
+        for speed in [2.0, 0.5] {
+            let live_status = Mutex::new(GamepadStatus::new());
+            let summary =
+                replay_recording(recording.clone(), ReplayOptions { speed }, &live_status);
+            assert_eq!(summary.notes_count, 2);
+            assert_eq!(summary.average_release_time, 95, "speed {}", speed);
+        }
+    }
+
+    #[test]
+    fn test_replay_copies_daily_goal() {
+        let live_status = Mutex::new(GamepadStatus::new());
+        live_status
+            .lock()
+            .unwrap()
+            .goal_tracker_mut()
+            .set_daily_goal(Some(2));
+
+        let sink = Arc::new(CollectingSink::default());
+        let event_hub = EventHub::new();
+        event_hub.add_sink(sink.clone());
+        let status = live_status.lock().unwrap().with_same_settings();
+        GamepadManager::run_replay(
+            test_recording(),
+            ReplayOptions { speed: 16.0 },
+            status,
+            &event_hub,
+            &Mutex::new(None),
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+
+        let events = sink.events.lock().unwrap();
+        let goals: Vec<&Value> = events
+            .iter()
+            .filter(|(event, _)| event == GOAL_REACHED)
+            .map(|(_, payload)| payload)
+            .collect();
+        assert_eq!(goals.len(), 1);
+        assert_eq!(goals[0]["notesCount"], json!(2));
+    }
+
+    #[test]
+    fn test_dp_turntables_are_tracked_separately() {
+        let sink = Arc::new(CollectingSink::default());
+        let event_hub = EventHub::new();
+        event_hub.add_sink(sink.clone());
+        let status = Arc::new(Mutex::new(GamepadStatus::new()));
+        status.lock().unwrap().set_layout(KeyLayoutId::IidxDp);
+        let osc = Mutex::new(None);
+
+        // 1P側は左、2P側は右に回し続け、それぞれの軸の値が交互に届く
+        for i in 1..=5 {
+            for input in [
+                RawInput::AxisChanged {
+                    code: 0,
+                    value: i * 100,
+                },
+                RawInput::AxisChanged {
+                    code: 1,
+                    value: -i * 100,
+                },
+            ] {
+                GamepadManager::handle_gamepad_event(
+                    input,
+                    Local::now(),
+                    &status,
+                    &event_hub,
+                    &osc,
+                )
+                .unwrap();
+            }
+        }
+        assert_eq!(status.lock().unwrap().notes_count(), 2);
+
+        // タイムアウトでは両方の皿がニュートラルに戻る
+        let mut status = status.lock().unwrap();
+        status.set_scratch_timeout(Duration::ZERO);
+        GamepadManager::reset_scratch_if_timeout(&mut status, Local::now(), &event_hub, &osc)
+            .unwrap();
+
+        let events: Vec<(Value, Value)> = sink
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, payload)| (payload["lane"].clone(), payload["direction"].clone()))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                (json!(0), json!("left")),
+                (json!(15), json!("right")),
+                (json!(0), json!("neutral")),
+                (json!(15), json!("neutral")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_axis_without_scratch_lane_is_not_counted() {
+        let sink = Arc::new(CollectingSink::default());
+        let event_hub = EventHub::new();
+        event_hub.add_sink(sink.clone());
+        let status = Arc::new(Mutex::new(GamepadStatus::new()));
+        status.lock().unwrap().set_layout(KeyLayoutId::Mania4k);
+
+        for value in [100, -100, 100] {
+            GamepadManager::handle_gamepad_event(
+                RawInput::AxisChanged { code: 0, value },
+                Local::now(),
+                &status,
+                &event_hub,
+                &Mutex::new(None),
+            )
+            .unwrap();
+        }
+        assert_eq!(status.lock().unwrap().notes_count(), 0);
+        assert!(sink.events.lock().unwrap().is_empty());
     }
 }
@@ -1,7 +1,15 @@
 pub mod button;
 pub mod error;
+pub mod event;
+pub mod goal;
+pub mod input;
+pub mod layout;
 pub mod manager;
+pub mod osc;
+pub mod recorder;
+pub mod replay;
 pub mod scratch;
+pub mod session;
 pub mod statistics;
 pub mod status;
 
@@ -0,0 +1,185 @@
+use super::error::GamepadError;
+use super::scratch::Direction;
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+
+pub const DEFAULT_HOST: &str = "127.0.0.1";
+pub const DEFAULT_PORT: u16 = 9000;
+pub const DEFAULT_LANE_ADDRESS: &str = "/keydisplay/lane/{lane}";
+pub const DEFAULT_SCRATCH_ADDRESS: &str = "/keydisplay/scratch";
+pub const DEFAULT_NPS_ADDRESS: &str = "/keydisplay/nps";
+// レーンのアドレスでレーン番号に置き換える文字列
+pub const LANE_PLACEHOLDER: &str = "{lane}";
+
+// OSCの送信先とアドレス
+#[derive(Debug, Clone, PartialEq)]
+pub struct OscConfig {
+    pub host: String,
+    pub port: u16,
+    pub lane_address: String,
+    pub scratch_address: String,
+    pub nps_address: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Float(f32),
+}
+
+// OSCの文字列。NUL終端を含めて4バイト単位になるようNULで埋める
+fn push_padded_str(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(s.as_bytes());
+    let padding = 4 - s.len() % 4;
+    buf.extend(std::iter::repeat_n(0, padding));
+}
+
+pub fn encode_message(address: &str, args: &[OscArg]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    push_padded_str(&mut buf, address);
+
+    let type_tags: String = std::iter::once(',')
+        .chain(args.iter().map(|arg| match arg {
+            OscArg::Int(_) => 'i',
+            OscArg::Float(_) => 'f',
+        }))
+        .collect();
+    push_padded_str(&mut buf, &type_tags);
+
+    for arg in args {
+        match arg {
+            OscArg::Int(value) => buf.extend_from_slice(&value.to_be_bytes()),
+            OscArg::Float(value) => buf.extend_from_slice(&value.to_be_bytes()),
+        }
+    }
+    buf
+}
+
+// OSCのアドレスとして使えるか。パターンマッチに使う文字と空白は使えない
+pub fn is_valid_address(address: &str) -> bool {
+    address.starts_with('/')
+        && !address
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '#' | '*' | ',' | '?' | '[' | ']'))
+}
+
+// 入力をOSCでUDP送信する
+#[derive(Debug)]
+pub struct OscSender {
+    socket: UdpSocket,
+    target: SocketAddr,
+    config: OscConfig,
+}
+
+impl OscSender {
+    pub fn new(config: OscConfig) -> Result<OscSender, GamepadError> {
+        let target = (config.host.as_str(), config.port)
+            .to_socket_addrs()
+            .map_err(|e| GamepadError::Osc(format!("Failed to resolve {}: {}", config.host, e)))?
+            .next()
+            .ok_or_else(|| GamepadError::Osc(format!("No address for {}", config.host)))?;
+        let bind_addr = match target {
+            SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+            SocketAddr::V6(_) => SocketAddr::from((std::net::Ipv6Addr::UNSPECIFIED, 0)),
+        };
+        let socket = UdpSocket::bind(bind_addr)
+            .map_err(|e| GamepadError::Osc(format!("Failed to bind socket: {}", e)))?;
+
+        Ok(OscSender {
+            socket,
+            target,
+            config,
+        })
+    }
+
+    pub fn config(&self) -> &OscConfig {
+        &self.config
+    }
+
+    fn send(&self, address: &str, args: &[OscArg]) -> Result<(), GamepadError> {
+        self.socket
+            .send_to(&encode_message(address, args), self.target)
+            .map_err(|e| GamepadError::Osc(format!("Failed to send {}: {}", address, e)))?;
+        Ok(())
+    }
+
+    // 押したら1、離したら0
+    pub fn send_lane(&self, lane: u32, pressed: bool) -> Result<(), GamepadError> {
+        let address = self
+            .config
+            .lane_address
+            .replace(LANE_PLACEHOLDER, &lane.to_string());
+        self.send(&address, &[OscArg::Int(pressed as i32)])
+    }
+
+    // 向き（左: -1、ニュートラル: 0、右: 1）と速さ（回転/秒）
+    pub fn send_scratch(&self, direction: &Direction, velocity: f32) -> Result<(), GamepadError> {
+        let direction = match direction {
+            Direction::Left => -1,
+            Direction::Neutral => 0,
+            Direction::Right => 1,
+        };
+        self.send(
+            &self.config.scratch_address,
+            &[OscArg::Int(direction), OscArg::Float(velocity)],
+        )
+    }
+
+    pub fn send_nps(&self, nps: u32) -> Result<(), GamepadError> {
+        self.send(&self.config.nps_address, &[OscArg::Int(nps as i32)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_encode_message() {
+        assert_eq!(
+            encode_message("/nps", &[OscArg::Int(12)]),
+            b"/nps\0\0\0\0,i\0\0\0\0\0\x0c".to_vec()
+        );
+        assert_eq!(
+            encode_message("/a", &[OscArg::Int(-1), OscArg::Float(1.5)]),
+            b"/a\0\0,if\0\xff\xff\xff\xff\x3f\xc0\0\0".to_vec()
+        );
+        assert!(is_valid_address(DEFAULT_LANE_ADDRESS));
+        assert!(!is_valid_address("/lane/*"));
+        assert!(!is_valid_address("lane"));
+    }
+
+    #[test]
+    fn test_send_to_listener() {
+        let listener = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        listener
+            .set_read_timeout(Some(Duration::from_secs(1)))
+            .unwrap();
+        let sender = OscSender::new(OscConfig {
+            host: "127.0.0.1".to_string(),
+            port: listener.local_addr().unwrap().port(),
+            lane_address: DEFAULT_LANE_ADDRESS.to_string(),
+            scratch_address: DEFAULT_SCRATCH_ADDRESS.to_string(),
+            nps_address: DEFAULT_NPS_ADDRESS.to_string(),
+        })
+        .unwrap();
+
+        let mut buf = [0; 256];
+        sender.send_lane(3, true).unwrap();
+        let len = listener.recv(&mut buf).unwrap();
+        assert_eq!(
+            &buf[..len],
+            encode_message("/keydisplay/lane/3", &[OscArg::Int(1)])
+        );
+
+        sender.send_scratch(&Direction::Right, 0.5).unwrap();
+        let len = listener.recv(&mut buf).unwrap();
+        assert_eq!(
+            &buf[..len],
+            encode_message(
+                DEFAULT_SCRATCH_ADDRESS,
+                &[OscArg::Int(1), OscArg::Float(0.5)]
+            )
+        );
+    }
+}
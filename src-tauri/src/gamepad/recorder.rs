@@ -0,0 +1,500 @@
+use super::error::GamepadError;
+use super::input::RawInput;
+use super::session;
+use chrono::{DateTime, Local, NaiveDateTime, TimeDelta, TimeZone};
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use ts_rs::TS;
+
+// 入力の記録ファイル（リトルエンディアン）
+//   ヘッダー: マジック(4) バージョン(u16) 記録開始時刻のUNIXミリ秒(i64)
+//   入力ごと: 開始からのマイクロ秒(u64) 種類(u8) デバイス(u16) コード(u32) レーン(u8) 軸の値(i32)
+pub const RECORDING_VERSION: u16 = 1;
+pub const RECORDING_EXTENSION: &str = "kdrec";
+const MAGIC: &[u8; 4] = b"KDIR";
+const FILE_TIME_FORMAT: &str = "%Y%m%d-%H%M%S-%3f";
+const HEADER_LEN: usize = 14;
+const RECORD_LEN: usize = 20;
+// レーンを持たない入力
+const NO_LANE: u8 = u8::MAX;
+
+const KIND_PRESSED: u8 = 0;
+const KIND_RELEASED: u8 = 1;
+const KIND_AXIS: u8 = 2;
+const KIND_CONNECTED: u8 = 3;
+const KIND_DISCONNECTED: u8 = 4;
+
+// 記録はデータベースと同じディレクトリのrecordingsに置く
+pub fn recordings_dir(db_path: &Path) -> PathBuf {
+    db_path
+        .parent()
+        .map(|parent| parent.join("recordings"))
+        .unwrap_or_else(|| PathBuf::from("recordings"))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct RecordingInfo {
+    pub file_name: String,
+    pub started_at: String,
+    #[ts(type = "number")]
+    pub size: u64,
+}
+
+fn parse_recording_time(file_name: &str) -> Option<NaiveDateTime> {
+    let time = file_name
+        .strip_suffix(RECORDING_EXTENSION)?
+        .strip_suffix('.')?;
+    NaiveDateTime::parse_from_str(time, FILE_TIME_FORMAT).ok()
+}
+
+// 新しい順に記録を返す
+pub fn list_recordings(dir: &Path) -> Result<Vec<RecordingInfo>, GamepadError> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = fs::read_dir(dir)
+        .map_err(|e| GamepadError::Recording(format!("Failed to read {}: {}", dir.display(), e)))?;
+
+    let mut recordings = Vec::new();
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if let Some(started_at) = parse_recording_time(&file_name) {
+            recordings.push(RecordingInfo {
+                file_name,
+                started_at: started_at.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                size: entry.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+            });
+        }
+    }
+    recordings.sort_by(|a, b| b.file_name.cmp(&a.file_name));
+    Ok(recordings)
+}
+
+// list_recordingsで返されたファイル名のパス。記録のファイル名でなければエラー
+pub fn recording_path(dir: &Path, file_name: &str) -> Result<PathBuf, GamepadError> {
+    if parse_recording_time(file_name).is_none() {
+        return Err(GamepadError::Recording(format!(
+            "Not a recording file name: {}",
+            file_name
+        )));
+    }
+    Ok(dir.join(file_name))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecordedInput {
+    // 記録開始からの経過時間
+    pub offset: Duration,
+    pub device: u16,
+    pub input: RawInput,
+    // 記録したときのレーン
+    pub lane: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording {
+    pub version: u16,
+    pub started_at: DateTime<Local>,
+    pub inputs: Vec<RecordedInput>,
+}
+
+fn encode_input(input: &RecordedInput) -> [u8; RECORD_LEN] {
+    let (kind, code, value) = match input.input {
+        RawInput::ButtonPressed(code) => (KIND_PRESSED, code, 0),
+        RawInput::ButtonReleased(code) => (KIND_RELEASED, code, 0),
+        RawInput::AxisChanged { code, value } => (KIND_AXIS, code, value),
+        RawInput::Connected => (KIND_CONNECTED, 0, 0),
+        RawInput::Disconnected => (KIND_DISCONNECTED, 0, 0),
+    };
+
+    let mut buf = [0; RECORD_LEN];
+    buf[0..8].copy_from_slice(&(input.offset.as_micros() as u64).to_le_bytes());
+    buf[8] = kind;
+    buf[9..11].copy_from_slice(&input.device.to_le_bytes());
+    buf[11..15].copy_from_slice(&code.to_le_bytes());
+    buf[15] = input.lane.unwrap_or(NO_LANE);
+    buf[16..20].copy_from_slice(&value.to_le_bytes());
+    buf
+}
+
+fn decode_input(buf: &[u8]) -> Result<RecordedInput, GamepadError> {
+    let offset = u64::from_le_bytes(buf[0..8].try_into().unwrap());
+    let device = u16::from_le_bytes(buf[9..11].try_into().unwrap());
+    let code = u32::from_le_bytes(buf[11..15].try_into().unwrap());
+    let value = i32::from_le_bytes(buf[16..20].try_into().unwrap());
+
+    let input = match buf[8] {
+        KIND_PRESSED => RawInput::ButtonPressed(code),
+        KIND_RELEASED => RawInput::ButtonReleased(code),
+        KIND_AXIS => RawInput::AxisChanged { code, value },
+        KIND_CONNECTED => RawInput::Connected,
+        KIND_DISCONNECTED => RawInput::Disconnected,
+        kind => {
+            return Err(GamepadError::Recording(format!(
+                "Unknown input kind: {}",
+                kind
+            )))
+        }
+    };
+
+    Ok(RecordedInput {
+        offset: Duration::from_micros(offset),
+        device,
+        input,
+        lane: Some(buf[15]).filter(|lane| *lane != NO_LANE),
+    })
+}
+
+// 書き込み途中で終了した場合に備え、末尾の不完全な入力は無視する
+pub fn read_recording(path: &Path) -> Result<Recording, GamepadError> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut bytes))
+        .map_err(|e| {
+            GamepadError::Recording(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+
+    if bytes.len() < HEADER_LEN || &bytes[0..4] != MAGIC {
+        return Err(GamepadError::Recording(format!(
+            "Not an input recording: {}",
+            path.display()
+        )));
+    }
+    let version = u16::from_le_bytes(bytes[4..6].try_into().unwrap());
+    if version > RECORDING_VERSION {
+        return Err(GamepadError::Recording(format!(
+            "Unsupported recording version {} (supported up to {})",
+            version, RECORDING_VERSION
+        )));
+    }
+    let started_at_ms = i64::from_le_bytes(bytes[6..14].try_into().unwrap());
+    let started_at = Local
+        .timestamp_millis_opt(started_at_ms)
+        .single()
+        .ok_or_else(|| GamepadError::Recording(format!("Invalid start time: {}", started_at_ms)))?;
+
+    let inputs = bytes[HEADER_LEN..]
+        .chunks_exact(RECORD_LEN)
+        .map(decode_input)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Recording {
+        version,
+        started_at,
+        inputs,
+    })
+}
+
+// 記録中のファイル
+#[derive(Debug)]
+struct RecordingFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    started_at: DateTime<Local>,
+    last_input: DateTime<Local>,
+}
+
+impl Recording {
+    // 記録開始からoffset後の時刻
+    pub fn time_at(&self, offset: Duration) -> DateTime<Local> {
+        TimeDelta::from_std(offset)
+            .ok()
+            .and_then(|offset| self.started_at.checked_add_signed(offset))
+            .unwrap_or(self.started_at)
+    }
+}
+
+impl RecordingFile {
+    fn create(dir: &Path, started_at: DateTime<Local>) -> Result<RecordingFile, GamepadError> {
+        fs::create_dir_all(dir).map_err(|e| {
+            GamepadError::Recording(format!("Failed to create {}: {}", dir.display(), e))
+        })?;
+        let path = dir.join(format!(
+            "{}.{}",
+            started_at.format(FILE_TIME_FORMAT),
+            RECORDING_EXTENSION
+        ));
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|e| {
+                GamepadError::Recording(format!("Failed to create {}: {}", path.display(), e))
+            })?;
+
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&RECORDING_VERSION.to_le_bytes());
+        header.extend_from_slice(&started_at.timestamp_millis().to_le_bytes());
+
+        let mut recording = RecordingFile {
+            path,
+            writer: BufWriter::new(file),
+            started_at,
+            last_input: started_at,
+        };
+        recording.write(&header)?;
+        Ok(recording)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), GamepadError> {
+        self.writer.write_all(bytes).map_err(|e| {
+            GamepadError::Recording(format!("Failed to write {}: {}", self.path.display(), e))
+        })
+    }
+
+    fn flush(&mut self) -> Result<(), GamepadError> {
+        self.writer.flush().map_err(|e| {
+            GamepadError::Recording(format!("Failed to write {}: {}", self.path.display(), e))
+        })
+    }
+}
+
+// すべての入力をセッションごとのファイルに記録する。
+// セッションと同じく、入力がidle_gap途切れたら次の入力から新しいファイルにする
+#[derive(Debug)]
+pub struct Recorder {
+    dir: PathBuf,
+    idle_gap: Duration,
+    file: Option<RecordingFile>,
+}
+
+impl Recorder {
+    pub fn new(dir: PathBuf) -> Recorder {
+        Recorder {
+            dir,
+            idle_gap: session::DEFAULT_IDLE_GAP,
+            file: None,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn set_idle_gap(&mut self, idle_gap: Duration) {
+        self.idle_gap = idle_gap;
+    }
+
+    // 記録中のファイル
+    #[cfg(test)]
+    pub fn current_path(&self) -> Option<&Path> {
+        self.file.as_ref().map(|file| file.path.as_path())
+    }
+
+    // 接続・切断だけでは新しいファイルを作らない
+    pub fn record(
+        &mut self,
+        time: DateTime<Local>,
+        device: usize,
+        input: RawInput,
+        lane: Option<u32>,
+    ) -> Result<(), GamepadError> {
+        if self.is_idle(time) {
+            self.finish()?;
+        }
+        if self.file.is_none() {
+            if matches!(input, RawInput::Connected | RawInput::Disconnected) {
+                return Ok(());
+            }
+            self.file = Some(RecordingFile::create(&self.dir, time)?);
+        }
+
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        // イベントの時刻が前後しても経過時間は負にしない
+        let offset = (time - file.started_at).to_std().unwrap_or_default();
+        let bytes = encode_input(&RecordedInput {
+            offset,
+            device: u16::try_from(device).unwrap_or(u16::MAX),
+            input,
+            lane: lane.and_then(|lane| u8::try_from(lane).ok()),
+        });
+        file.last_input = file.last_input.max(time);
+        file.write(&bytes)
+    }
+
+    // 最後の入力からidle_gap以上経過していればファイルを閉じる。そうでなければ書き出しておく
+    pub fn close_if_idle(&mut self, now: DateTime<Local>) -> Result<bool, GamepadError> {
+        if self.is_idle(now) {
+            self.finish()?;
+            return Ok(true);
+        }
+        match self.file.as_mut() {
+            Some(file) => file.flush().map(|_| false),
+            None => Ok(false),
+        }
+    }
+
+    fn is_idle(&self, now: DateTime<Local>) -> bool {
+        self.file
+            .as_ref()
+            .and_then(|file| (now - file.last_input).to_std().ok())
+            .is_some_and(|elapsed| elapsed >= self.idle_gap)
+    }
+
+    pub fn finish(&mut self) -> Result<(), GamepadError> {
+        match self.file.take() {
+            Some(mut file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            eprintln!("Failed to finish recording: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+
+    fn setup_test_dir(test_name: &str) -> PathBuf {
+        let dir = PathBuf::from(format!("test_recorder_{}", test_name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn cleanup_test_dir(dir: &Path) {
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_record_and_read() {
+        let dir = setup_test_dir("round_trip");
+        let start = Local::now();
+        let mut recorder = Recorder::new(dir.clone());
+
+        // 記録中でなければ接続だけでは記録しない
+        recorder
+            .record(start, 0, RawInput::Connected, None)
+            .unwrap();
+        assert!(recorder.current_path().is_none());
+
+        recorder
+            .record(start, 1, RawInput::ButtonPressed(3), Some(4))
+            .unwrap();
+        recorder
+            .record(
+                start + TimeDelta::microseconds(1500),
+                1,
+                RawInput::AxisChanged {
+                    code: 5,
+                    value: -42,
+                },
+                Some(0),
+            )
+            .unwrap();
+        recorder
+            .record(
+                start + TimeDelta::milliseconds(40),
+                1,
+                RawInput::ButtonReleased(3),
+                Some(4),
+            )
+            .unwrap();
+        let path = recorder.current_path().unwrap().to_path_buf();
+        recorder.finish().unwrap();
+
+        let recording = read_recording(&path).unwrap();
+        assert_eq!(recording.version, RECORDING_VERSION);
+        assert_eq!(
+            recording.started_at.timestamp_millis(),
+            start.timestamp_millis()
+        );
+        assert_eq!(
+            recording.inputs,
+            vec![
+                RecordedInput {
+                    offset: Duration::ZERO,
+                    device: 1,
+                    input: RawInput::ButtonPressed(3),
+                    lane: Some(4),
+                },
+                RecordedInput {
+                    offset: Duration::from_micros(1500),
+                    device: 1,
+                    input: RawInput::AxisChanged {
+                        code: 5,
+                        value: -42
+                    },
+                    lane: Some(0),
+                },
+                RecordedInput {
+                    offset: Duration::from_millis(40),
+                    device: 1,
+                    input: RawInput::ButtonReleased(3),
+                    lane: Some(4),
+                },
+            ]
+        );
+
+        // 書き込み途中の入力は無視する
+        let mut bytes = fs::read(&path).unwrap();
+        bytes.truncate(bytes.len() - 5);
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(read_recording(&path).unwrap().inputs.len(), 2);
+
+        // 新しいバージョンのファイルは読まない
+        bytes[4..6].copy_from_slice(&(RECORDING_VERSION + 1).to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+        assert!(read_recording(&path).is_err());
+
+        cleanup_test_dir(&dir);
+    }
+
+    #[test]
+    fn test_new_file_after_idle_gap() {
+        let dir = setup_test_dir("idle_gap");
+        let start = Local::now();
+        let mut recorder = Recorder::new(dir.clone());
+        recorder.set_idle_gap(Duration::from_secs(60));
+
+        recorder
+            .record(start, 0, RawInput::ButtonPressed(0), Some(1))
+            .unwrap();
+        let first = recorder.current_path().unwrap().to_path_buf();
+        assert!(!recorder
+            .close_if_idle(start + TimeDelta::seconds(59))
+            .unwrap());
+
+        recorder
+            .record(
+                start + TimeDelta::seconds(120),
+                0,
+                RawInput::ButtonPressed(0),
+                Some(1),
+            )
+            .unwrap();
+        let second = recorder.current_path().unwrap().to_path_buf();
+        assert_ne!(first, second);
+        assert!(recorder
+            .close_if_idle(start + TimeDelta::seconds(180))
+            .unwrap());
+        assert!(recorder.current_path().is_none());
+
+        assert_eq!(read_recording(&first).unwrap().inputs.len(), 1);
+        assert_eq!(read_recording(&second).unwrap().inputs.len(), 1);
+
+        let recordings = list_recordings(&dir).unwrap();
+        assert_eq!(recordings.len(), 2);
+        assert_eq!(
+            recording_path(&dir, &recordings[0].file_name).unwrap(),
+            second
+        );
+        assert!(recording_path(&dir, "../statistics.db").is_err());
+
+        cleanup_test_dir(&dir);
+    }
+}
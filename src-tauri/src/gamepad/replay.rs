@@ -0,0 +1,42 @@
+use super::event::ReplayFinishedEvent;
+use super::session::PlaySession;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+// 再生速度の倍率
+pub const SPEED_RANGE: RangeInclusive<f64> = 0.1..=16.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayOptions {
+    pub speed: f64,
+}
+
+// リプレイ中に終了したセッションをまとめる
+pub fn summarize(sessions: &[PlaySession], inputs: u32, stopped: bool) -> ReplayFinishedEvent {
+    let mut summary = ReplayFinishedEvent {
+        inputs,
+        notes_count: 0,
+        scratch_count: 0,
+        key_counts: Default::default(),
+        peak_nps: 0,
+        average_release_time: 0,
+        stopped,
+    };
+    let mut release_count = 0;
+    let mut release_time_total = Duration::ZERO;
+
+    for session in sessions {
+        summary.notes_count += session.notes_count;
+        summary.scratch_count += session.scratch_count;
+        for (button, count) in &session.key_counts {
+            *summary.key_counts.entry(*button).or_insert(0) += count;
+        }
+        summary.peak_nps = summary.peak_nps.max(session.peak_nps);
+        release_count += session.release_count;
+        release_time_total += session.release_time_total;
+    }
+    if release_count > 0 {
+        summary.average_release_time = (release_time_total / release_count).as_millis() as u32;
+    }
+    summary
+}
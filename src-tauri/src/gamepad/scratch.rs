@@ -1,32 +1,34 @@
-use std::time::Instant;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::time::Duration;
+use ts_rs::TS;
 
 use crate::gamepad::error::GamepadError;
 
 const AXIS_MIN_VALUE: i32 = -32768;
 const AXIS_MAX_VALUE: i32 = 32767;
+// 1回転あたりの軸の値の変化量
+const AXIS_RANGE: i32 = AXIS_MAX_VALUE - AXIS_MIN_VALUE + 1;
+// この時間スクラッチの入力がなければニュートラルに戻す
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(100);
 
-#[derive(PartialEq, Clone, Debug)]
+#[derive(PartialEq, Clone, Debug, Serialize, TS)]
+#[serde(rename_all = "lowercase")]
+#[ts(export)]
 pub enum Direction {
     Neutral,
     Left,
     Right,
 }
 
-impl Direction {
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            Direction::Neutral => "neutral",
-            Direction::Left => "left",
-            Direction::Right => "right",
-        }
-    }
-}
-
 #[derive(Debug)]
 pub struct ScratchEvent {
-    spined_time: Option<Instant>,
+    spined_time: Option<DateTime<Local>>,
     direction: Direction,
     axis: i32,
+    // 直前の入力からの回転の速さ（回転/秒）
+    velocity: f32,
+    timeout: Duration,
 }
 
 impl ScratchEvent {
@@ -36,20 +38,32 @@ impl ScratchEvent {
             spined_time: None,
             direction: Direction::Neutral,
             axis: 0,
+            velocity: 0.0,
+            timeout: DEFAULT_TIMEOUT,
         }
     }
 
-    pub fn on_spin(&mut self, new_axis: i32) -> Result<Option<Direction>, GamepadError> {
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    // nowは入力の時刻。リプレイでは記録した時刻を渡す
+    pub fn on_spin(
+        &mut self,
+        new_axis: i32,
+        now: DateTime<Local>,
+    ) -> Result<Option<Direction>, GamepadError> {
         let new_direction = self.calculate_direction(new_axis);
+        self.velocity = self.calculate_velocity(new_axis, now);
 
         self.axis = new_axis;
-        self.spined_time = Some(Instant::now());
+        self.spined_time = Some(now);
 
         if self.direction == new_direction {
-            return Ok(None);
+            Ok(None)
         } else {
             self.direction = new_direction.clone();
-            return Ok(Some(new_direction));
+            Ok(Some(new_direction))
         }
     }
 
@@ -57,14 +71,15 @@ impl ScratchEvent {
         if self.direction != Direction::Neutral {
             self.direction = Direction::Neutral;
             self.spined_time = None;
+            self.velocity = 0.0;
             return true;
         }
         false
     }
-    pub fn is_timeout(&self) -> bool {
+    pub fn is_timeout(&self, now: DateTime<Local>) -> bool {
         if let Some(spined_time) = self.spined_time {
             if self.direction != Direction::Neutral {
-                return spined_time.elapsed() >= std::time::Duration::from_millis(100);
+                return (now - spined_time).to_std().unwrap_or_default() >= self.timeout;
             }
         }
         false
@@ -74,6 +89,32 @@ impl ScratchEvent {
         self.axis
     }
 
+    pub fn direction(&self) -> &Direction {
+        &self.direction
+    }
+
+    pub fn velocity(&self) -> f32 {
+        self.velocity
+    }
+
+    // 止まっていた場合（前回の入力からタイムアウト以上経過）は0
+    fn calculate_velocity(&self, new_axis: i32, now: DateTime<Local>) -> f32 {
+        let Some(spined_time) = self.spined_time else {
+            return 0.0;
+        };
+        let elapsed = (now - spined_time).to_std().unwrap_or_default();
+        if elapsed >= self.timeout || elapsed.is_zero() {
+            return 0.0;
+        }
+
+        // 最小値と最大値の間をまたいだ場合は短い方の向きに回ったとみなす
+        let mut delta = (new_axis - self.axis).abs();
+        if delta > AXIS_RANGE / 2 {
+            delta = AXIS_RANGE - delta;
+        }
+        delta as f32 / AXIS_RANGE as f32 / elapsed.as_secs_f32()
+    }
+
     fn calculate_direction(&self, new_axis: i32) -> Direction {
         if new_axis == AXIS_MIN_VALUE && self.axis == AXIS_MAX_VALUE {
             Direction::Left
@@ -0,0 +1,284 @@
+use super::statistics;
+use chrono::{DateTime, Local, NaiveDate};
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+pub const DEFAULT_IDLE_GAP: Duration = Duration::from_secs(300);
+const NPS_WINDOW: chrono::TimeDelta = chrono::TimeDelta::seconds(1);
+
+// 終了したプレイセッションの記録
+#[derive(Debug, Clone)]
+pub struct PlaySession {
+    pub start_time: DateTime<Local>,
+    pub end_time: DateTime<Local>,
+    pub notes_count: u32,
+    pub scratch_count: u32,
+    pub key_counts: BTreeMap<u32, u32>,
+    pub peak_nps: u32,
+    pub release_count: u32,
+    pub release_time_total: Duration,
+}
+
+impl PlaySession {
+    fn new(now: DateTime<Local>) -> PlaySession {
+        PlaySession {
+            start_time: now,
+            end_time: now,
+            notes_count: 0,
+            scratch_count: 0,
+            key_counts: BTreeMap::new(),
+            peak_nps: 0,
+            release_count: 0,
+            release_time_total: Duration::ZERO,
+        }
+    }
+
+    pub fn average_release_time(&self) -> Duration {
+        if self.release_count == 0 {
+            return Duration::ZERO;
+        }
+        self.release_time_total / self.release_count
+    }
+}
+
+#[derive(Debug)]
+pub struct SessionTracker {
+    current: Option<PlaySession>,
+    finished: Vec<PlaySession>,
+    recent_notes: VecDeque<DateTime<Local>>,
+    idle_gap: Duration,
+}
+
+impl SessionTracker {
+    pub fn new() -> SessionTracker {
+        SessionTracker {
+            current: None,
+            finished: Vec::new(),
+            recent_notes: VecDeque::new(),
+            idle_gap: DEFAULT_IDLE_GAP,
+        }
+    }
+
+    pub fn idle_gap(&self) -> Duration {
+        self.idle_gap
+    }
+
+    pub fn set_idle_gap(&mut self, idle_gap: Duration) {
+        self.idle_gap = idle_gap;
+    }
+
+    pub fn on_key(&mut self, now: DateTime<Local>, button_code: u32) {
+        let session = self.touch(now);
+        *session.key_counts.entry(button_code).or_insert(0) += 1;
+        self.on_note(now);
+    }
+
+    pub fn on_scratch(&mut self, now: DateTime<Local>) {
+        self.touch(now).scratch_count += 1;
+        self.on_note(now);
+    }
+
+    pub fn on_release(&mut self, now: DateTime<Local>, release_time: Option<Duration>) {
+        let session = self.touch(now);
+        if let Some(release_time) = release_time {
+            session.release_count += 1;
+            session.release_time_total += release_time;
+        }
+    }
+
+    // 最後の入力からidle_gap以上経過していればセッションを終了する
+    pub fn close_if_idle(&mut self, now: DateTime<Local>) -> bool {
+        let is_idle = match &self.current {
+            Some(session) => self.is_expired(session, now),
+            None => false,
+        };
+        if is_idle {
+            self.finish();
+        }
+        is_idle
+    }
+
+    pub fn finish(&mut self) {
+        if let Some(session) = self.current.take() {
+            if session.notes_count > 0 {
+                self.finished.push(session);
+            }
+        }
+        self.recent_notes.clear();
+    }
+
+    // 直近1秒間のノーツ数
+    pub fn nps(&self, now: DateTime<Local>) -> u32 {
+        self.recent_notes
+            .iter()
+            .filter(|&&note| now - note < NPS_WINDOW)
+            .count() as u32
+    }
+
+    #[cfg(test)]
+    pub fn current(&self) -> Option<&PlaySession> {
+        self.current.as_ref()
+    }
+
+    pub fn take_finished(&mut self) -> Vec<PlaySession> {
+        std::mem::take(&mut self.finished)
+    }
+
+    pub fn requeue(&mut self, sessions: Vec<PlaySession>) {
+        self.finished.splice(0..0, sessions);
+    }
+
+    // 開始時刻のプレイ日付がstartからend（両端を含む）の終了済みセッションを捨てる
+    pub fn discard_finished(&mut self, start: NaiveDate, end: NaiveDate, rollover_hour: u32) {
+        let range = start..=end;
+        self.finished.retain(|session| {
+            !range.contains(&statistics::play_date(
+                session.start_time.naive_local(),
+                rollover_hour,
+            ))
+        });
+    }
+
+    // 入力があったセッションを返す。間隔が空いていれば新しいセッションを開始する
+    fn touch(&mut self, now: DateTime<Local>) -> &mut PlaySession {
+        let expired = match &self.current {
+            Some(session) => self.is_expired(session, now),
+            None => false,
+        };
+        if expired {
+            self.finish();
+        }
+
+        let session = self.current.get_or_insert_with(|| PlaySession::new(now));
+        session.end_time = now;
+        session
+    }
+
+    fn on_note(&mut self, now: DateTime<Local>) {
+        self.recent_notes.push_back(now);
+        while let Some(&oldest) = self.recent_notes.front() {
+            if now - oldest >= NPS_WINDOW {
+                self.recent_notes.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        let nps = self.recent_notes.len() as u32;
+        if let Some(session) = self.current.as_mut() {
+            session.notes_count += 1;
+            session.peak_nps = session.peak_nps.max(nps);
+        }
+    }
+
+    fn is_expired(&self, session: &PlaySession, now: DateTime<Local>) -> bool {
+        match (now - session.end_time).to_std() {
+            Ok(elapsed) => elapsed >= self.idle_gap,
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+
+    #[test]
+    fn test_session_starts_on_first_input() {
+        let mut tracker = SessionTracker::new();
+        assert!(tracker.current().is_none());
+
+        let now = Local::now();
+        tracker.on_key(now, 1);
+        tracker.on_key(now, 1);
+        tracker.on_scratch(now);
+
+        let session = tracker.current().unwrap();
+        assert_eq!(session.start_time, now);
+        assert_eq!(session.notes_count, 3);
+        assert_eq!(session.scratch_count, 1);
+        assert_eq!(session.key_counts.get(&1), Some(&2));
+    }
+
+    #[test]
+    fn test_session_closes_after_idle_gap() {
+        let mut tracker = SessionTracker::new();
+        tracker.set_idle_gap(Duration::from_secs(60));
+
+        let start = Local::now();
+        tracker.on_key(start, 0);
+        assert!(!tracker.close_if_idle(start + TimeDelta::seconds(30)));
+        assert!(tracker.close_if_idle(start + TimeDelta::seconds(60)));
+
+        let finished = tracker.take_finished();
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].end_time, start);
+        assert!(tracker.current().is_none());
+    }
+
+    #[test]
+    fn test_input_after_idle_gap_starts_new_session() {
+        let mut tracker = SessionTracker::new();
+        tracker.set_idle_gap(Duration::from_secs(60));
+
+        let start = Local::now();
+        tracker.on_key(start, 0);
+        tracker.on_key(start + TimeDelta::seconds(120), 2);
+
+        let finished = tracker.take_finished();
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].notes_count, 1);
+        assert_eq!(tracker.current().unwrap().notes_count, 1);
+    }
+
+    #[test]
+    fn test_peak_nps() {
+        let mut tracker = SessionTracker::new();
+        let start = Local::now();
+
+        for i in 0..10 {
+            tracker.on_key(start + TimeDelta::milliseconds(i * 100), 0);
+        }
+        // 1秒以上経過した後の入力はウィンドウから外れる
+        tracker.on_key(start + TimeDelta::milliseconds(2500), 0);
+
+        assert_eq!(tracker.current().unwrap().peak_nps, 10);
+        assert_eq!(tracker.nps(start + TimeDelta::milliseconds(2500)), 1);
+        assert_eq!(tracker.nps(start + TimeDelta::milliseconds(4000)), 0);
+    }
+
+    #[test]
+    fn test_release_statistics() {
+        let mut tracker = SessionTracker::new();
+        let now = Local::now();
+
+        tracker.on_key(now, 0);
+        tracker.on_release(now, Some(Duration::from_millis(40)));
+        tracker.on_key(now, 0);
+        tracker.on_release(now, Some(Duration::from_millis(60)));
+        tracker.on_key(now, 0);
+        tracker.on_release(now, None);
+
+        let session = tracker.current().unwrap();
+        assert_eq!(session.release_count, 2);
+        assert_eq!(session.average_release_time(), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_discard_finished_sessions_in_range() {
+        let mut tracker = SessionTracker::new();
+        let now = Local::now();
+        let yesterday = now - TimeDelta::days(1);
+
+        tracker.on_key(yesterday, 0);
+        tracker.finish();
+        tracker.on_key(now, 0);
+        tracker.finish();
+
+        tracker.discard_finished(now.date_naive(), now.date_naive(), 0);
+        let finished = tracker.take_finished();
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].start_time, yesterday);
+    }
+}
@@ -1,4 +1,5 @@
-use std::collections::VecDeque;
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta, Timelike};
+use std::collections::{BTreeMap, VecDeque};
 use std::time::Duration;
 
 #[derive(Debug)]
@@ -29,10 +30,34 @@ impl ReleaseTimeTracker {
     }
 }
 
+// rollover_hour時より前の入力は前日分として扱う
+pub fn play_date(time: NaiveDateTime, rollover_hour: u32) -> NaiveDate {
+    (time - TimeDelta::hours(rollover_hour as i64)).date()
+}
+
+// 未保存のノーツ数。dailyはプレイ日付ごと、hourlyは叩いた時刻の時間帯（正時）ごと
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PendingCounts {
+    pub daily: BTreeMap<NaiveDate, u32>,
+    pub hourly: BTreeMap<NaiveDateTime, u32>,
+}
+
+impl PendingCounts {
+    pub fn merge(&mut self, other: PendingCounts) {
+        for (date, count) in other.daily {
+            *self.daily.entry(date).or_insert(0) += count;
+        }
+        for (hour, count) in other.hourly {
+            *self.hourly.entry(hour).or_insert(0) += count;
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct GameStatistics {
     notes_count: u32,
-    last_save_count: u32,
+    pending_counts: PendingCounts,
+    rollover_hour: u32,
     release_tracker: ReleaseTimeTracker,
 }
 
@@ -40,25 +65,63 @@ impl GameStatistics {
     pub fn new() -> GameStatistics {
         GameStatistics {
             notes_count: 0,
-            last_save_count: 0,
+            pending_counts: PendingCounts::default(),
+            rollover_hour: 0,
             release_tracker: ReleaseTimeTracker::new(2000),
         }
     }
 
-    pub fn increment_notes(&mut self) {
+    pub fn increment_notes(&mut self, time: NaiveDateTime) {
         self.notes_count += 1;
+        *self
+            .pending_counts
+            .daily
+            .entry(play_date(time, self.rollover_hour))
+            .or_insert(0) += 1;
+
+        let hour = time.date().and_hms_opt(time.hour(), 0, 0).unwrap_or(time);
+        *self.pending_counts.hourly.entry(hour).or_insert(0) += 1;
     }
 
     pub fn notes_count(&self) -> u32 {
         self.notes_count
     }
 
-    pub fn set_last_saved_count(&mut self) {
-        self.last_save_count = self.notes_count;
+    pub fn rollover_hour(&self) -> u32 {
+        self.rollover_hour
+    }
+
+    pub fn set_rollover_hour(&mut self, rollover_hour: u32) {
+        self.rollover_hour = rollover_hour;
     }
 
-    pub fn difference_notes_count(&self) -> u32 {
-        self.notes_count - self.last_save_count
+    pub fn play_date(&self, time: NaiveDateTime) -> NaiveDate {
+        play_date(time, self.rollover_hour)
+    }
+
+    pub fn pending_counts(&self) -> &PendingCounts {
+        &self.pending_counts
+    }
+
+    // 未保存分を取り出す。保存に失敗した場合はrestore_pending_countsで戻す
+    pub fn take_pending_counts(&mut self) -> PendingCounts {
+        std::mem::take(&mut self.pending_counts)
+    }
+
+    pub fn restore_pending_counts(&mut self, counts: PendingCounts) {
+        self.pending_counts.merge(counts);
+    }
+
+    // プレイ日付がstartからend（両端を含む）の未保存分を捨てる
+    pub fn discard_pending_counts(&mut self, start: NaiveDate, end: NaiveDate) {
+        let range = start..=end;
+        let rollover_hour = self.rollover_hour;
+        self.pending_counts
+            .daily
+            .retain(|date, _| !range.contains(date));
+        self.pending_counts
+            .hourly
+            .retain(|hour, _| !range.contains(&play_date(*hour, rollover_hour)));
     }
 
     pub fn add_release_time(&mut self, duration: Duration) {
@@ -69,3 +132,130 @@ impl GameStatistics {
         self.release_tracker.average_release_time()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_play_date_without_rollover() {
+        assert_eq!(
+            play_date(datetime("2025-01-01 23:50"), 0),
+            date("2025-01-01")
+        );
+        assert_eq!(
+            play_date(datetime("2025-01-02 00:10"), 0),
+            date("2025-01-02")
+        );
+    }
+
+    #[test]
+    fn test_play_date_with_rollover() {
+        assert_eq!(
+            play_date(datetime("2025-01-02 04:59"), 5),
+            date("2025-01-01")
+        );
+        assert_eq!(
+            play_date(datetime("2025-01-02 05:00"), 5),
+            date("2025-01-02")
+        );
+        assert_eq!(
+            play_date(datetime("2025-03-01 01:00"), 5),
+            date("2025-02-28")
+        );
+    }
+
+    #[test]
+    fn test_notes_are_bucketed_by_play_date() {
+        let mut statistics = GameStatistics::new();
+        statistics.increment_notes(datetime("2025-01-01 23:50"));
+        statistics.increment_notes(datetime("2025-01-01 23:59"));
+        statistics.increment_notes(datetime("2025-01-02 00:10"));
+
+        let pending = statistics.take_pending_counts();
+        assert_eq!(pending.daily.get(&date("2025-01-01")), Some(&2));
+        assert_eq!(pending.daily.get(&date("2025-01-02")), Some(&1));
+        assert!(statistics.pending_counts().daily.is_empty());
+        assert_eq!(statistics.notes_count(), 3);
+    }
+
+    #[test]
+    fn test_restore_pending_counts_merges_new_notes() {
+        let mut statistics = GameStatistics::new();
+        statistics.increment_notes(datetime("2025-01-01 12:00"));
+        let pending = statistics.take_pending_counts();
+
+        statistics.increment_notes(datetime("2025-01-01 12:01"));
+        statistics.restore_pending_counts(pending);
+
+        assert_eq!(
+            statistics.pending_counts().daily.get(&date("2025-01-01")),
+            Some(&2)
+        );
+    }
+
+    #[test]
+    fn test_notes_are_bucketed_by_hour() {
+        let mut statistics = GameStatistics::new();
+        statistics.set_rollover_hour(5);
+        statistics.increment_notes(datetime("2025-01-02 01:10"));
+        statistics.increment_notes(datetime("2025-01-02 01:50"));
+        statistics.increment_notes(datetime("2025-01-02 02:00"));
+
+        // 時間帯はロールオーバーに関係なく実際の日時で集計する
+        let pending = statistics.take_pending_counts();
+        assert_eq!(pending.daily.get(&date("2025-01-01")), Some(&3));
+        assert_eq!(pending.hourly.get(&datetime("2025-01-02 01:00")), Some(&2));
+        assert_eq!(pending.hourly.get(&datetime("2025-01-02 02:00")), Some(&1));
+    }
+
+    #[test]
+    fn test_discard_pending_counts_in_range() {
+        let mut statistics = GameStatistics::new();
+        statistics.increment_notes(datetime("2025-01-01 12:00"));
+        statistics.increment_notes(datetime("2025-01-02 12:00"));
+        statistics.increment_notes(datetime("2025-01-03 12:00"));
+
+        statistics.discard_pending_counts(date("2025-01-02"), date("2025-01-03"));
+
+        let pending = statistics.take_pending_counts();
+        assert_eq!(
+            pending.daily.into_iter().collect::<Vec<_>>(),
+            vec![(date("2025-01-01"), 1)]
+        );
+        assert_eq!(
+            pending.hourly.into_iter().collect::<Vec<_>>(),
+            vec![(datetime("2025-01-01 12:00"), 1)]
+        );
+    }
+
+    #[test]
+    fn test_discard_pending_counts_uses_play_date() {
+        let mut statistics = GameStatistics::new();
+        statistics.set_rollover_hour(5);
+        statistics.increment_notes(datetime("2025-01-02 12:00"));
+        statistics.increment_notes(datetime("2025-01-03 02:00"));
+        statistics.increment_notes(datetime("2025-01-03 06:00"));
+
+        // 2025-01-03 02:00は2025-01-02のプレイとして捨てる
+        statistics.discard_pending_counts(date("2025-01-02"), date("2025-01-02"));
+
+        let pending = statistics.take_pending_counts();
+        assert_eq!(
+            pending.daily.into_iter().collect::<Vec<_>>(),
+            vec![(date("2025-01-03"), 1)]
+        );
+        assert_eq!(
+            pending.hourly.into_iter().collect::<Vec<_>>(),
+            vec![(datetime("2025-01-03 06:00"), 1)]
+        );
+    }
+}
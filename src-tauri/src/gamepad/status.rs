@@ -1,35 +1,79 @@
 use super::button::ButtonManager;
 use super::error::GamepadError;
-use super::scratch::{Direction, ScratchEvent};
+use super::goal::GoalTracker;
+use super::layout::{KeyLayout, KeyLayoutId};
+use super::scratch::{self, Direction, ScratchEvent};
+use super::session::{PlaySession, SessionTracker};
 use super::statistics::GameStatistics;
+use chrono::{DateTime, Local, NaiveDate};
+use std::collections::BTreeMap;
 use std::time::Duration;
 
 #[derive(Debug)]
 pub struct GamepadStatus {
     button_manager: ButtonManager,
-    scratch_event: ScratchEvent,
+    // 皿のレーンごとの状態。DPのように皿が2つあっても互いの入力で向きが変わらないようにする
+    scratch_events: BTreeMap<u32, ScratchEvent>,
+    scratch_timeout: Duration,
     statistics: GameStatistics,
+    session_tracker: SessionTracker,
+    goal_tracker: GoalTracker,
+    layout: KeyLayout,
 }
 
 impl GamepadStatus {
     pub fn new() -> Self {
         Self {
             button_manager: ButtonManager::new(),
-            scratch_event: ScratchEvent::new(),
+            scratch_events: BTreeMap::new(),
+            scratch_timeout: scratch::DEFAULT_TIMEOUT,
             statistics: GameStatistics::new(),
+            session_tracker: SessionTracker::new(),
+            goal_tracker: GoalTracker::new(),
+            layout: KeyLayout::new(KeyLayoutId::IidxSp),
         }
     }
 
-    pub fn on_button_press(&mut self, button_code: u32) -> Result<u32, GamepadError> {
-        self.button_manager.on_press(button_code)?;
-        self.statistics.increment_notes();
+    // 同じ設定で、カウントが空の状態を作る
+    pub fn with_same_settings(&self) -> Self {
+        let mut status = Self::new();
+        status
+            .statistics
+            .set_rollover_hour(self.statistics.rollover_hour());
+        status
+            .session_tracker
+            .set_idle_gap(self.session_tracker.idle_gap());
+        status.scratch_timeout = self.scratch_timeout;
+        status.layout = self.layout.clone();
+        // 目標だけを写す。保存済みのノーツ数は、記録した入力がすでに数えられているため写さない
+        status
+            .goal_tracker
+            .set_daily_goal(self.goal_tracker.daily_goal());
+        status
+    }
+
+    // nowは入力の時刻。リプレイでは記録した時刻を渡す
+    pub fn on_button_press(
+        &mut self,
+        button_code: u32,
+        now: DateTime<Local>,
+    ) -> Result<u32, GamepadError> {
+        self.button_manager.on_press(button_code, now)?;
+        self.statistics.increment_notes(now.naive_local());
+        self.session_tracker.on_key(now, button_code);
         Ok(self.statistics.notes_count())
     }
 
-    pub fn on_button_release(&mut self, button_code: u32) -> Result<(u32, Duration), GamepadError> {
-        if let Some(release_time) = self.button_manager.on_release(button_code)? {
+    pub fn on_button_release(
+        &mut self,
+        button_code: u32,
+        now: DateTime<Local>,
+    ) -> Result<(u32, Duration), GamepadError> {
+        let release_time = self.button_manager.on_release(button_code, now)?;
+        if let Some(release_time) = release_time {
             self.statistics.add_release_time(release_time);
         }
+        self.session_tracker.on_release(now, release_time);
 
         Ok((
             self.statistics.notes_count(),
@@ -37,42 +81,131 @@ impl GamepadStatus {
         ))
     }
 
+    // laneはレイアウトの皿のレーン。皿に割り当てていない軸は呼び出し側で無視する
     pub fn on_scratch_spin(
         &mut self,
         new_axis: i32,
+        lane: u32,
+        now: DateTime<Local>,
     ) -> Result<(Option<Direction>, u32), GamepadError> {
-        let direction = self.scratch_event.on_spin(new_axis)?;
+        let timeout = self.scratch_timeout;
+        let direction = self
+            .scratch_events
+            .entry(lane)
+            .or_insert_with(|| {
+                let mut scratch_event = ScratchEvent::new();
+                scratch_event.set_timeout(timeout);
+                scratch_event
+            })
+            .on_spin(new_axis, now)?;
 
         if let Some(ref dir) = direction {
             if *dir != Direction::Neutral {
-                self.statistics.increment_notes();
+                self.statistics.increment_notes(now.naive_local());
+                self.session_tracker.on_scratch(now);
             }
         }
 
         Ok((direction, self.statistics.notes_count()))
     }
 
-    pub fn scratch_event(&self) -> &ScratchEvent {
-        &self.scratch_event
+    pub fn layout(&self) -> &KeyLayout {
+        &self.layout
     }
 
-    pub fn scratch_event_mut(&mut self) -> &mut ScratchEvent {
-        &mut self.scratch_event
+    pub fn set_layout(&mut self, id: KeyLayoutId) {
+        if self.layout.id != id {
+            self.layout = KeyLayout::new(id);
+            self.scratch_events.clear();
+        }
+    }
+
+    pub fn scratch_event(&self, lane: u32) -> Option<&ScratchEvent> {
+        self.scratch_events.get(&lane)
+    }
+
+    pub fn set_scratch_timeout(&mut self, timeout: Duration) {
+        self.scratch_timeout = timeout;
+        for scratch_event in self.scratch_events.values_mut() {
+            scratch_event.set_timeout(timeout);
+        }
+    }
+
+    // 入力が途切れた皿をニュートラルに戻し、戻した皿の(レーン, 軸の値)を返す
+    pub fn reset_timed_out_scratches(&mut self, now: DateTime<Local>) -> Vec<(u32, i32)> {
+        self.scratch_events
+            .iter_mut()
+            .filter(|(_, scratch_event)| scratch_event.is_timeout(now))
+            .filter_map(|(&lane, scratch_event)| {
+                scratch_event
+                    .reset_to_neutral()
+                    .then(|| (lane, scratch_event.axis()))
+            })
+            .collect()
     }
 
     pub fn notes_count(&self) -> u32 {
         self.statistics.notes_count()
     }
 
-    pub fn difference_notes_count(&self) -> u32 {
-        self.statistics.difference_notes_count()
+    pub fn statistics(&self) -> &GameStatistics {
+        &self.statistics
     }
 
-    pub fn set_last_saved_count(&mut self) {
-        self.statistics.set_last_saved_count();
+    pub fn statistics_mut(&mut self) -> &mut GameStatistics {
+        &mut self.statistics
     }
 
     pub fn average_release_time(&self) -> Duration {
         self.statistics.average_release_time()
     }
+
+    pub fn nps(&self) -> u32 {
+        self.session_tracker.nps(Local::now())
+    }
+
+    pub fn session_tracker(&self) -> &SessionTracker {
+        &self.session_tracker
+    }
+
+    pub fn session_tracker_mut(&mut self) -> &mut SessionTracker {
+        &mut self.session_tracker
+    }
+
+    pub fn take_finished_sessions(&mut self) -> Vec<PlaySession> {
+        self.session_tracker.take_finished()
+    }
+
+    pub fn goal_tracker_mut(&mut self) -> &mut GoalTracker {
+        &mut self.goal_tracker
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.statistics.play_date(Local::now().naive_local())
+    }
+
+    // 未保存のその日のノーツ数
+    pub fn pending_count(&self, date: NaiveDate) -> u32 {
+        self.statistics
+            .pending_counts()
+            .daily
+            .get(&date)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // 今日の保存済みと未保存の合計
+    pub fn today_count(&self) -> u32 {
+        let today = self.today();
+        self.goal_tracker.saved_count(today) + self.pending_count(today)
+    }
+
+    // nowの日の目標に初めて達したときに(日付, 合計ノーツ数, 目標)を返す
+    pub fn check_daily_goal(&mut self, now: DateTime<Local>) -> Option<(NaiveDate, u32, u32)> {
+        let today = self.statistics.play_date(now.naive_local());
+        let pending_count = self.pending_count(today);
+        let total = self.goal_tracker.check(today, pending_count)?;
+        let goal = self.goal_tracker.daily_goal()?;
+        Some((today, total, goal))
+    }
 }
@@ -0,0 +1,135 @@
+use crate::event_hub::EventSink;
+use crate::gamepad::event::GOAL_REACHED;
+use crate::services::{self, Services};
+use chrono::Local;
+use serde_json::Value;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// 今日のノーツ数を出力する間隔
+const STATUS_INTERVAL: Duration = Duration::from_secs(60);
+
+fn log(message: &str) {
+    println!("[{}] {}", Local::now().format("%Y-%m-%d %H:%M:%S"), message);
+}
+
+// 標準出力に残すイベントのメッセージ。入力ごとのイベントは多すぎるため出力しない
+fn log_message(event: &str, payload: &Value) -> Option<String> {
+    match event {
+        GOAL_REACHED => Some(format!(
+            "Daily goal reached: {} notes (goal {}) on {}",
+            payload["notesCount"],
+            payload["goal"],
+            payload["date"].as_str().unwrap_or_default()
+        )),
+        _ => None,
+    }
+}
+
+// イベントを標準出力へ書く
+struct LogSink;
+
+impl EventSink for LogSink {
+    fn send(&self, event: &str, payload: &Value) -> Result<(), String> {
+        if let Some(message) = log_message(event, payload) {
+            log(&message);
+        }
+        Ok(())
+    }
+}
+
+fn log_today(services: &Services) {
+    let result = (|| {
+        let gamepad = services.gamepad.lock().map_err(|e| e.to_string())?;
+        let (today, pending_count) = gamepad.today_pending_count().map_err(|e| e.to_string())?;
+        let db = services.database.lock().map_err(|e| e.to_string())?;
+        let saved_count = db
+            .get(&today.format("%Y-%m-%d").to_string())
+            .map_err(|e| e.to_string())?
+            .unwrap_or(0);
+        Ok::<_, String>(format!(
+            "Today ({}): {} notes",
+            today.format("%Y-%m-%d"),
+            saved_count as i64 + pending_count as i64
+        ))
+    })();
+    match result {
+        Ok(message) => log(&message),
+        Err(e) => eprintln!("Failed to get today's count: {}", e),
+    }
+}
+
+// ウィンドウを作らずにカウント・統計・サーバーだけを動かす。
+// SIGINT・SIGTERM（WindowsではCtrl+C）を受け取ったら保存して終了する
+pub fn run() -> Result<(), String> {
+    let settings_path = services::default_settings_path()
+        .ok_or_else(|| "Failed to find the config directory".to_string())?;
+    let local_data_dir = services::default_local_data_dir()
+        .ok_or_else(|| "Failed to find the local data directory".to_string())?;
+
+    let services = Services::start(settings_path, &local_data_dir, vec![Arc::new(LogSink)])?;
+
+    let settings = services.settings.settings();
+    log(&format!(
+        "Started in headless mode (database: {})",
+        local_data_dir.join(services::DATABASE_FILE).display()
+    ));
+    if settings.websocket_enabled || settings.overlay_enabled {
+        log(&format!(
+            "WebSocket: ws://127.0.0.1:{}",
+            settings.websocket_port
+        ));
+    }
+    if settings.overlay_enabled {
+        log(&format!(
+            "Overlay: http://127.0.0.1:{}/overlay",
+            settings.http_port
+        ));
+    }
+    if settings.api_enabled {
+        log(&format!(
+            "REST API: http://127.0.0.1:{}/api",
+            settings.http_port
+        ));
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    ctrlc::set_handler(move || {
+        let _ = sender.send(());
+    })
+    .map_err(|e| format!("Failed to set signal handler: {}", e))?;
+
+    loop {
+        match receiver.recv_timeout(STATUS_INTERVAL) {
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => log_today(&services),
+        }
+    }
+
+    log("Stopping, saving statistics...");
+    services::shutdown(
+        &Mutex::new(services.autosave),
+        &services.gamepad,
+        &services.database,
+        &Mutex::new(services.servers),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gamepad::event::{GAMEPAD_INPUT, STATS};
+
+    #[test]
+    fn test_log_only_goal_reached() {
+        let payload = serde_json::json!({ "date": "2025-01-01", "notesCount": 1000, "goal": 1000 });
+        assert_eq!(
+            log_message(GOAL_REACHED, &payload).unwrap(),
+            "Daily goal reached: 1000 notes (goal 1000) on 2025-01-01"
+        );
+        assert!(log_message(GAMEPAD_INPUT, &payload).is_none());
+        assert!(log_message(STATS, &payload).is_none());
+    }
+}
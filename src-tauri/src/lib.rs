@@ -1,12 +1,31 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+mod autosave;
+mod cli;
 mod database;
+mod error;
+mod event_hub;
 mod gamepad;
-use database::DatabaseManager;
-use gamepad::GamepadManager;
-use std::{
-    fmt::format,
-    sync::{Arc, Mutex},
+mod headless;
+mod server;
+mod services;
+mod settings;
+use autosave::Autosave;
+use chrono::NaiveDate;
+use database::{
+    backup, BackupInfo, Correction, DatabaseManager, DeletionSummary, ExportFormat, ExportSummary,
+    GoalKind, GoalProgress, Goals, Grouping, HourlyMatrix, ImportPreview, MergeStrategy,
+    PeriodStatistics, RangeSummary, Recovery, SessionRecord, Streaks,
 };
+use error::AppError;
+use event_hub::{EventHub, EventSink, TauriSink};
+use gamepad::layout::KeyLayout;
+use gamepad::recorder::{self, RecordingInfo};
+use gamepad::replay::{self, ReplayOptions};
+use gamepad::GamepadManager;
+use server::ServerManager;
+use services::Services;
+use settings::{config, Settings, SettingsStore};
+use std::sync::{Arc, Mutex};
 use tauri::{Manager, RunEvent};
 
 #[tauri::command]
@@ -14,26 +33,207 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+// 設定をゲームパッド・データベース・自動保存に反映する
+fn apply_settings(
+    settings: &Settings,
+    gamepad: &GamepadManager,
+    db: &mut DatabaseManager,
+    autosave: &Autosave,
+) -> Result<(), AppError> {
+    gamepad.set_day_rollover_hour(settings.day_rollover_hour)?;
+    gamepad.set_session_idle_gap(settings.session_idle_gap())?;
+    gamepad.set_scratch_timeout(settings.scratch_timeout())?;
+    gamepad.set_key_layout(settings.key_layout)?;
+    db.set_backup_keep(settings.backup_keep as usize);
+    autosave.set_interval(settings.autosave_interval());
+
+    // 日付変更時刻が変わると今日の日付も変わる
+    autosave::sync_saved_today(db, gamepad)?;
+
+    gamepad.set_recording(
+        settings
+            .record_inputs
+            .then(|| recorder::recordings_dir(db.path())),
+    )?;
+
+    // 送信先を解決できない場合もそれ以外の設定は反映しておく
+    gamepad
+        .set_osc(settings.osc_config())
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+fn get_settings(settings: tauri::State<Mutex<SettingsStore>>) -> Result<Settings, AppError> {
+    let settings = settings.lock()?;
+    Ok(settings.settings().clone())
+}
+
+// 設定を反映し、サーバーを設定し直す。
+// REST APIの応答中はゲームパッドとデータベースをロックするため、
+// サーバーの停止を待つのはそれらのロックを離してから
+fn apply_all_settings(
+    settings: &Settings,
+    gamepad: &Mutex<GamepadManager>,
+    db: &Mutex<DatabaseManager>,
+    autosave: &Mutex<Autosave>,
+    servers: &Mutex<ServerManager>,
+) -> Result<(), AppError> {
+    {
+        let gamepad = gamepad.lock()?;
+        let mut db = db.lock()?;
+        let autosave = autosave.lock()?;
+        apply_settings(settings, &gamepad, &mut db, &autosave)?;
+    }
+    servers.lock()?.configure(settings)?;
+    Ok(())
+}
+
+// 検証・反映してから保存し、settings-changedイベントを送る。
+// 反映か保存に失敗した場合は元の設定に戻し、ファイルも変更しない
+#[tauri::command]
+fn update_settings(
+    new_settings: Settings,
+    event_hub: tauri::State<EventHub>,
+    settings: tauri::State<Mutex<SettingsStore>>,
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+    gamepad: tauri::State<Arc<Mutex<GamepadManager>>>,
+    autosave: tauri::State<Mutex<Autosave>>,
+    servers: tauri::State<Mutex<ServerManager>>,
+) -> Result<Settings, AppError> {
+    // サーバーの停止を待つ間は設定のロックを持たない
+    let previous = settings.lock()?.settings().clone();
+    new_settings.validate()?;
+
+    let result =
+        apply_all_settings(&new_settings, &gamepad, &db, &autosave, &servers).and_then(|_| {
+            let mut settings = settings.lock()?;
+            Ok(settings.update(new_settings)?.clone())
+        });
+    let updated = match result {
+        Ok(updated) => updated,
+        Err(e) => {
+            if let Err(rollback) = apply_all_settings(&previous, &gamepad, &db, &autosave, &servers)
+            {
+                eprintln!("Failed to restore previous settings: {}", rollback);
+            }
+            return Err(e);
+        }
+    };
+
+    event_hub
+        .emit(config::SETTINGS_CHANGED, &updated)
+        .map_err(|e| AppError::Internal(format!("Failed to emit settings changed: {}", e)))?;
+    Ok(updated)
+}
+
+#[tauri::command]
+fn list_key_layouts() -> Vec<KeyLayout> {
+    KeyLayout::all()
+}
+
 #[tauri::command]
 fn get_statistics(
     date: String,
     db: tauri::State<Arc<Mutex<DatabaseManager>>>,
-) -> Result<Option<i32>, String> {
-    let db = db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    db.get(&date).map_err(|e| e.to_string())
+) -> Result<Option<i32>, AppError> {
+    let db = db.lock()?;
+    db.get(&date).map_err(AppError::from)
 }
 
 #[tauri::command]
 fn get_year_statistics(
     year: i32,
     db: tauri::State<Arc<Mutex<DatabaseManager>>>,
-) -> Result<Vec<(String, i32)>, String> {
-    let db = db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    db.get_year_statistics(year).map_err(|e| e.to_string())
+) -> Result<Vec<(String, i32)>, AppError> {
+    let db = db.lock()?;
+    db.get_year_statistics(year).map_err(AppError::from)
+}
+
+#[tauri::command]
+fn get_range_statistics(
+    start_date: String,
+    end_date: String,
+    group_by: Grouping,
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+) -> Result<Vec<PeriodStatistics>, AppError> {
+    let db = db.lock()?;
+    db.get_range_statistics(&start_date, &end_date, group_by)
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+fn get_range_summary(
+    start_date: String,
+    end_date: String,
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+) -> Result<RangeSummary, AppError> {
+    let db = db.lock()?;
+    db.get_range_summary(&start_date, &end_date)
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+fn get_hourly_statistics(
+    start_date: String,
+    end_date: String,
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+) -> Result<HourlyMatrix, AppError> {
+    let db = db.lock()?;
+    db.get_hourly_statistics(&start_date, &end_date)
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+fn get_goals(db: tauri::State<Arc<Mutex<DatabaseManager>>>) -> Result<Goals, AppError> {
+    let db = db.lock()?;
+    db.get_goals().map_err(AppError::from)
+}
+
+#[tauri::command]
+fn set_goal(
+    kind: GoalKind,
+    notes_count: Option<u32>,
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+    gamepad: tauri::State<Arc<Mutex<GamepadManager>>>,
+) -> Result<(), AppError> {
+    let gamepad = gamepad.lock()?;
+    let db = db.lock()?;
+    db.set_goal(kind, notes_count)?;
+
+    if kind == GoalKind::Daily {
+        gamepad.set_daily_goal(notes_count)?;
+        autosave::sync_saved_today(&db, &gamepad)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn get_streaks(
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+    gamepad: tauri::State<Arc<Mutex<GamepadManager>>>,
+) -> Result<Streaks, AppError> {
+    let today = {
+        let gamepad = gamepad.lock()?;
+        gamepad.today()?
+    };
+    let db = db.lock()?;
+    db.get_streaks(&today.format("%Y-%m-%d").to_string())
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+fn get_goal_progress(
+    start_date: String,
+    end_date: String,
+    kind: GoalKind,
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+) -> Result<Vec<GoalProgress>, AppError> {
+    let db = db.lock()?;
+    match kind {
+        GoalKind::Daily => db.get_daily_goal_progress(&start_date, &end_date),
+        GoalKind::Weekly => db.get_weekly_goal_progress(&start_date, &end_date),
+    }
+    .map_err(AppError::from)
 }
 
 #[tauri::command]
@@ -41,58 +241,259 @@ fn update_statistics(
     date: String,
     notes_count: i32,
     db: tauri::State<Arc<Mutex<DatabaseManager>>>,
-) -> Result<(), String> {
-    let db = db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+) -> Result<(), AppError> {
+    let db = db.lock()?;
+    db.set_notes(&date, notes_count).map_err(AppError::from)
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| AppError::InvalidInput(format!("date must be YYYY-MM-DD, got {}", date)))
+}
 
-    // 既存のデータがあれば更新、なければ挿入
-    match db.get(&date) {
-        Ok(Some(_)) => db.update(&date, notes_count).map_err(|e| e.to_string()),
-        Ok(None) => db.insert(&date, notes_count).map_err(|e| e.to_string()),
-        Err(e) => Err(e.to_string()),
+// confirmがfalseの場合は削除せず、削除される内容だけを返す。
+// 削除した日付の未保存分も捨てる（残すと次の自動保存で復活する）
+#[tauri::command]
+fn delete_statistics(
+    start_date: String,
+    end_date: String,
+    confirm: bool,
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+    gamepad: tauri::State<Arc<Mutex<GamepadManager>>>,
+) -> Result<DeletionSummary, AppError> {
+    let (start, end) = (parse_date(&start_date)?, parse_date(&end_date)?);
+    let gamepad = gamepad.lock()?;
+    let db = db.lock()?;
+    let rollover_hour = gamepad.day_rollover_hour()?;
+    if !confirm {
+        return db
+            .preview_delete_range(&start_date, &end_date, rollover_hour)
+            .map_err(AppError::from);
     }
+
+    let summary = db.delete_range(&start_date, &end_date, rollover_hour)?;
+    gamepad.discard_pending_counts(start, end)?;
+    gamepad.discard_finished_sessions(start, end)?;
+    autosave::sync_saved_today(&db, &gamepad)?;
+    Ok(summary)
 }
 
+// confirmがfalseの場合は削除せず、削除される内容だけを返す
 #[tauri::command]
-fn save_current_count(
+fn delete_all_statistics(
+    confirm: bool,
     db: tauri::State<Arc<Mutex<DatabaseManager>>>,
     gamepad: tauri::State<Arc<Mutex<GamepadManager>>>,
-) -> Result<(), String> {
-    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
-    let delta_count = {
-        let gamepad = gamepad
-            .lock()
-            .map_err(|e| format!("Failed to lock gamepad:{}", e))?;
-        gamepad
-            .difference_notes_count()
-            .map_err(|e| format!("Failed to get notes_count: {}", e))?
-    };
+) -> Result<DeletionSummary, AppError> {
+    let gamepad = gamepad.lock()?;
+    let db = db.lock()?;
+    if !confirm {
+        return db.preview_delete_all().map_err(AppError::from);
+    }
 
-    // データベースから現在の値を取得して加算
-    let new_count = {
-        let db_guard = db
-            .lock()
-            .map_err(|e| format!("Failed to lock database: {}", e))?;
-        let current_count = db_guard
-            .get(&today)
-            .map_err(|e| e.to_string())?
-            .unwrap_or(0);
-        current_count + delta_count as i32
-    };
+    let summary = db.delete_all()?;
+    gamepad.discard_pending_counts(NaiveDate::MIN, NaiveDate::MAX)?;
+    gamepad.discard_finished_sessions(NaiveDate::MIN, NaiveDate::MAX)?;
+    autosave::sync_saved_today(&db, &gamepad)?;
+    Ok(summary)
+}
 
-    match update_statistics(today, new_count as i32, db) {
-        Ok(()) => {
-            let mut gamepad = gamepad
-                .lock()
-                .map_err(|e| format!("Failed to lock gamepad: {}", e))?;
-            gamepad
-                .update_last_saved_count()
-                .map_err(|e| format!("Failed to update save count: {}", e))?;
-        }
-        Err(error) => Err(error)?,
+#[tauri::command]
+fn correct_statistics(
+    date: String,
+    notes_count: i32,
+    reason: String,
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+    gamepad: tauri::State<Arc<Mutex<GamepadManager>>>,
+) -> Result<Correction, AppError> {
+    if notes_count < 0 {
+        return Err(AppError::InvalidInput(
+            "notes_count must not be negative".to_string(),
+        ));
     }
-    Ok(())
+    if reason.trim().is_empty() {
+        return Err(AppError::InvalidInput("reason is required".to_string()));
+    }
+    let corrected_date = parse_date(&date)?;
+
+    let gamepad = gamepad.lock()?;
+    let db = db.lock()?;
+    let correction = db.correct_notes(
+        &date,
+        notes_count,
+        reason.trim(),
+        &chrono::Local::now().to_rfc3339(),
+        gamepad.day_rollover_hour()?,
+    )?;
+    // 修正した値に未保存分が加算されないようにする
+    gamepad.discard_pending_counts(corrected_date, corrected_date)?;
+    autosave::sync_saved_today(&db, &gamepad)?;
+    Ok(correction)
+}
+
+#[tauri::command]
+fn list_corrections(
+    limit: Option<u32>,
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+) -> Result<Vec<Correction>, AppError> {
+    let db = db.lock()?;
+    db.list_corrections(limit.unwrap_or(50))
+        .map_err(AppError::from)
+}
+
+// 形式はREADMEの「エクスポート形式」を参照
+#[tauri::command]
+fn export_statistics(
+    path: String,
+    format: ExportFormat,
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+) -> Result<ExportSummary, AppError> {
+    let db = db.lock()?;
+    db.export_to_file(
+        std::path::Path::new(&path),
+        format,
+        &chrono::Local::now().to_rfc3339(),
+    )
+    .map_err(AppError::from)
+}
+
+// pathは別のstatistics.db、またはエクスポートしたCSV/JSON。dry_runの場合は変更内容だけを返す
+#[tauri::command]
+fn import_statistics(
+    path: String,
+    strategy: MergeStrategy,
+    dry_run: bool,
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+    gamepad: tauri::State<Arc<Mutex<GamepadManager>>>,
+) -> Result<ImportPreview, AppError> {
+    let data = database::import::read_import_source(std::path::Path::new(&path))?;
+
+    let gamepad = gamepad.lock()?;
+    let db = db.lock()?;
+    let preview = db.import_data(&data, strategy, dry_run)?;
+    if preview.applied {
+        autosave::sync_saved_today(&db, &gamepad)?;
+    }
+    Ok(preview)
+}
+
+#[tauri::command]
+fn list_backups(
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+) -> Result<Vec<BackupInfo>, AppError> {
+    let db = db.lock()?;
+    backup::list_backups(&db.backup_dir()).map_err(AppError::from)
+}
+
+// 起動時に壊れたデータベースを退避した場合、その結果を返す
+#[tauri::command]
+fn get_database_recovery(recovery: tauri::State<Option<Recovery>>) -> Option<Recovery> {
+    recovery.inner().clone()
+}
+
+#[tauri::command]
+fn create_backup(db: tauri::State<Arc<Mutex<DatabaseManager>>>) -> Result<String, AppError> {
+    let db = db.lock()?;
+    db.create_backup(chrono::Local::now().naive_local(), db.backup_keep())
+        .map(|path| path.to_string_lossy().into_owned())
+        .map_err(AppError::from)
+}
+
+// file_nameはlist_backupsで返されたバックアップのファイル名
+#[tauri::command]
+fn restore_backup(
+    file_name: String,
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+    gamepad: tauri::State<Arc<Mutex<GamepadManager>>>,
+) -> Result<(), AppError> {
+    let gamepad = gamepad.lock()?;
+    let mut db = db.lock()?;
+    let keep = db.backup_keep();
+    db.restore_backup(&file_name, chrono::Local::now().naive_local(), keep)?;
+    autosave::sync_saved_today(&db, &gamepad)
+}
+
+#[tauri::command]
+fn list_recordings(
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+) -> Result<Vec<RecordingInfo>, AppError> {
+    let db = db.lock()?;
+    recorder::list_recordings(&recorder::recordings_dir(db.path())).map_err(AppError::from)
+}
+
+// file_nameはlist_recordingsで返された記録のファイル名。
+// 終了するとreplay-finishedイベントを送る。統計は計算し直すだけでデータベースには保存しない
+#[tauri::command]
+fn start_replay(
+    file_name: String,
+    speed: f64,
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+    gamepad: tauri::State<Arc<Mutex<GamepadManager>>>,
+) -> Result<(), AppError> {
+    if !replay::SPEED_RANGE.contains(&speed) {
+        return Err(AppError::InvalidInput(format!(
+            "speed must be between {} and {}, got {}",
+            replay::SPEED_RANGE.start(),
+            replay::SPEED_RANGE.end(),
+            speed
+        )));
+    }
+    let path = {
+        let db = db.lock()?;
+        recorder::recording_path(&recorder::recordings_dir(db.path()), &file_name)?
+    };
+    let recording = recorder::read_recording(&path)?;
+
+    let gamepad = gamepad.lock()?;
+    gamepad
+        .start_replay(recording, ReplayOptions { speed })
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+fn stop_replay(gamepad: tauri::State<Arc<Mutex<GamepadManager>>>) -> Result<(), AppError> {
+    let gamepad = gamepad.lock()?;
+    gamepad.stop_replay().map_err(AppError::from)
+}
+
+#[tauri::command]
+fn save_current_count(
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+    gamepad: tauri::State<Arc<Mutex<GamepadManager>>>,
+) -> Result<(), AppError> {
+    autosave::save_statistics(db.inner(), gamepad.inner())
+}
+
+#[tauri::command]
+fn list_sessions(
+    limit: Option<u32>,
+    offset: Option<u32>,
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+) -> Result<Vec<SessionRecord>, AppError> {
+    let db = db.lock()?;
+    db.list_sessions(limit.unwrap_or(50), offset.unwrap_or(0))
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+fn get_session(
+    id: i64,
+    db: tauri::State<Arc<Mutex<DatabaseManager>>>,
+) -> Result<Option<SessionRecord>, AppError> {
+    let db = db.lock()?;
+    db.get_session(id).map_err(AppError::from)
+}
+
+pub use cli::{Cli, Command};
+
+// statsなどのサブコマンドを実行する
+pub fn run_command(command: Command) -> Result<(), String> {
+    cli::run(command).map_err(|e| e.to_string())
+}
+
+// ウィンドウを作らずに起動する（--headless）
+pub fn run_headless() -> Result<(), String> {
+    headless::run()
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -101,34 +502,50 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .invoke_handler(tauri::generate_handler![
             greet,
+            get_settings,
+            update_settings,
+            list_key_layouts,
             get_statistics,
             get_year_statistics,
+            get_range_statistics,
+            get_range_summary,
+            get_hourly_statistics,
+            get_goals,
+            set_goal,
+            get_streaks,
+            get_goal_progress,
             update_statistics,
+            delete_statistics,
+            delete_all_statistics,
+            correct_statistics,
+            list_corrections,
+            export_statistics,
+            import_statistics,
+            list_backups,
+            get_database_recovery,
+            create_backup,
+            restore_backup,
             save_current_count,
+            list_sessions,
+            get_session,
+            list_recordings,
+            start_replay,
+            stop_replay,
         ])
         .setup(move |app| {
-            let app_handle = app.handle().clone();
+            let settings_path = app.path().app_config_dir()?.join(config::SETTINGS_FILE);
             let app_local_dir = app.path().local_data_dir()?;
-            let db_path = app_local_dir.join("statistics.db");
-
-            // データベースの初期化
-            let mut database_manager = DatabaseManager::new(db_path)
-                .map_err(|e| format!("Failed to create DatabaseManager: {}", e))?;
-
-            database_manager
-                .initialize()
-                .map_err(|e| format!("Failed to initialize database: {}", e))?;
-
-            app.manage(Arc::new(Mutex::new(database_manager)));
-
-            let gamepad_manager = GamepadManager::new()
-                .map_err(|e| format!("Failed to create GamepadManager: {}", e))?;
-
-            gamepad_manager
-                .start_event_loop(app_handle)
-                .map_err(|e| format!("Failed to start event loop: {}", e))?;
+            let sinks: Vec<Arc<dyn EventSink>> =
+                vec![Arc::new(TauriSink::new(app.handle().clone()))];
+            let services = Services::start(settings_path, &app_local_dir, sinks)?;
 
-            app.manage(Arc::new(Mutex::new(gamepad_manager)));
+            app.manage(Mutex::new(services.settings));
+            app.manage(services.event_hub);
+            app.manage(services.database);
+            app.manage(services.gamepad);
+            app.manage(Mutex::new(services.servers));
+            app.manage(Mutex::new(services.autosave));
+            app.manage(services.recovery);
             Ok(())
         })
         .build(tauri::generate_context!())
@@ -137,15 +554,13 @@ pub fn run() {
             if let RunEvent::Exit = event {
                 println!("Application is exiting, saving statistics...");
 
-                // GamepadManagerとDatabaseManagerを取得
-                if let (Some(gamepad_mgr), Some(db_mgr)) = (
+                if let (Some(autosave), Some(gamepad), Some(db), Some(servers)) = (
+                    app_handle.try_state::<Mutex<Autosave>>(),
                     app_handle.try_state::<Arc<Mutex<GamepadManager>>>(),
                     app_handle.try_state::<Arc<Mutex<DatabaseManager>>>(),
+                    app_handle.try_state::<Mutex<ServerManager>>(),
                 ) {
-                    match save_current_count(db_mgr, gamepad_mgr) {
-                        Ok(_) => println!("Statistics saved successfully"),
-                        Err(e) => eprintln!("Failed to save statistics: {}", e),
-                    }
+                    services::shutdown(&autosave, &gamepad, &db, &servers);
                 }
             }
         });
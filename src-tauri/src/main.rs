@@ -1,6 +1,67 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use clap::Parser;
+use key_display_lib::Cli;
+
+// サブコマンド・--headless・ヘルプとして起動されたか
+fn is_cli_invocation() -> bool {
+    std::env::args().nth(1).is_some_and(|arg| {
+        matches!(
+            arg.as_str(),
+            "stats" | "--headless" | "help" | "--help" | "-h" | "--version" | "-V"
+        )
+    })
+}
+
+// Windowsのリリースビルドはコンソールを持たないため、起動元のコンソールに出力をつなぐ。
+// コンソールから起動していない場合は何もしない
+#[cfg(all(windows, not(debug_assertions)))]
+fn attach_parent_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(all(windows, not(debug_assertions))))]
+fn attach_parent_console() {}
+
 fn main() {
+    if is_cli_invocation() {
+        attach_parent_console();
+    }
+
+    // GUIとして起動した場合の知らない引数（macOSの-psn_やランチャーの引数など）では終了しない
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) if is_cli_invocation() => e.exit(),
+        Err(_) => {
+            key_display_lib::run();
+            return;
+        }
+    };
+    if let Some(command) = cli.command {
+        if let Err(e) = key_display_lib::run_command(command) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if cli.headless {
+        if let Err(e) = key_display_lib::run_headless() {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     key_display_lib::run();
 }
@@ -0,0 +1,326 @@
+use super::http::{self, Handler, HttpResponse};
+use crate::database::{DatabaseManager, Grouping};
+use crate::error::AppError;
+use crate::gamepad::GamepadManager;
+use chrono::NaiveDate;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tiny_http::Method;
+
+const PREFIX: &str = "/api";
+const DEFAULT_SESSIONS_LIMIT: u32 = 50;
+const MAX_SESSIONS_LIMIT: u32 = 500;
+
+// 今日の日付と、まだデータベースに保存していない今日のノーツ数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Today {
+    pub date: NaiveDate,
+    pub pending_count: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TodayResponse {
+    date: String,
+    notes_count: i64,
+    daily_goal: Option<u32>,
+}
+
+// (ステータスコード, エラー)。エラーはコマンドと同じ{ kind, message }の形で返す
+type ApiError = (u16, AppError);
+
+impl From<AppError> for ApiError {
+    fn from(error: AppError) -> ApiError {
+        let status = match error {
+            AppError::InvalidInput(_) => 400,
+            AppError::NotFound(_) => 404,
+            _ => 500,
+        };
+        (status, error)
+    }
+}
+
+fn json_response(status: u16, body: &impl Serialize) -> HttpResponse {
+    let body = serde_json::to_vec(body).unwrap_or_default();
+    http::response(status, "application/json; charset=utf-8", body)
+}
+
+fn bad_request(message: String) -> ApiError {
+    (400, AppError::InvalidInput(message))
+}
+
+fn not_found(message: String) -> ApiError {
+    (404, AppError::NotFound(message))
+}
+
+// /api以下のパスなら/apiより後ろを返す。/apixなどは対象外
+fn api_path(url: &str) -> Option<&str> {
+    let path = http::path(url).strip_prefix(PREFIX)?;
+    (path.is_empty() || path.starts_with('/')).then_some(path)
+}
+
+fn date_param<'a>(params: &BTreeMap<&str, &'a str>, name: &str) -> Result<&'a str, ApiError> {
+    let value = params
+        .get(name)
+        .ok_or_else(|| bad_request(format!("Missing query parameter: {}", name)))?;
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| bad_request(format!("{} must be YYYY-MM-DD, got {}", name, value)))?;
+    Ok(value)
+}
+
+// start・endのクエリパラメーター（両端を含む）
+fn range_params<'a>(params: &BTreeMap<&str, &'a str>) -> Result<(&'a str, &'a str), ApiError> {
+    let start = date_param(params, "start")?;
+    let end = date_param(params, "end")?;
+    if start > end {
+        return Err(bad_request(format!(
+            "start must not be after end, got {} > {}",
+            start, end
+        )));
+    }
+    Ok((start, end))
+}
+
+fn number_param(params: &BTreeMap<&str, &str>, name: &str, default: u32) -> Result<u32, ApiError> {
+    params.get(name).map_or(Ok(default), |value| {
+        value
+            .parse()
+            .map_err(|_| bad_request(format!("{} must be a number, got {}", name, value)))
+    })
+}
+
+fn to_value(body: impl Serialize) -> Result<Value, ApiError> {
+    serde_json::to_value(body)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize: {}", e)).into())
+}
+
+fn handle(
+    path: &str,
+    params: &BTreeMap<&str, &str>,
+    db: &DatabaseManager,
+    today: Today,
+) -> Result<Value, ApiError> {
+    let today_str = today.date.format("%Y-%m-%d").to_string();
+
+    match path.trim_end_matches('/') {
+        "/today" => {
+            let saved_count = db.get(&today_str).map_err(AppError::from)?.unwrap_or(0);
+            to_value(TodayResponse {
+                date: today_str,
+                notes_count: saved_count as i64 + today.pending_count as i64,
+                daily_goal: db.get_goals().map_err(AppError::from)?.daily,
+            })
+        }
+        "/statistics" => {
+            let (start, end) = range_params(params)?;
+            let group_by = params.get("groupBy").copied().unwrap_or("day");
+            let grouping: Grouping = serde_json::from_value(Value::from(group_by))
+                .map_err(|_| bad_request(format!("Invalid groupBy: {}", group_by)))?;
+            to_value(
+                db.get_range_statistics(start, end, grouping)
+                    .map_err(AppError::from)?,
+            )
+        }
+        "/summary" => {
+            let (start, end) = range_params(params)?;
+            to_value(db.get_range_summary(start, end).map_err(AppError::from)?)
+        }
+        "/keys" => {
+            let (start, end) = range_params(params)?;
+            to_value(db.get_key_breakdown(start, end).map_err(AppError::from)?)
+        }
+        "/streaks" => to_value(db.get_streaks(&today_str).map_err(AppError::from)?),
+        "/sessions" => {
+            let limit = number_param(params, "limit", DEFAULT_SESSIONS_LIMIT)?;
+            let offset = number_param(params, "offset", 0)?;
+            to_value(
+                db.list_sessions(limit.min(MAX_SESSIONS_LIMIT), offset)
+                    .map_err(AppError::from)?,
+            )
+        }
+        path => {
+            let id = path
+                .strip_prefix("/sessions/")
+                .ok_or_else(|| not_found(format!("Unknown endpoint: {}{}", PREFIX, path)))?;
+            let id: i64 = id
+                .parse()
+                .map_err(|_| bad_request(format!("Invalid session id: {}", id)))?;
+            match db.get_session(id).map_err(AppError::from)? {
+                Some(session) => to_value(session),
+                None => Err(not_found(format!("Session {} not found", id))),
+            }
+        }
+    }
+}
+
+// /api以下のリクエストに応答する。データベースは読み取るだけ
+pub fn route(
+    method: &Method,
+    url: &str,
+    db: &DatabaseManager,
+    today: Today,
+) -> Option<HttpResponse> {
+    let path = api_path(url)?;
+
+    if *method != Method::Get {
+        let error = AppError::InvalidInput(format!("Method {} is not allowed", method));
+        return Some(json_response(405, &error));
+    }
+
+    Some(match handle(path, &http::query_params(url), db, today) {
+        Ok(body) => json_response(200, &body),
+        Err((status, error)) => json_response(status, &error),
+    })
+}
+
+// 自動保存と同じく、ゲームパッド・データベースの順にロックする。
+// 未保存のノーツ数と保存済みのノーツ数を同じ時点で読むため、両方をロックしたまま応答を作る
+pub fn handler(db: Arc<Mutex<DatabaseManager>>, gamepad: Arc<Mutex<GamepadManager>>) -> Handler {
+    Box::new(move |method, url| {
+        // 対象外のリクエストではロックしない
+        api_path(url)?;
+
+        let result = (|| {
+            let gamepad = gamepad.lock()?;
+            let (date, pending_count) = gamepad.today_pending_count()?;
+            let db = db.lock()?;
+            Ok::<_, AppError>(route(
+                method,
+                url,
+                &db,
+                Today {
+                    date,
+                    pending_count,
+                },
+            ))
+        })();
+        match result {
+            Ok(response) => response,
+            Err(error) => Some(json_response(500, &error)),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_util::{cleanup_test_db, setup_test_db};
+    use crate::database::SessionRecord;
+    use std::collections::BTreeMap;
+    use std::io::Read;
+
+    fn get(db: &DatabaseManager, url: &str) -> (u16, Value) {
+        let today = Today {
+            date: NaiveDate::from_ymd_opt(2025, 1, 3).unwrap(),
+            pending_count: 25,
+        };
+        let response = route(&Method::Get, url, db, today).unwrap();
+        let status = response.status_code().0;
+        let mut body = String::new();
+        response.into_reader().read_to_string(&mut body).unwrap();
+        (status, serde_json::from_str(&body).unwrap())
+    }
+
+    #[test]
+    fn test_today_includes_unsaved_notes() {
+        let (db, path) = setup_test_db("api_today");
+        db.insert("2025-01-03", 100).unwrap();
+
+        let (status, body) = get(&db, "/api/today");
+        assert_eq!(status, 200);
+        assert_eq!(
+            body,
+            serde_json::json!({ "date": "2025-01-03", "notesCount": 125, "dailyGoal": null })
+        );
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_range_endpoints() {
+        let (db, path) = setup_test_db("api_range");
+        db.insert("2025-01-01", 100).unwrap();
+        db.insert("2025-01-02", 300).unwrap();
+        db.insert_session(&SessionRecord {
+            id: 0,
+            start_time: "2025-01-02T20:00:00+09:00".to_string(),
+            end_time: "2025-01-02T20:30:00+09:00".to_string(),
+            notes_count: 300,
+            scratch_count: 0,
+            peak_nps: 10,
+            average_release_time: 40.0,
+            release_count: 300,
+            key_counts: BTreeMap::from([(1, 300)]),
+        })
+        .unwrap();
+
+        let (status, body) = get(
+            &db,
+            "/api/statistics?start=2025-01-01&end=2025-01-31&groupBy=month",
+        );
+        assert_eq!(status, 200);
+        assert_eq!(body[0]["period"], "2025-01");
+        assert_eq!(body[0]["notesCount"], 400);
+
+        let (_, body) = get(&db, "/api/summary?start=2025-01-01&end=2025-01-02");
+        assert_eq!(body["bestDay"]["date"], "2025-01-02");
+
+        let (_, body) = get(&db, "/api/keys?start=2025-01-01&end=2025-01-02");
+        assert_eq!(body, serde_json::json!([{ "button": 1, "count": 300 }]));
+
+        let (_, body) = get(&db, "/api/sessions?limit=10");
+        let id = body[0]["id"].as_i64().unwrap();
+        let (status, body) = get(&db, &format!("/api/sessions/{}", id));
+        assert_eq!(status, 200);
+        assert_eq!(body["notesCount"], 300);
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_errors() {
+        let (db, path) = setup_test_db("api_errors");
+
+        let (status, body) = get(&db, "/api/summary?start=2025-01-01");
+        assert_eq!(status, 400);
+        assert_eq!(body["kind"], "invalidInput");
+        assert_eq!(
+            get(&db, "/api/summary?start=2025-01-05&end=2025-01-01").0,
+            400
+        );
+        assert_eq!(
+            get(
+                &db,
+                "/api/statistics?start=2025-01-01&end=2025-01-02&groupBy=hour"
+            )
+            .0,
+            400
+        );
+        let (status, body) = get(&db, "/api/sessions/999");
+        assert_eq!(status, 404);
+        assert_eq!(body["kind"], "notFound");
+        assert_eq!(get(&db, "/api/unknown").0, 404);
+
+        let today = Today {
+            date: NaiveDate::from_ymd_opt(2025, 1, 3).unwrap(),
+            pending_count: 0,
+        };
+        let response = route(&Method::Post, "/api/today", &db, today).unwrap();
+        assert_eq!(response.status_code().0, 405);
+        assert!(route(&Method::Get, "/overlay", &db, today).is_none());
+        assert!(route(&Method::Get, "/apix", &db, today).is_none());
+
+        cleanup_test_db(path);
+    }
+
+    #[test]
+    fn test_api_path() {
+        assert_eq!(api_path("/api"), Some(""));
+        assert_eq!(api_path("/api/today?x=1"), Some("/today"));
+        assert_eq!(api_path("/apix"), None);
+        assert_eq!(api_path("/apix/today"), None);
+        assert_eq!(api_path("/overlay"), None);
+    }
+}
@@ -0,0 +1,18 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ServerError {
+    BindError(String),
+    ConnectionError(String),
+}
+
+impl std::error::Error for ServerError {}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ServerError::BindError(msg) => write!(f, "Bind error: {}", msg),
+            ServerError::ConnectionError(msg) => write!(f, "Connection error: {}", msg),
+        }
+    }
+}
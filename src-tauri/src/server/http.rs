@@ -0,0 +1,154 @@
+use super::error::ServerError;
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::net::Ipv4Addr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+pub type HttpResponse = Response<Cursor<Vec<u8>>>;
+// 対応するパスならレスポンスを返す。Noneの場合は次のハンドラーに任せる
+pub type Handler = Box<dyn Fn(&Method, &str) -> Option<HttpResponse> + Send>;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+pub fn response(status: u16, content_type: &str, body: impl Into<Vec<u8>>) -> HttpResponse {
+    let mut response = Response::from_data(body.into()).with_status_code(status);
+    for (field, value) in [
+        ("Content-Type", content_type),
+        ("Cache-Control", "no-store"),
+    ] {
+        if let Ok(header) = Header::from_bytes(field, value) {
+            response.add_header(header);
+        }
+    }
+    response
+}
+
+pub fn not_found() -> HttpResponse {
+    response(404, "text/plain; charset=utf-8", "Not Found")
+}
+
+// クエリを除いたパス
+pub fn path(url: &str) -> &str {
+    url.split(['?', '#']).next().unwrap_or(url)
+}
+
+// クエリパラメーター。値のデコードはしない
+pub fn query_params(url: &str) -> BTreeMap<&str, &str> {
+    let query = url.split_once('?').map(|(_, query)| query).unwrap_or("");
+    let query = query.split('#').next().unwrap_or("");
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+        .collect()
+}
+
+// localhostでHTTPのリクエストを受け付け、登録順にハンドラーへ渡すサーバー
+pub struct HttpServer {
+    port: u16,
+    is_running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl HttpServer {
+    // portが0の場合は空いているポートを使う
+    pub fn start(port: u16, handlers: Vec<Handler>) -> Result<HttpServer, ServerError> {
+        let server = Server::http((Ipv4Addr::LOCALHOST, port))
+            .map_err(|e| ServerError::BindError(format!("Failed to bind port {}: {}", port, e)))?;
+        let port = server
+            .server_addr()
+            .to_ip()
+            .map(|addr| addr.port())
+            .unwrap_or(port);
+
+        let is_running = Arc::new(AtomicBool::new(true));
+        let thread_is_running = Arc::clone(&is_running);
+        let handle = thread::spawn(move || {
+            Self::run_loop(server, handlers, thread_is_running);
+        });
+
+        Ok(HttpServer {
+            port,
+            is_running,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn stop(&mut self) {
+        self.is_running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                eprintln!("HTTP server thread panicked");
+            }
+        }
+    }
+
+    fn run_loop(server: Server, handlers: Vec<Handler>, is_running: Arc<AtomicBool>) {
+        while is_running.load(Ordering::Relaxed) {
+            match server.recv_timeout(POLL_INTERVAL) {
+                Ok(Some(request)) => Self::handle(request, &handlers),
+                Ok(None) => {}
+                Err(e) => eprintln!("Failed to receive HTTP request: {}", e),
+            }
+        }
+    }
+
+    fn handle(request: Request, handlers: &[Handler]) {
+        let method = request.method().clone();
+        let response = handlers
+            .iter()
+            .find_map(|handler| handler(&method, request.url()))
+            .unwrap_or_else(not_found);
+
+        if let Err(e) = request.respond(response) {
+            eprintln!("Failed to send HTTP response: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    // (ステータスコード, ボディ)を返す
+    pub fn get(port: u16, url: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).unwrap();
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            url
+        )
+        .unwrap();
+
+        let mut raw = String::new();
+        stream.read_to_string(&mut raw).unwrap();
+        let status = raw.split(' ').nth(1).unwrap().parse().unwrap();
+        let body = raw.split_once("\r\n\r\n").unwrap().1.to_string();
+        (status, body)
+    }
+
+    #[test]
+    fn test_handlers_are_tried_in_order() {
+        let handlers: Vec<Handler> = vec![
+            Box::new(|_, url| (path(url) == "/a").then(|| response(200, "text/plain", "first"))),
+            Box::new(|_, url| (path(url) != "/b").then(|| response(200, "text/plain", "second"))),
+        ];
+        let mut server = HttpServer::start(0, handlers).unwrap();
+
+        assert_eq!(get(server.port(), "/a?x=1"), (200, "first".to_string()));
+        assert_eq!(get(server.port(), "/c"), (200, "second".to_string()));
+        assert_eq!(get(server.port(), "/b").0, 404);
+
+        server.stop();
+    }
+}
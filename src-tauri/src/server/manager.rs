@@ -0,0 +1,108 @@
+use super::api;
+use super::error::ServerError;
+use super::http::{Handler, HttpServer};
+use super::overlay::{self, OverlayConfig};
+use super::websocket::{WebSocketServer, WebSocketSink};
+use crate::database::DatabaseManager;
+use crate::gamepad::layout::KeyLayout;
+use crate::gamepad::GamepadManager;
+use crate::settings::Settings;
+use std::sync::{Arc, Mutex, RwLock};
+
+// 設定に合わせてWebSocketサーバーとHTTPサーバーを起動・停止する
+pub struct ServerManager {
+    websocket_sink: Arc<WebSocketSink>,
+    overlay_config: Arc<RwLock<OverlayConfig>>,
+    db: Arc<Mutex<DatabaseManager>>,
+    gamepad: Arc<Mutex<GamepadManager>>,
+    websocket: Option<WebSocketServer>,
+    // HTTPサーバーと、起動時の(オーバーレイ, REST API)の有効・無効
+    http: Option<(HttpServer, (bool, bool))>,
+}
+
+impl ServerManager {
+    pub fn new(
+        websocket_sink: Arc<WebSocketSink>,
+        db: Arc<Mutex<DatabaseManager>>,
+        gamepad: Arc<Mutex<GamepadManager>>,
+        settings: &Settings,
+    ) -> ServerManager {
+        ServerManager {
+            websocket_sink,
+            overlay_config: Arc::new(RwLock::new(Self::overlay_config(settings))),
+            db,
+            gamepad,
+            websocket: None,
+            http: None,
+        }
+    }
+
+    fn overlay_config(settings: &Settings) -> OverlayConfig {
+        OverlayConfig {
+            websocket_port: settings.websocket_port,
+            play_side: settings.play_side,
+            key_layout: settings.key_layout,
+            layouts: KeyLayout::all(),
+        }
+    }
+
+    fn http_handlers(&self, (overlay_enabled, api_enabled): (bool, bool)) -> Vec<Handler> {
+        let mut handlers = Vec::new();
+        if overlay_enabled {
+            handlers.push(overlay::handler(Arc::clone(&self.overlay_config)));
+        }
+        if api_enabled {
+            handlers.push(api::handler(
+                Arc::clone(&self.db),
+                Arc::clone(&self.gamepad),
+            ));
+        }
+        handlers
+    }
+
+    // オーバーレイはWebSocketでイベントを受け取るため、オーバーレイが有効ならWebSocketサーバーも起動する
+    pub fn configure(&mut self, settings: &Settings) -> Result<(), ServerError> {
+        if let Ok(mut config) = self.overlay_config.write() {
+            *config = Self::overlay_config(settings);
+        }
+
+        let websocket_enabled = settings.websocket_enabled || settings.overlay_enabled;
+        if let Some(server) = &mut self.websocket {
+            if !websocket_enabled || server.port() != settings.websocket_port {
+                server.stop();
+                self.websocket = None;
+            }
+        }
+        // 配信する内容が変わった場合はハンドラーを入れ替えるため起動し直す
+        let http_features = (settings.overlay_enabled, settings.api_enabled);
+        let http_enabled = settings.overlay_enabled || settings.api_enabled;
+        if let Some((server, features)) = &mut self.http {
+            if !http_enabled || server.port() != settings.http_port || *features != http_features {
+                server.stop();
+                self.http = None;
+            }
+        }
+
+        if websocket_enabled && self.websocket.is_none() {
+            self.websocket = Some(WebSocketServer::start(
+                settings.websocket_port,
+                Arc::clone(&self.websocket_sink),
+            )?);
+        }
+        if http_enabled && self.http.is_none() {
+            let handlers = self.http_handlers(http_features);
+            let server = HttpServer::start(settings.http_port, handlers)?;
+            self.http = Some((server, http_features));
+        }
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(mut server) = self.websocket.take() {
+            server.stop();
+        }
+        if let Some((mut server, _)) = self.http.take() {
+            server.stop();
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pub mod api;
+pub mod error;
+pub mod http;
+pub mod manager;
+pub mod overlay;
+pub mod websocket;
+
+pub use error::ServerError;
+pub use manager::ServerManager;
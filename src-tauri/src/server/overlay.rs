@@ -0,0 +1,75 @@
+use super::http::{self, Handler};
+use crate::gamepad::layout::{KeyLayout, KeyLayoutId};
+use crate::settings::PlaySide;
+use serde::Serialize;
+use std::sync::{Arc, RwLock};
+use tiny_http::Method;
+
+pub const DEFAULT_PORT: u16 = 9002;
+const OVERLAY_HTML: &str = include_str!("overlay.html");
+const CONFIG_PLACEHOLDER: &str = "__OVERLAY_CONFIG__";
+
+// オーバーレイのページに埋め込む設定
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverlayConfig {
+    pub websocket_port: u16,
+    pub play_side: PlaySide,
+    pub key_layout: KeyLayoutId,
+    // settings-changedでレイアウトが変わっても描き直せるよう、すべてのレイアウトを埋め込む
+    pub layouts: Vec<KeyLayout>,
+}
+
+pub fn render(config: &OverlayConfig) -> String {
+    let config = serde_json::to_string(config).unwrap_or_else(|_| "{}".to_string());
+    OVERLAY_HTML.replace(CONFIG_PLACEHOLDER, &config)
+}
+
+// GET /と/overlayでオーバーレイのページを返す
+pub fn handler(config: Arc<RwLock<OverlayConfig>>) -> Handler {
+    Box::new(move |method, url| {
+        if *method != Method::Get || !matches!(http::path(url), "/" | "/overlay") {
+            return None;
+        }
+        let config = config.read().ok()?.clone();
+        Some(http::response(
+            200,
+            "text/html; charset=utf-8",
+            render(&config),
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::http::tests::get;
+    use crate::server::http::HttpServer;
+
+    #[test]
+    fn test_serve_overlay_with_config() {
+        let config = Arc::new(RwLock::new(OverlayConfig {
+            websocket_port: 9100,
+            play_side: PlaySide::TwoP,
+            key_layout: KeyLayoutId::Mania4k,
+            layouts: KeyLayout::all(),
+        }));
+        let mut server = HttpServer::start(0, vec![handler(Arc::clone(&config))]).unwrap();
+
+        let (status, body) = get(server.port(), "/overlay?skin=neon&layout=1p");
+        assert_eq!(status, 200);
+        assert!(body.contains(
+            r#"const CONFIG = {"websocketPort":9100,"playSide":"2p","keyLayout":"mania4k","layouts":[{"id":"iidxSp","#
+        ));
+        assert!(!body.contains(CONFIG_PLACEHOLDER));
+
+        // 設定の変更は次のリクエストから反映される
+        config.write().unwrap().websocket_port = 9200;
+        assert!(get(server.port(), "/")
+            .1
+            .contains(r#""websocketPort":9200"#));
+        assert_eq!(get(server.port(), "/missing").0, 404);
+
+        server.stop();
+    }
+}
@@ -0,0 +1,414 @@
+use super::error::ServerError;
+use crate::event_hub::EventSink;
+use crate::gamepad::event::STATS;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tungstenite::handshake::server::{Callback, ErrorResponse, Request, Response};
+use tungstenite::http::header::ORIGIN;
+use tungstenite::http::StatusCode;
+use tungstenite::{HandshakeError, Message, WebSocket};
+
+pub const DEFAULT_PORT: u16 = 9001;
+// 新しく接続したクライアントへ、最後に送った内容をすぐに送るイベント
+const RETAINED_EVENTS: &[&str] = &[STATS];
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+// ハンドシェイクを送らないクライアント（ポートの確認など）を切断するまでの時間
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+// クライアントごとに送信待ちにできるメッセージ数。溢れたクライアントは切断する
+const CLIENT_QUEUE_SIZE: usize = 256;
+
+// 接続中のクライアントへイベントを{ event, payload }のJSONで送る
+#[derive(Default)]
+pub struct WebSocketSink {
+    clients: Mutex<Vec<SyncSender<String>>>,
+    retained: Mutex<BTreeMap<String, String>>,
+}
+
+impl WebSocketSink {
+    pub fn new() -> WebSocketSink {
+        WebSocketSink::default()
+    }
+
+    fn subscribe(&self) -> Receiver<String> {
+        let (sender, receiver) = mpsc::sync_channel(CLIENT_QUEUE_SIZE);
+        if let Ok(retained) = self.retained.lock() {
+            for message in retained.values() {
+                let _ = sender.try_send(message.clone());
+            }
+        }
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.push(sender);
+        }
+        receiver
+    }
+
+    fn disconnect_all(&self) {
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.clear();
+        }
+    }
+}
+
+impl EventSink for WebSocketSink {
+    fn send(&self, event: &str, payload: &Value) -> Result<(), String> {
+        let message = serde_json::json!({ "event": event, "payload": payload }).to_string();
+
+        if RETAINED_EVENTS.contains(&event) {
+            let mut retained = self.retained.lock().map_err(|e| e.to_string())?;
+            retained.insert(event.to_string(), message.clone());
+        }
+
+        // 切断したクライアントと、受信が追いつかずキューが溢れたクライアントは取り除く。
+        // 送信側がなくなると、クライアントのスレッドは残りを送ってから接続を閉じる
+        let mut clients = self.clients.lock().map_err(|e| e.to_string())?;
+        clients.retain(|client| client.try_send(message.clone()).is_ok());
+        Ok(())
+    }
+}
+
+// localhostでWebSocketの接続を受け付けるサーバー
+pub struct WebSocketServer {
+    port: u16,
+    is_running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl WebSocketServer {
+    // portが0の場合は空いているポートを使う
+    pub fn start(port: u16, sink: Arc<WebSocketSink>) -> Result<WebSocketServer, ServerError> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+            .map_err(|e| ServerError::BindError(format!("Failed to bind port {}: {}", port, e)))?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| ServerError::BindError(format!("Failed to get address: {}", e)))?
+            .port();
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| ServerError::BindError(format!("Failed to set nonblocking: {}", e)))?;
+
+        let is_running = Arc::new(AtomicBool::new(true));
+        let thread_is_running = Arc::clone(&is_running);
+        let handle = thread::spawn(move || {
+            Self::run_accept_loop(listener, sink, thread_is_running);
+        });
+
+        Ok(WebSocketServer {
+            port,
+            is_running,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    // 接続中のクライアントを切断し、スレッドの終了を待つ
+    pub fn stop(&mut self) {
+        self.is_running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                eprintln!("WebSocket server thread panicked");
+            }
+        }
+    }
+
+    fn run_accept_loop(
+        listener: TcpListener,
+        sink: Arc<WebSocketSink>,
+        is_running: Arc<AtomicBool>,
+    ) {
+        let mut clients: Vec<JoinHandle<()>> = Vec::new();
+
+        while is_running.load(Ordering::Relaxed) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    let receiver = sink.subscribe();
+                    let is_running = Arc::clone(&is_running);
+                    clients.push(thread::spawn(move || {
+                        if let Err(e) = Self::serve_client(stream, receiver, is_running) {
+                            eprintln!("WebSocket client error: {}", e);
+                        }
+                    }));
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(e) => eprintln!("Failed to accept WebSocket connection: {}", e),
+            }
+            clients.retain(|client| !client.is_finished());
+        }
+
+        sink.disconnect_all();
+        for client in clients {
+            let _ = client.join();
+        }
+    }
+
+    fn serve_client(
+        stream: TcpStream,
+        receiver: Receiver<String>,
+        is_running: Arc<AtomicBool>,
+    ) -> Result<(), ServerError> {
+        // ハンドシェイクの後は送信と受信を交互に確認する
+        let mut socket = Self::handshake(stream, &is_running)?;
+
+        while is_running.load(Ordering::Relaxed) {
+            match receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(message) => {
+                    Self::write(&mut socket, message)?;
+                    while let Ok(message) = receiver.try_recv() {
+                        Self::write(&mut socket, message)?;
+                    }
+                    Self::flush(&mut socket)?;
+                }
+                Err(RecvTimeoutError::Timeout) => Self::flush(&mut socket)?,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            // クライアントからのメッセージは読み捨てる（Ping・Closeへの応答のため）
+            match socket.read() {
+                Ok(_) => {}
+                Err(e) if is_would_block(&e) => {}
+                Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                    return Ok(())
+                }
+                Err(e) => return Err(ServerError::ConnectionError(e.to_string())),
+            }
+        }
+
+        let _ = socket.close(None);
+        let _ = socket.flush();
+        Ok(())
+    }
+
+    // ノンブロッキングで行い、サーバーの停止とタイムアウトを確認しながら待つ。
+    // ブロッキングにすると、何も送らないクライアントのスレッドが終わらずstopが戻らない
+    fn handshake(
+        stream: TcpStream,
+        is_running: &AtomicBool,
+    ) -> Result<WebSocket<TcpStream>, ServerError> {
+        stream
+            .set_nonblocking(true)
+            .map_err(|e| ServerError::ConnectionError(e.to_string()))?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+
+        let mut result = tungstenite::accept_hdr(stream, OriginCheck);
+        loop {
+            match result {
+                Ok(socket) => return Ok(socket),
+                Err(HandshakeError::Interrupted(mid)) => {
+                    if !is_running.load(Ordering::Relaxed) {
+                        return Err(ServerError::ConnectionError(
+                            "Server stopped during handshake".to_string(),
+                        ));
+                    }
+                    if Instant::now() >= deadline {
+                        return Err(ServerError::ConnectionError(
+                            "Handshake timed out".to_string(),
+                        ));
+                    }
+                    thread::sleep(POLL_INTERVAL);
+                    result = mid.handshake();
+                }
+                Err(HandshakeError::Failure(e)) => {
+                    return Err(ServerError::ConnectionError(format!(
+                        "Handshake failed: {}",
+                        e
+                    )))
+                }
+            }
+        }
+    }
+
+    fn write(socket: &mut WebSocket<TcpStream>, message: String) -> Result<(), ServerError> {
+        match socket.write(Message::text(message)) {
+            Err(e) if !is_would_block(&e) => Err(ServerError::ConnectionError(e.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    fn flush(socket: &mut WebSocket<TcpStream>) -> Result<(), ServerError> {
+        match socket.flush() {
+            Err(e) if !is_would_block(&e) => Err(ServerError::ConnectionError(e.to_string())),
+            _ => Ok(()),
+        }
+    }
+}
+
+// ブラウザは他のサイトからもlocalhostへ接続できるため、ページのOriginを確認する。
+// Originがない（ブラウザ以外）、null・file://（ローカルのファイル）、localhostのページだけを受け付ける
+fn is_allowed_origin(origin: Option<&str>) -> bool {
+    let Some(origin) = origin else {
+        return true;
+    };
+    if origin == "null" || origin.starts_with("file://") {
+        return true;
+    }
+    let Some(host) = origin
+        .strip_prefix("http://")
+        .or_else(|| origin.strip_prefix("https://"))
+    else {
+        return false;
+    };
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split_once(']').map(|(host, _)| host),
+        None => host.split(':').next(),
+    };
+    matches!(host, Some("localhost" | "127.0.0.1" | "::1"))
+}
+
+// ハンドシェイクの要求でOriginを確認し、許可しないページには403を返す
+struct OriginCheck;
+
+impl Callback for OriginCheck {
+    fn on_request(self, request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+        // 文字列にできないOriginは拒否する
+        let origin = request
+            .headers()
+            .get(ORIGIN)
+            .map(|value| value.to_str().unwrap_or_default());
+        if is_allowed_origin(origin) {
+            return Ok(response);
+        }
+        let mut response = ErrorResponse::new(Some("Origin not allowed".to_string()));
+        *response.status_mut() = StatusCode::FORBIDDEN;
+        Err(response)
+    }
+}
+
+fn is_would_block(error: &tungstenite::Error) -> bool {
+    matches!(error, tungstenite::Error::Io(e) if e.kind() == ErrorKind::WouldBlock)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tungstenite::client::IntoClientRequest;
+    use tungstenite::stream::MaybeTlsStream;
+
+    fn connect(port: u16) -> WebSocket<MaybeTlsStream<TcpStream>> {
+        let (socket, _) = tungstenite::connect(format!("ws://127.0.0.1:{}", port)).unwrap();
+        socket
+    }
+
+    fn read_json(socket: &mut WebSocket<MaybeTlsStream<TcpStream>>) -> Value {
+        let message = socket.read().unwrap();
+        serde_json::from_str(message.to_text().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_broadcast_to_clients() {
+        let sink = Arc::new(WebSocketSink::new());
+        let mut server = WebSocketServer::start(0, Arc::clone(&sink)).unwrap();
+
+        let mut first = connect(server.port());
+        let mut second = connect(server.port());
+        sink.send(
+            "gamepad-input",
+            &serde_json::json!({ "type": "button", "button": 1 }),
+        )
+        .unwrap();
+
+        for socket in [&mut first, &mut second] {
+            assert_eq!(
+                read_json(socket),
+                serde_json::json!({
+                    "event": "gamepad-input",
+                    "payload": { "type": "button", "button": 1 }
+                })
+            );
+        }
+
+        server.stop();
+    }
+
+    #[test]
+    fn test_new_client_receives_retained_stats() {
+        let sink = Arc::new(WebSocketSink::new());
+        let mut server = WebSocketServer::start(0, Arc::clone(&sink)).unwrap();
+
+        sink.send(STATS, &serde_json::json!({ "count": 10 }))
+            .unwrap();
+        sink.send(STATS, &serde_json::json!({ "count": 20 }))
+            .unwrap();
+
+        let mut socket = connect(server.port());
+        assert_eq!(read_json(&mut socket)["payload"]["count"], 20);
+
+        server.stop();
+    }
+
+    #[test]
+    fn test_stop_with_client_that_never_handshakes() {
+        let sink = Arc::new(WebSocketSink::new());
+        let mut server = WebSocketServer::start(0, Arc::clone(&sink)).unwrap();
+
+        let _idle = TcpStream::connect(("127.0.0.1", server.port())).unwrap();
+        thread::sleep(Duration::from_millis(50));
+
+        let started = Instant::now();
+        server.stop();
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_origin_is_checked() {
+        for origin in [
+            None,
+            Some("null"),
+            Some("file:///C:/overlay.html"),
+            Some("http://localhost:9002"),
+            Some("http://127.0.0.1:9002"),
+            Some("https://[::1]"),
+        ] {
+            assert!(is_allowed_origin(origin), "{:?}", origin);
+        }
+        for origin in [
+            "https://example.com",
+            "http://localhost.example.com",
+            "http://127.0.0.1.example.com",
+            "ws://localhost",
+            "",
+        ] {
+            assert!(!is_allowed_origin(Some(origin)), "{}", origin);
+        }
+
+        let sink = Arc::new(WebSocketSink::new());
+        let mut server = WebSocketServer::start(0, Arc::clone(&sink)).unwrap();
+        let connect_from = |origin: &str| {
+            let mut request = format!("ws://127.0.0.1:{}", server.port())
+                .into_client_request()
+                .unwrap();
+            request
+                .headers_mut()
+                .insert(ORIGIN, origin.parse().unwrap());
+            tungstenite::connect(request)
+        };
+
+        assert!(connect_from("https://example.com").is_err());
+        assert!(connect_from("http://localhost:9002").is_ok());
+
+        server.stop();
+    }
+
+    #[test]
+    fn test_client_that_falls_behind_is_dropped() {
+        let sink = WebSocketSink::new();
+        let receiver = sink.subscribe();
+
+        for count in 0..=CLIENT_QUEUE_SIZE {
+            sink.send(STATS, &serde_json::json!({ "count": count }))
+                .unwrap();
+        }
+
+        assert!(sink.clients.lock().unwrap().is_empty());
+        // 溢れる前に入ったメッセージは受け取れる
+        assert_eq!(receiver.iter().count(), CLIENT_QUEUE_SIZE);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::autosave::{self, Autosave};
+use crate::database::{backup, DatabaseManager, Recovery};
+use crate::event_hub::{EventHub, EventSink};
+use crate::gamepad::{recorder, GamepadManager};
+use crate::server::websocket::WebSocketSink;
+use crate::server::ServerManager;
+use crate::settings::{config, SettingsStore};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+pub const APP_IDENTIFIER: &str = "key-display";
+pub const DATABASE_FILE: &str = "statistics.db";
+
+// ウィンドウの有無に関係なく動かす、カウント・統計・サーバーの各マネージャー
+pub struct Services {
+    pub settings: SettingsStore,
+    pub event_hub: EventHub,
+    pub database: Arc<Mutex<DatabaseManager>>,
+    pub gamepad: Arc<Mutex<GamepadManager>>,
+    pub servers: ServerManager,
+    pub autosave: Autosave,
+    // 起動時にデータベースを退避した場合の結果
+    pub recovery: Option<Recovery>,
+}
+
+impl Services {
+    // イベントはsinksと、有効な場合はWebSocketのクライアントへ送る
+    pub fn start(
+        settings_path: PathBuf,
+        local_data_dir: &Path,
+        sinks: Vec<Arc<dyn EventSink>>,
+    ) -> Result<Services, String> {
+        // 設定の読み込み。読めない場合は既定値で起動する
+        let settings_store = SettingsStore::load(settings_path.clone()).unwrap_or_else(|e| {
+            eprintln!("Failed to load settings, using defaults: {}", e);
+            SettingsStore::with_defaults(settings_path)
+        });
+        let settings = settings_store.settings().clone();
+
+        // データベースの初期化。壊れている場合はバックアップから復元する
+        fs::create_dir_all(local_data_dir)
+            .map_err(|e| format!("Failed to create {}: {}", local_data_dir.display(), e))?;
+        let db_path = local_data_dir.join(DATABASE_FILE);
+        let (mut database_manager, recovery) =
+            backup::open_with_recovery(db_path, chrono::Local::now().naive_local())
+                .map_err(|e| format!("Failed to create DatabaseManager: {}", e))?;
+        if let Some(recovery) = &recovery {
+            eprintln!("{}", recovery);
+        }
+
+        database_manager
+            .initialize()
+            .map_err(|e| format!("Failed to initialize database: {}", e))?;
+        database_manager.set_backup_keep(settings.backup_keep as usize);
+
+        let database_manager = Arc::new(Mutex::new(database_manager));
+
+        // 正常なバックアップがなかった場合、この起動中はスキップされる
+        if let Err(e) = autosave::backup_if_due(&database_manager) {
+            eprintln!("Failed to backup database: {}", e);
+        }
+
+        let event_hub = EventHub::new();
+        let websocket_sink = Arc::new(WebSocketSink::new());
+        for sink in sinks {
+            event_hub.add_sink(sink);
+        }
+        event_hub.add_sink(websocket_sink.clone());
+
+        let gamepad_manager = GamepadManager::new(event_hub.clone())
+            .map_err(|e| format!("Failed to create GamepadManager: {}", e))?;
+        gamepad_manager
+            .set_day_rollover_hour(settings.day_rollover_hour)
+            .and_then(|_| gamepad_manager.set_session_idle_gap(settings.session_idle_gap()))
+            .and_then(|_| gamepad_manager.set_scratch_timeout(settings.scratch_timeout()))
+            .and_then(|_| gamepad_manager.set_key_layout(settings.key_layout))
+            .map_err(|e| format!("Failed to apply settings: {}", e))?;
+        if settings.record_inputs {
+            let dir = recorder::recordings_dir(&local_data_dir.join(DATABASE_FILE));
+            if let Err(e) = gamepad_manager.set_recording(Some(dir)) {
+                eprintln!("Failed to start recording: {}", e);
+            }
+        }
+        if let Err(e) = gamepad_manager.set_osc(settings.osc_config()) {
+            eprintln!("Failed to start OSC output: {}", e);
+        }
+
+        gamepad_manager
+            .start_event_loop()
+            .map_err(|e| format!("Failed to start event loop: {}", e))?;
+
+        // 目標達成の判定に使う値を読み込む
+        {
+            let db = database_manager
+                .lock()
+                .map_err(|e| format!("Failed to lock database: {}", e))?;
+            let goals = db
+                .get_goals()
+                .map_err(|e| format!("Failed to get goals: {}", e))?;
+            gamepad_manager
+                .set_daily_goal(goals.daily)
+                .map_err(|e| format!("Failed to set daily goal: {}", e))?;
+            autosave::sync_saved_today(&db, &gamepad_manager)
+                .map_err(|e| format!("Failed to sync saved count: {}", e))?;
+        }
+
+        let gamepad_manager = Arc::new(Mutex::new(gamepad_manager));
+
+        let mut servers = ServerManager::new(
+            websocket_sink,
+            Arc::clone(&database_manager),
+            Arc::clone(&gamepad_manager),
+            &settings,
+        );
+        if let Err(e) = servers.configure(&settings) {
+            eprintln!("Failed to start server: {}", e);
+        }
+
+        // 定期保存の開始
+        let autosave = Autosave::start(
+            Arc::clone(&database_manager),
+            Arc::clone(&gamepad_manager),
+            settings.autosave_interval(),
+        );
+
+        Ok(Services {
+            settings: settings_store,
+            event_hub,
+            database: database_manager,
+            gamepad: gamepad_manager,
+            servers,
+            autosave,
+            recovery,
+        })
+    }
+}
+
+// 自動保存と入力スレッドを止めてから最後の保存をし、サーバーを停止する
+pub fn shutdown(
+    autosave: &Mutex<Autosave>,
+    gamepad: &Arc<Mutex<GamepadManager>>,
+    db: &Arc<Mutex<DatabaseManager>>,
+    servers: &Mutex<ServerManager>,
+) {
+    if let Ok(mut autosave) = autosave.lock() {
+        autosave.stop();
+    }
+
+    if let Ok(gamepad) = gamepad.lock() {
+        // 入力スレッドを止めてから保存する
+        if let Err(e) = gamepad.shutdown() {
+            eprintln!("Failed to stop input threads: {}", e);
+        }
+        if let Err(e) = gamepad.end_session() {
+            eprintln!("Failed to end session: {}", e);
+        }
+    }
+    match autosave::save_statistics(db, gamepad) {
+        Ok(_) => println!("Statistics saved successfully"),
+        Err(e) => eprintln!("Failed to save statistics: {}", e),
+    }
+
+    if let Ok(mut servers) = servers.lock() {
+        servers.stop();
+    }
+}
+
+// ウィンドウなしで起動する場合の設定ファイルのパス。Tauriのapp_config_dirと同じ場所
+pub fn default_settings_path() -> Option<PathBuf> {
+    Some(
+        dirs::config_dir()?
+            .join(APP_IDENTIFIER)
+            .join(config::SETTINGS_FILE),
+    )
+}
+
+// ウィンドウなしで起動する場合のデータディレクトリ。Tauriのlocal_data_dirと同じ場所
+pub fn default_local_data_dir() -> Option<PathBuf> {
+    dirs::data_local_dir()
+}
@@ -0,0 +1,360 @@
+use super::error::SettingsError;
+use crate::autosave;
+use crate::database::backup;
+use crate::gamepad::layout::KeyLayoutId;
+use crate::gamepad::osc::{self, OscConfig};
+use crate::gamepad::{scratch, session};
+use crate::server::{overlay, websocket};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use ts_rs::TS;
+
+pub const SETTINGS_VERSION: u32 = 1;
+pub const SETTINGS_FILE: &str = "settings.json";
+// 設定が変更されたときにフロントエンドへ送るイベント名
+pub const SETTINGS_CHANGED: &str = "settings-changed";
+
+const DAY_ROLLOVER_HOUR_RANGE: RangeInclusive<u32> = 0..=23;
+const SESSION_IDLE_GAP_SECS_RANGE: RangeInclusive<u32> = 30..=3600;
+const SCRATCH_TIMEOUT_MS_RANGE: RangeInclusive<u32> = 20..=1000;
+const AUTOSAVE_INTERVAL_SECS_RANGE: RangeInclusive<u32> = 10..=3600;
+const BACKUP_KEEP_RANGE: RangeInclusive<u32> = 1..=100;
+const PORT_RANGE: RangeInclusive<u32> = 1024..=65535;
+// 送信先のポートは他のアプリが決めるため制限しない
+const OSC_PORT_RANGE: RangeInclusive<u32> = 1..=65535;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub enum PlaySide {
+    #[serde(rename = "1p")]
+    OneP,
+    #[serde(rename = "2p")]
+    TwoP,
+}
+
+// アプリの設定。ファイルにない項目は既定値になる
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase", default)]
+#[ts(export)]
+pub struct Settings {
+    pub version: u32,
+    pub play_side: PlaySide,
+    // 表示するキーとイベントのレーン番号の対応
+    pub key_layout: KeyLayoutId,
+    // この時刻より前のプレイは前日分として記録する
+    pub day_rollover_hour: u32,
+    // 入力がこの秒数途切れたらセッションを区切る
+    pub session_idle_gap_secs: u32,
+    // スクラッチの入力がこのミリ秒途切れたらニュートラルに戻す
+    pub scratch_timeout_ms: u32,
+    pub autosave_interval_secs: u32,
+    // 保持する自動バックアップの数
+    pub backup_keep: u32,
+    // OBSのブラウザソースなどへ入力を配信するWebSocketサーバー（localhostのみ）
+    pub websocket_enabled: bool,
+    pub websocket_port: u16,
+    // オーバーレイと統計のREST APIは同じHTTPサーバー（localhostのみ）で配信する
+    pub overlay_enabled: bool,
+    pub api_enabled: bool,
+    pub http_port: u16,
+    // 照明やVJソフトへ入力を送るOSC（UDP）
+    pub osc_enabled: bool,
+    pub osc_host: String,
+    pub osc_port: u16,
+    // {lane}はレーン番号に置き換える
+    pub osc_lane_address: String,
+    pub osc_scratch_address: String,
+    pub osc_nps_address: String,
+    // すべての入力をデータベースと同じディレクトリのrecordingsに記録する
+    pub record_inputs: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            version: SETTINGS_VERSION,
+            play_side: PlaySide::OneP,
+            key_layout: KeyLayoutId::IidxSp,
+            day_rollover_hour: 0,
+            session_idle_gap_secs: session::DEFAULT_IDLE_GAP.as_secs() as u32,
+            scratch_timeout_ms: scratch::DEFAULT_TIMEOUT.as_millis() as u32,
+            autosave_interval_secs: autosave::DEFAULT_INTERVAL.as_secs() as u32,
+            backup_keep: backup::DEFAULT_KEEP as u32,
+            websocket_enabled: false,
+            websocket_port: websocket::DEFAULT_PORT,
+            overlay_enabled: false,
+            api_enabled: false,
+            http_port: overlay::DEFAULT_PORT,
+            osc_enabled: false,
+            osc_host: osc::DEFAULT_HOST.to_string(),
+            osc_port: osc::DEFAULT_PORT,
+            osc_lane_address: osc::DEFAULT_LANE_ADDRESS.to_string(),
+            osc_scratch_address: osc::DEFAULT_SCRATCH_ADDRESS.to_string(),
+            osc_nps_address: osc::DEFAULT_NPS_ADDRESS.to_string(),
+            record_inputs: false,
+        }
+    }
+}
+
+fn check_range(name: &str, value: u32, range: RangeInclusive<u32>) -> Result<(), SettingsError> {
+    if range.contains(&value) {
+        return Ok(());
+    }
+    Err(SettingsError::InvalidValue(format!(
+        "{} must be between {} and {}, got {}",
+        name,
+        range.start(),
+        range.end(),
+        value
+    )))
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if self.version > SETTINGS_VERSION {
+            return Err(SettingsError::InvalidValue(format!(
+                "Settings version {} is newer than supported version {}",
+                self.version, SETTINGS_VERSION
+            )));
+        }
+        check_range(
+            "dayRolloverHour",
+            self.day_rollover_hour,
+            DAY_ROLLOVER_HOUR_RANGE,
+        )?;
+        check_range(
+            "sessionIdleGapSecs",
+            self.session_idle_gap_secs,
+            SESSION_IDLE_GAP_SECS_RANGE,
+        )?;
+        check_range(
+            "scratchTimeoutMs",
+            self.scratch_timeout_ms,
+            SCRATCH_TIMEOUT_MS_RANGE,
+        )?;
+        check_range(
+            "autosaveIntervalSecs",
+            self.autosave_interval_secs,
+            AUTOSAVE_INTERVAL_SECS_RANGE,
+        )?;
+        check_range("backupKeep", self.backup_keep, BACKUP_KEEP_RANGE)?;
+        check_range("websocketPort", self.websocket_port as u32, PORT_RANGE)?;
+        check_range("httpPort", self.http_port as u32, PORT_RANGE)?;
+        if self.websocket_port == self.http_port {
+            return Err(SettingsError::InvalidValue(
+                "websocketPort and httpPort must be different".to_string(),
+            ));
+        }
+
+        if self.osc_host.trim().is_empty() {
+            return Err(SettingsError::InvalidValue(
+                "oscHost must not be empty".to_string(),
+            ));
+        }
+        check_range("oscPort", self.osc_port as u32, OSC_PORT_RANGE)?;
+        for (name, address) in [
+            ("oscLaneAddress", &self.osc_lane_address),
+            ("oscScratchAddress", &self.osc_scratch_address),
+            ("oscNpsAddress", &self.osc_nps_address),
+        ] {
+            if !osc::is_valid_address(address) {
+                return Err(SettingsError::InvalidValue(format!(
+                    "{} must start with / and must not contain spaces or # * , ? [ ], got {}",
+                    name, address
+                )));
+            }
+        }
+        if !self.osc_lane_address.contains(osc::LANE_PLACEHOLDER) {
+            return Err(SettingsError::InvalidValue(format!(
+                "oscLaneAddress must contain {}",
+                osc::LANE_PLACEHOLDER
+            )));
+        }
+        Ok(())
+    }
+
+    // ファイルがない場合は既定値を返す
+    pub fn load(path: &Path) -> Result<Settings, SettingsError> {
+        if !path.exists() {
+            return Ok(Settings::default());
+        }
+
+        let content = fs::read_to_string(path).map_err(|e| {
+            SettingsError::FileError(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        let mut settings: Settings = serde_json::from_str(&content).map_err(|e| {
+            SettingsError::ParseError(format!("Failed to parse {}: {}", path.display(), e))
+        })?;
+        settings.validate()?;
+
+        // 古いバージョンの設定は、足りない項目が既定値で補われた状態で現在のバージョンになる
+        settings.version = SETTINGS_VERSION;
+        Ok(settings)
+    }
+
+    // 書き込み途中で終了しても壊れないよう、一時ファイルに書いてから置き換える
+    pub fn save(&self, path: &Path) -> Result<(), SettingsError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                SettingsError::FileError(format!("Failed to create {}: {}", parent.display(), e))
+            })?;
+        }
+
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| SettingsError::ParseError(format!("Failed to serialize: {}", e)))?;
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, content).map_err(|e| {
+            SettingsError::FileError(format!("Failed to write {}: {}", temp_path.display(), e))
+        })?;
+        fs::rename(&temp_path, path).map_err(|e| {
+            SettingsError::FileError(format!("Failed to replace {}: {}", path.display(), e))
+        })
+    }
+
+    pub fn session_idle_gap(&self) -> Duration {
+        Duration::from_secs(self.session_idle_gap_secs as u64)
+    }
+
+    pub fn scratch_timeout(&self) -> Duration {
+        Duration::from_millis(self.scratch_timeout_ms as u64)
+    }
+
+    pub fn autosave_interval(&self) -> Duration {
+        Duration::from_secs(self.autosave_interval_secs as u64)
+    }
+
+    // OSCが無効ならNone
+    pub fn osc_config(&self) -> Option<OscConfig> {
+        self.osc_enabled.then(|| OscConfig {
+            host: self.osc_host.trim().to_string(),
+            port: self.osc_port,
+            lane_address: self.osc_lane_address.clone(),
+            scratch_address: self.osc_scratch_address.clone(),
+            nps_address: self.osc_nps_address.clone(),
+        })
+    }
+}
+
+// 設定ファイルと、現在適用されている設定
+#[derive(Debug)]
+pub struct SettingsStore {
+    path: PathBuf,
+    settings: Settings,
+}
+
+impl SettingsStore {
+    pub fn load(path: PathBuf) -> Result<SettingsStore, SettingsError> {
+        let settings = Settings::load(&path)?;
+        Ok(SettingsStore { path, settings })
+    }
+
+    // 設定ファイルが読めない場合に使う。ファイルは次に更新するまで変更しない
+    pub fn with_defaults(path: PathBuf) -> SettingsStore {
+        SettingsStore {
+            path,
+            settings: Settings::default(),
+        }
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    // 検証してから保存し、保存できた場合のみ置き換える
+    pub fn update(&mut self, mut settings: Settings) -> Result<&Settings, SettingsError> {
+        settings.version = SETTINGS_VERSION;
+        settings.validate()?;
+        settings.save(&self.path)?;
+        self.settings = settings;
+        Ok(&self.settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_test_dir(test_name: &str) -> PathBuf {
+        let dir = PathBuf::from(format!("test_settings_{}", test_name));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        dir
+    }
+
+    fn cleanup_test_dir(dir: PathBuf) {
+        if dir.exists() {
+            fs::remove_dir_all(dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_missing_file_uses_defaults() {
+        let dir = setup_test_dir("missing");
+
+        let settings = Settings::load(&dir.join(SETTINGS_FILE)).unwrap();
+        assert_eq!(settings, Settings::default());
+        assert!(!dir.exists());
+
+        cleanup_test_dir(dir);
+    }
+
+    #[test]
+    fn test_update_persists() {
+        let dir = setup_test_dir("update");
+        let path = dir.join(SETTINGS_FILE);
+
+        let mut store = SettingsStore::load(path.clone()).unwrap();
+        let mut settings = store.settings().clone();
+        settings.play_side = PlaySide::TwoP;
+        settings.day_rollover_hour = 5;
+        store.update(settings.clone()).unwrap();
+
+        let reloaded = SettingsStore::load(path).unwrap();
+        assert_eq!(reloaded.settings(), &settings);
+
+        cleanup_test_dir(dir);
+    }
+
+    #[test]
+    fn test_invalid_update_is_rejected() {
+        let dir = setup_test_dir("invalid");
+        let path = dir.join(SETTINGS_FILE);
+
+        let mut store = SettingsStore::load(path.clone()).unwrap();
+        let mut settings = store.settings().clone();
+        settings.day_rollover_hour = 24;
+        assert!(matches!(
+            store.update(settings),
+            Err(SettingsError::InvalidValue(_))
+        ));
+
+        let mut settings = store.settings().clone();
+        settings.osc_lane_address = "/keydisplay/lane".to_string();
+        assert!(store.update(settings).is_err());
+        assert_eq!(store.settings(), &Settings::default());
+        assert!(!path.exists());
+
+        cleanup_test_dir(dir);
+    }
+
+    #[test]
+    fn test_partial_file_is_filled_with_defaults() {
+        let dir = setup_test_dir("partial");
+        let path = dir.join(SETTINGS_FILE);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, r#"{ "version": 1, "playSide": "2p" }"#).unwrap();
+
+        let settings = Settings::load(&path).unwrap();
+        assert_eq!(settings.play_side, PlaySide::TwoP);
+        assert_eq!(settings.backup_keep, Settings::default().backup_keep);
+
+        fs::write(&path, r#"{ "version": 2 }"#).unwrap();
+        assert!(Settings::load(&path).is_err());
+
+        cleanup_test_dir(dir);
+    }
+}
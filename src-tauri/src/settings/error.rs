@@ -0,0 +1,20 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum SettingsError {
+    FileError(String),
+    ParseError(String),
+    InvalidValue(String),
+}
+
+impl std::error::Error for SettingsError {}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::FileError(msg) => write!(f, "File error: {}", msg),
+            SettingsError::ParseError(msg) => write!(f, "Parse error: {}", msg),
+            SettingsError::InvalidValue(msg) => write!(f, "Invalid value: {}", msg),
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod config;
+pub mod error;
+
+pub use config::{PlaySide, Settings, SettingsStore};
+pub use error::SettingsError;
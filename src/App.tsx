@@ -4,84 +4,59 @@ import "./App.css";
 import { listen } from '@tauri-apps/api/event';
 
 import Stats from "./components/Stats";
-
-interface GamepadEvent {
-  type: string;
-  button?: number;
-  pressed?: boolean;
-
-  axis?: number;
-  direction?: string;
-
-  count: number;
-  averageReleaseTime?: number;
-}
+import Settings from "./components/Settings";
+import { getSettings, onSettingsChanged, updateSettings } from "./api/settings";
+import type { GamepadInputEvent } from "./bindings/GamepadInputEvent";
+import type { GoalReachedEvent } from "./bindings/GoalReachedEvent";
+import type { Direction } from "./bindings/Direction";
+import { listKeyLayouts, type KeyLayout, type KeyLayoutId, type Lane } from "./api/layout";
+import { getDatabaseRecovery, type Recovery } from "./api/database";
 
 type Tab = 'mania' | 'stats' | 'setting';
 
-{
-  /*
-  const ScratchComponent = () => {
-    return (
-      <>
-        <div className="scratch-container">
-          <div className={`scratch scratch-top ${isTopRotating ? 'rotating' : ''}`}></div>
-          <div className={`scratch scratch-bottom ${isBottomRotating ? 'rotating' : ''}`}></div>
-        </div>
-      </>
-    );
-  }
-  */
+const ScratchComponent = ({ direction, playerTwo }: { direction: Direction, playerTwo: boolean }) => {
+  return (
+    <div className={`scratch-container ${playerTwo ? 'player-two' : ''}`}>
+      <div className={`scratch scratch-top ${direction === 'left' ? 'rotating' : ''}`}></div>
+      <div className={`scratch scratch-bottom ${direction === 'right' ? 'rotating' : ''}`}></div>
+    </div>
+  );
 }
 
-const KeysComponent = ({ pressed }: { pressed: Array<boolean> }) => {
+// 鍵盤はレイアウトの位置（鍵盤の半分が1）でグリッドに並べる
+const KeysComponent = ({ lanes, pressed }: { lanes: Lane[], pressed: Array<boolean> }) => {
+  const left = Math.min(...lanes.map(lane => lane.x));
 
   return (
     <div className="keys-container">
-      <div className="row">
-        {
-          pressed.map((isPressed, index) => {
-            const is_blue_key = (index: number) => {
-              if (index === 1 || index === 3 || index === 5) {
-                return <div key={index} className={isPressed ? "key-pressed-blue" : "key"}></div>;
-              } else {
-                return;
-              }
-            }
-
-            return (is_blue_key(index))
-          })
-        }
-      </div>
-
-      <div className="row">
-        {
-          pressed.map((isPressed, index) => {
-            const is_blue_key = (index: number) => {
-              if (index === 0 || index === 2 || index === 4 || index === 6) {
-                return <div key={index} className={isPressed ? "key-pressed-white" : "key"}></div>;
-              } else {
-                return;
-              }
-            }
-
-            return (is_blue_key(index))
-          })
-        }
-      </div>
+      {
+        lanes.map(lane => (
+          <div
+            key={lane.index}
+            className={`key key-${lane.color} ${pressed[lane.index] ? 'pressed' : ''}`}
+            style={{
+              gridColumn: `${lane.x - left + 1} / span ${lane.width}`,
+              gridRow: `${lane.y + 1} / span ${lane.height}`,
+            }}
+          ></div>
+        ))
+      }
     </div>
-
   );
 }
 
 function App() {
   // コントローラーに関するState
-  const [pressed, setPressed] = useState(Array(7).fill(false));
+  // レーンの添字ごとの状態
+  const [pressed, setPressed] = useState<boolean[]>([]);
+  const [scratchDirections, setScratchDirections] = useState<Record<number, Direction>>({});
   const [averageReleaseTime, setAverageReleaseTime] = useState(0);
-  const [isTopRotating, setIsTopRotating] = useState(false);
-  const [isBottomRotating, setIsBottomRotating] = useState(false);
+  const [layouts, setLayouts] = useState<KeyLayout[]>([]);
+  const [keyLayoutId, setKeyLayoutId] = useState<KeyLayoutId>('iidxSp');
   const [count, setCount] = useState(0);
   const [isPlayerOneSide, setIsPlayerOneSide] = useState(true);
+  const [isGoalReached, setIsGoalReached] = useState(false);
+  const [recovery, setRecovery] = useState<Recovery | null>(null);
 
   // UIに関するState 
   // build用
@@ -92,73 +67,120 @@ function App() {
   // UseEfectを一度だけ実行する
   const once = useRef(false);
 
+  // レイアウトにないボタンはlaneがないので表示しない
+  function setLanePressed(lane: number | undefined, isPressed: boolean) {
+    if (lane === undefined) return;
+    setPressed(prevPressed => {
+      const newPressed = [...prevPressed];
+      newPressed[lane] = isPressed;
+      return newPressed;
+    });
+  }
+
   async function setupGamepadListener() {
-    const unlisten = await listen<GamepadEvent>('gamepad-input', event => {
+    const unlisten = await listen<GamepadInputEvent>('gamepad-input', event => {
       // ボタンの処理
-      if (event.payload.type == "button" && event.payload.button !== undefined) {
-        const buttonIndex = event.payload.button;
+      if (event.payload.type == "button") {
+        const lane = event.payload.lane;
         const isPressed = event.payload.pressed;
         // 押したとき
         if (isPressed) {
-          setPressed(prevPressed => {
-            const newPressed = [...prevPressed];
-            newPressed[buttonIndex] = true;
-            return newPressed;
-          });
+          setLanePressed(lane, true);
           setCount(event.payload.count);
         }
         // リリース
         else if (event.payload.averageReleaseTime !== undefined) {
-          setPressed(prevPressed => {
-            const newPressed = [...prevPressed];
-            newPressed[buttonIndex] = false;
-            return newPressed;
-          });
+          setLanePressed(lane, false);
           setAverageReleaseTime(event.payload.averageReleaseTime);
           setCount(event.payload.count);
         }
       }
       // スクラッチの処理
-      if (event.payload.type == "scratch" && event.payload.axis !== undefined && event.payload.direction !== undefined) {
+      if (event.payload.type == "scratch") {
 
         setCount(event.payload.count);
 
+        const lane = event.payload.lane;
         const direction = event.payload.direction;
-        if (direction == "left") {
-          setIsTopRotating(true);
-          setIsBottomRotating(false);
-        } else if (direction == "right") {
-          setIsTopRotating(false);
-          setIsBottomRotating(true);
-        } else if (direction == "neutral") {
-          setIsTopRotating(false);
-          setIsBottomRotating(false);
+        if (lane !== undefined) {
+          setScratchDirections(prev => ({ ...prev, [lane]: direction }));
         }
       }
     })
     return unlisten;
   }
 
+  // 1P/2Pは設定として保存する
+  async function togglePlayerSide() {
+    const settings = await getSettings();
+    await updateSettings({ ...settings, playSide: settings.playSide === '1p' ? '2p' : '1p' });
+  }
+
+  async function setupSettingsListener() {
+    const [settings, keyLayouts] = await Promise.all([getSettings(), listKeyLayouts()]);
+    setLayouts(keyLayouts);
+    setIsPlayerOneSide(settings.playSide === '1p');
+    setKeyLayoutId(settings.keyLayout);
+    return await onSettingsChanged(changed => {
+      setIsPlayerOneSide(changed.playSide === '1p');
+      setKeyLayoutId(changed.keyLayout);
+    });
+  }
+
+  async function setupGoalListener() {
+    return await listen<GoalReachedEvent>('goal-reached', () => {
+      setIsGoalReached(true);
+    });
+  }
+
+  // レイアウトが変わったらレーンの状態を捨てる
+  useEffect(() => {
+    setPressed([]);
+    setScratchDirections({});
+  }, [keyLayoutId]);
+
   // Gamepad listenerの起動
   useEffect(() => {
     if (once.current) return;
     once.current = true;
 
     let unlistenFn: (() => void) | null = null;
+    let unlistenGoalFn: (() => void) | null = null;
+    let unlistenSettingsFn: (() => void) | null = null;
 
     setupGamepadListener().then(unlisten => {
       unlistenFn = unlisten;
     });
+    setupGoalListener().then(unlisten => {
+      unlistenGoalFn = unlisten;
+    });
+    setupSettingsListener().then(unlisten => {
+      unlistenSettingsFn = unlisten;
+    });
+    getDatabaseRecovery().then(setRecovery).catch(() => {});
 
     // クリーンアップ関数
     return () => {
       if (unlistenFn) {
         unlistenFn();
       }
+      if (unlistenGoalFn) {
+        unlistenGoalFn();
+      }
+      if (unlistenSettingsFn) {
+        unlistenSettingsFn();
+      }
     }
   }, []);
 
 
+  const lanes = layouts.find(layout => layout.id === keyLayoutId)?.lanes ?? [];
+  const keyLanes = lanes.filter(lane => lane.kind === 'key');
+  const scratchLanes = lanes.filter(lane => lane.kind === 'scratch');
+  // 皿が1つなら1P/2Pで左右を決め、DPのように2つあればレイアウトの位置に従う
+  const keysLeft = Math.min(...keyLanes.map(lane => lane.x));
+  const isLeftScratch = (lane: Lane) => scratchLanes.length === 1 ? isPlayerOneSide : lane.x < keysLeft;
+
   return (
     <>
       <div className="container">
@@ -169,26 +191,18 @@ function App() {
               <>
                 <div className="mania-layout">
                   {
-                    isPlayerOneSide ? (
-                      <>
-                        <div className="scratch-container">
-                          <div className={`scratch scratch-top ${isTopRotating ? 'rotating' : ''}`}></div>
-                          <div className={`scratch scratch-bottom ${isBottomRotating ? 'rotating' : ''}`}></div>
-                        </div>
-                        <KeysComponent pressed={pressed} />
-                      </>
-                    ) : (
-                      <>
-                        <KeysComponent pressed={pressed} />
-                        <div className="scratch-container player-two">
-                          <div className={`scratch scratch-top ${isTopRotating ? 'rotating' : ''}`}></div>
-                          <div className={`scratch scratch-bottom ${isBottomRotating ? 'rotating' : ''}`}></div>
-                        </div>
-                      </>
-                    )
+                    scratchLanes.filter(isLeftScratch).map(lane => (
+                      <ScratchComponent key={lane.index} direction={scratchDirections[lane.index] ?? 'neutral'} playerTwo={false} />
+                    ))
+                  }
+                  <KeysComponent lanes={keyLanes} pressed={pressed} />
+                  {
+                    scratchLanes.filter(lane => !isLeftScratch(lane)).map(lane => (
+                      <ScratchComponent key={lane.index} direction={scratchDirections[lane.index] ?? 'neutral'} playerTwo={true} />
+                    ))
                   }
 
-                  <div className="change-button-container" onClick={() => { setIsPlayerOneSide(!isPlayerOneSide) }}>
+                  <div className="change-button-container" onClick={() => { togglePlayerSide() }}>
                     <img src="/change_icon.png" className="change-icon">
                     </img>
                   </div>
@@ -201,6 +215,27 @@ function App() {
                   <p>
                     Release : {averageReleaseTime}
                   </p>
+                  {
+                    isGoalReached && (
+                      <p>
+                        Goal reached!
+                      </p>
+                    )
+                  }
+                  {
+                    recovery?.kind === 'restored' && (
+                      <p>
+                        Database was corrupt and restored from {recovery.backupPath}
+                      </p>
+                    )
+                  }
+                  {
+                    recovery?.kind === 'noBackup' && (
+                      <p>
+                        Database was corrupt and no backup was found. Started with empty statistics; the old file was moved to {recovery.corruptPath}
+                      </p>
+                    )
+                  }
                 </div>
               </>
             )
@@ -214,7 +249,9 @@ function App() {
           }
           {
             activeTab === 'setting' && (
-              <></>
+              <>
+                <Settings />
+              </>
             )
           }
 
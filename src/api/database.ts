@@ -1,4 +1,24 @@
 import { invoke } from '@tauri-apps/api/core'
+import type { BackupInfo } from '../bindings/BackupInfo'
+import type { Correction } from '../bindings/Correction'
+import type { DayChange } from '../bindings/DayChange'
+import type { DeletionSummary } from '../bindings/DeletionSummary'
+import type { ExportFormat } from '../bindings/ExportFormat'
+import type { ExportSummary } from '../bindings/ExportSummary'
+import type { GoalKind } from '../bindings/GoalKind'
+import type { GoalProgress } from '../bindings/GoalProgress'
+import type { Goals } from '../bindings/Goals'
+import type { Grouping } from '../bindings/Grouping'
+import type { ImportPreview } from '../bindings/ImportPreview'
+import type { MergeStrategy } from '../bindings/MergeStrategy'
+import type { PeriodStatistics } from '../bindings/PeriodStatistics'
+import type { RangeSummary } from '../bindings/RangeSummary'
+import type { Recovery } from '../bindings/Recovery'
+import type { SessionRecord } from '../bindings/SessionRecord'
+import type { Streaks } from '../bindings/Streaks'
+
+// 型はsrc-tauriからts-rsで生成される（src/bindings）
+export type { BackupInfo, Correction, DayChange, DeletionSummary, ExportFormat, ExportSummary, GoalKind, GoalProgress, Goals, Grouping, ImportPreview, MergeStrategy, PeriodStatistics, RangeSummary, Recovery, Streaks }
 
 export type StatisticsData = {
   date: string;
@@ -24,6 +44,70 @@ export async function getYearStatistics(year: number): Promise<StatisticsData[]>
   }
 }
 
+export async function getRangeStatistics(startDate: string, endDate: string, groupBy: Grouping): Promise<PeriodStatistics[]> {
+  try {
+    return await invoke<PeriodStatistics[]>('get_range_statistics', { startDate, endDate, groupBy });
+  } catch (error) {
+    console.error(`Failed to get range statistics for ${startDate} - ${endDate}:`, error);
+    throw error;
+  }
+}
+
+export async function getRangeSummary(startDate: string, endDate: string): Promise<RangeSummary> {
+  try {
+    return await invoke<RangeSummary>('get_range_summary', { startDate, endDate });
+  } catch (error) {
+    console.error(`Failed to get range summary for ${startDate} - ${endDate}:`, error);
+    throw error;
+  }
+}
+
+// [曜日(0=日曜日)][時(0-23)]のノーツ数
+export async function getHourlyStatistics(startDate: string, endDate: string): Promise<number[][]> {
+  try {
+    return await invoke<number[][]>('get_hourly_statistics', { startDate, endDate });
+  } catch (error) {
+    console.error(`Failed to get hourly statistics for ${startDate} - ${endDate}:`, error);
+    throw error;
+  }
+}
+
+export async function getGoals(): Promise<Goals> {
+  try {
+    return await invoke<Goals>('get_goals');
+  } catch (error) {
+    console.error('Failed to get goals:', error);
+    throw error;
+  }
+}
+
+export async function setGoal(kind: GoalKind, notesCount: number | null): Promise<void> {
+  try {
+    await invoke('set_goal', { kind, notesCount });
+  } catch (error) {
+    console.error(`Failed to set ${kind} goal:`, error);
+    throw error;
+  }
+}
+
+export async function getStreaks(): Promise<Streaks> {
+  try {
+    return await invoke<Streaks>('get_streaks');
+  } catch (error) {
+    console.error('Failed to get streaks:', error);
+    throw error;
+  }
+}
+
+export async function getGoalProgress(startDate: string, endDate: string, kind: GoalKind): Promise<GoalProgress[]> {
+  try {
+    return await invoke<GoalProgress[]>('get_goal_progress', { startDate, endDate, kind });
+  } catch (error) {
+    console.error(`Failed to get ${kind} goal progress:`, error);
+    throw error;
+  }
+}
+
 export async function updateStatistics(date: string, notesCount: number): Promise<void> {
   try {
     await invoke('update_statistics', { date, notesCount });
@@ -33,6 +117,102 @@ export async function updateStatistics(date: string, notesCount: number): Promis
   }
 }
 
+// confirmがfalseの場合は削除されず、削除対象の概要だけが返る
+export async function deleteStatistics(startDate: string, endDate: string, confirm: boolean): Promise<DeletionSummary> {
+  try {
+    return await invoke<DeletionSummary>('delete_statistics', { startDate, endDate, confirm });
+  } catch (error) {
+    console.error(`Failed to delete statistics from ${startDate} to ${endDate}:`, error);
+    throw error;
+  }
+}
+
+export async function deleteAllStatistics(confirm: boolean): Promise<DeletionSummary> {
+  try {
+    return await invoke<DeletionSummary>('delete_all_statistics', { confirm });
+  } catch (error) {
+    console.error('Failed to delete all statistics:', error);
+    throw error;
+  }
+}
+
+export async function correctStatistics(date: string, notesCount: number, reason: string): Promise<Correction> {
+  try {
+    return await invoke<Correction>('correct_statistics', { date, notesCount, reason });
+  } catch (error) {
+    console.error(`Failed to correct statistics for ${date}:`, error);
+    throw error;
+  }
+}
+
+export async function listCorrections(limit?: number): Promise<Correction[]> {
+  try {
+    return await invoke<Correction[]>('list_corrections', { limit });
+  } catch (error) {
+    console.error('Failed to list corrections:', error);
+    throw error;
+  }
+}
+
+// 出力される形式はREADMEの「エクスポート形式」を参照
+export async function exportStatistics(path: string, format: ExportFormat): Promise<ExportSummary> {
+  try {
+    return await invoke<ExportSummary>('export_statistics', { path, format });
+  } catch (error) {
+    console.error(`Failed to export statistics to ${path}:`, error);
+    throw error;
+  }
+}
+
+// pathは別のstatistics.db、またはエクスポートしたCSV/JSON。dryRunの場合は変更されず、変更内容だけが返る
+export async function importStatistics(path: string, strategy: MergeStrategy, dryRun: boolean): Promise<ImportPreview> {
+  try {
+    return await invoke<ImportPreview>('import_statistics', { path, strategy, dryRun });
+  } catch (error) {
+    console.error(`Failed to import statistics from ${path}:`, error);
+    throw error;
+  }
+}
+
+// 新しい順に返る
+export async function listBackups(): Promise<BackupInfo[]> {
+  try {
+    return await invoke<BackupInfo[]>('list_backups');
+  } catch (error) {
+    console.error('Failed to list backups:', error);
+    throw error;
+  }
+}
+
+// 起動時に壊れたデータベースを退避した場合のみ値が返る
+export async function getDatabaseRecovery(): Promise<Recovery | null> {
+  try {
+    return await invoke<Recovery | null>('get_database_recovery');
+  } catch (error) {
+    console.error('Failed to get database recovery:', error);
+    throw error;
+  }
+}
+
+export async function createBackup(): Promise<string> {
+  try {
+    return await invoke<string>('create_backup');
+  } catch (error) {
+    console.error('Failed to create backup:', error);
+    throw error;
+  }
+}
+
+// 復元前の内容も自動でバックアップされる
+export async function restoreBackup(fileName: string): Promise<void> {
+  try {
+    await invoke('restore_backup', { fileName });
+  } catch (error) {
+    console.error(`Failed to restore backup ${fileName}:`, error);
+    throw error;
+  }
+}
+
 export async function saveCurrentCount(): Promise<void> {
   try {
     await invoke('save_current_count');
@@ -40,4 +220,24 @@ export async function saveCurrentCount(): Promise<void> {
     console.error('Failed to update statistics');
     throw error;
   }
-}
\ No newline at end of file
+}
+
+export type SessionData = SessionRecord;
+
+export async function listSessions(limit?: number, offset?: number): Promise<SessionData[]> {
+  try {
+    return await invoke<SessionData[]>('list_sessions', { limit, offset });
+  } catch (error) {
+    console.error('Failed to list sessions:', error);
+    throw error;
+  }
+}
+
+export async function getSession(id: number): Promise<SessionData | null> {
+  try {
+    return await invoke<SessionData | null>('get_session', { id });
+  } catch (error) {
+    console.error(`Failed to get session ${id}:`, error);
+    throw error;
+  }
+}
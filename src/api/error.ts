@@ -0,0 +1,18 @@
+import type { AppError } from '../bindings/AppError'
+
+// バックエンドのコマンドが返すエラー（src-tauri/src/error.rsのAppError）
+export type { AppError }
+
+export type AppErrorKind = AppError['kind'];
+
+export function isAppError(error: unknown): error is AppError {
+  return typeof error === 'object'
+    && error !== null
+    && typeof (error as AppError).kind === 'string'
+    && typeof (error as AppError).message === 'string';
+}
+
+export function errorMessage(error: unknown): string {
+  if (isAppError(error)) return `${error.kind}: ${error.message}`;
+  return String(error);
+}
@@ -0,0 +1,16 @@
+import { invoke } from '@tauri-apps/api/core'
+import type { KeyLayout } from '../bindings/KeyLayout'
+import type { KeyLayoutId } from '../bindings/KeyLayoutId'
+import type { Lane } from '../bindings/Lane'
+
+export type { KeyLayout, KeyLayoutId, Lane }
+
+// 設定のkeyLayoutで選べるすべてのレイアウト
+export async function listKeyLayouts(): Promise<KeyLayout[]> {
+  try {
+    return await invoke<KeyLayout[]>('list_key_layouts');
+  } catch (error) {
+    console.error('Failed to list key layouts:', error);
+    throw error;
+  }
+}
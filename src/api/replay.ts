@@ -0,0 +1,39 @@
+import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
+import type { RecordingInfo } from '../bindings/RecordingInfo'
+import type { ReplayFinishedEvent } from '../bindings/ReplayFinishedEvent'
+
+export type { RecordingInfo, ReplayFinishedEvent }
+
+// 新しい順に返る
+export async function listRecordings(): Promise<RecordingInfo[]> {
+  try {
+    return await invoke<RecordingInfo[]>('list_recordings');
+  } catch (error) {
+    console.error('Failed to list recordings:', error);
+    throw error;
+  }
+}
+
+// speedは0.1〜16倍。統計は計算し直すだけでデータベースには保存しない
+export async function startReplay(fileName: string, speed: number): Promise<void> {
+  try {
+    await invoke('start_replay', { fileName, speed });
+  } catch (error) {
+    console.error(`Failed to start replay ${fileName}:`, error);
+    throw error;
+  }
+}
+
+export async function stopReplay(): Promise<void> {
+  try {
+    await invoke('stop_replay');
+  } catch (error) {
+    console.error('Failed to stop replay:', error);
+    throw error;
+  }
+}
+
+export async function onReplayFinished(handler: (summary: ReplayFinishedEvent) => void): Promise<() => void> {
+  return await listen<ReplayFinishedEvent>('replay-finished', event => handler(event.payload));
+}
@@ -0,0 +1,29 @@
+import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
+import type { PlaySide } from '../bindings/PlaySide'
+import type { Settings } from '../bindings/Settings'
+
+export type { PlaySide, Settings }
+
+export async function getSettings(): Promise<Settings> {
+  try {
+    return await invoke<Settings>('get_settings');
+  } catch (error) {
+    console.error('Failed to get settings:', error);
+    throw error;
+  }
+}
+
+// 検証に失敗した場合はinvalidInputのAppErrorになり、設定は変更されない
+export async function updateSettings(newSettings: Settings): Promise<Settings> {
+  try {
+    return await invoke<Settings>('update_settings', { newSettings });
+  } catch (error) {
+    console.error('Failed to update settings:', error);
+    throw error;
+  }
+}
+
+export async function onSettingsChanged(handler: (settings: Settings) => void): Promise<() => void> {
+  return await listen<Settings>('settings-changed', event => handler(event.payload));
+}
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type AppError = { "kind": "lock", "message": string } | { "kind": "databaseConnection", "message": string } | { "kind": "databaseInitialize", "message": string } | { "kind": "database", "message": string } | { "kind": "file", "message": string } | { "kind": "gamepad", "message": string } | { "kind": "server", "message": string } | { "kind": "invalidInput", "message": string } | { "kind": "notFound", "message": string } | { "kind": "internal", "message": string };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DailyCount } from "./DailyCount";
+import type { HourlyCount } from "./HourlyCount";
+import type { SessionRecord } from "./SessionRecord";
+
+export type ExportData = { formatVersion: number, exportedAt: string, daily: Array<DailyCount>, hourly: Array<HourlyCount>, sessions: Array<SessionRecord>, };
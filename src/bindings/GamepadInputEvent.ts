@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Direction } from "./Direction";
+
+export type GamepadInputEvent = { "type": "button", button: number, lane?: number, pressed: boolean, count: number, averageReleaseTime?: number, } | { "type": "scratch", lane?: number, axis: number, direction: Direction, count: number, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DayChange } from "./DayChange";
+
+export type ImportPreview = { days: Array<DayChange>, hourlyChanges: number, newSessions: number, applied: boolean, };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { KeyLayoutId } from "./KeyLayoutId";
+import type { Lane } from "./Lane";
+
+export type KeyLayout = { id: KeyLayoutId, name: string, lanes: Array<Lane>, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type KeyLayoutId = "iidxSp" | "iidxDp" | "fiveKey" | "popn9" | "mania4k" | "mania6k" | "mania7k" | "djmax4b" | "djmax5b" | "djmax6b" | "djmax8b";
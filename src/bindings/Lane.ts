@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LaneColor } from "./LaneColor";
+import type { LaneInput } from "./LaneInput";
+import type { LaneKind } from "./LaneKind";
+
+export type Lane = { index: number, kind: LaneKind, color: LaneColor, input: LaneInput, x: number, y: number, width: number, height: number, };
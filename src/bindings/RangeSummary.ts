@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DailyCount } from "./DailyCount";
+
+export type RangeSummary = { total: number, activeDays: number, averagePerActiveDay: number, bestDay: DailyCount | null, };
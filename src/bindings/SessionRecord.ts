@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type SessionRecord = { id: number, startTime: string, endTime: string, notesCount: number, scratchCount: number, peakNps: number, averageReleaseTime: number, releaseCount: number, keyCounts: { [key in number]?: number }, };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { KeyLayoutId } from "./KeyLayoutId";
+import type { PlaySide } from "./PlaySide";
+
+export type Settings = { version: number, playSide: PlaySide, keyLayout: KeyLayoutId, dayRolloverHour: number, sessionIdleGapSecs: number, scratchTimeoutMs: number, autosaveIntervalSecs: number, backupKeep: number, websocketEnabled: boolean, websocketPort: number, overlayEnabled: boolean, apiEnabled: boolean, httpPort: number, oscEnabled: boolean, oscHost: string, oscPort: number, oscLaneAddress: string, oscScratchAddress: string, oscNpsAddress: string, recordInputs: boolean, };
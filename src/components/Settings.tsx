@@ -0,0 +1,180 @@
+import { useEffect, useState } from 'react';
+import { errorMessage } from '../api/error';
+import { KeyLayout, KeyLayoutId, listKeyLayouts } from '../api/layout';
+import { getSettings, onSettingsChanged, PlaySide, Settings as SettingsData, updateSettings } from '../api/settings';
+
+import './Settings.css';
+
+type NumberField = 'dayRolloverHour' | 'sessionIdleGapSecs' | 'scratchTimeoutMs' | 'autosaveIntervalSecs' | 'backupKeep' | 'websocketPort' | 'httpPort' | 'oscPort';
+
+const numberFields: { key: NumberField; label: string; min: number; max: number }[] = [
+  { key: 'dayRolloverHour', label: 'Day rollover hour', min: 0, max: 23 },
+  { key: 'sessionIdleGapSecs', label: 'Session idle gap (s)', min: 30, max: 3600 },
+  { key: 'scratchTimeoutMs', label: 'Scratch timeout (ms)', min: 20, max: 1000 },
+  { key: 'autosaveIntervalSecs', label: 'Autosave interval (s)', min: 10, max: 3600 },
+  { key: 'backupKeep', label: 'Backups to keep', min: 1, max: 100 },
+  { key: 'websocketPort', label: 'WebSocket port', min: 1024, max: 65535 },
+  { key: 'httpPort', label: 'HTTP port (overlay / API)', min: 1024, max: 65535 },
+  { key: 'oscPort', label: 'OSC port', min: 1, max: 65535 },
+];
+
+type TextField = 'oscHost' | 'oscLaneAddress' | 'oscScratchAddress' | 'oscNpsAddress';
+
+const textFields: { key: TextField; label: string }[] = [
+  { key: 'oscHost', label: 'OSC host' },
+  { key: 'oscLaneAddress', label: 'OSC lane address ({lane})' },
+  { key: 'oscScratchAddress', label: 'OSC scratch address' },
+  { key: 'oscNpsAddress', label: 'OSC NPS address' },
+];
+
+export default function Settings() {
+  const [settings, setSettings] = useState<SettingsData | null>(null);
+  const [draft, setDraft] = useState<SettingsData | null>(null);
+  const [error, setError] = useState<string | null>(null);
+  const [layouts, setLayouts] = useState<KeyLayout[]>([]);
+
+  useEffect(() => {
+    listKeyLayouts().then(setLayouts);
+    getSettings().then(loaded => {
+      setSettings(loaded);
+      setDraft(loaded);
+    });
+
+    // 他の画面（1P/2Pの切り替えなど）での変更を反映する
+    const unlisten = onSettingsChanged(changed => {
+      setSettings(changed);
+      setDraft(changed);
+    });
+    return () => {
+      unlisten.then(fn => fn());
+    }
+  }, []);
+
+  if (!settings || !draft) return null;
+
+  const handleSave = async () => {
+    try {
+      await updateSettings(draft);
+      setError(null);
+    } catch (e) {
+      setError(errorMessage(e));
+    }
+  }
+
+  return (
+    <div className="settings-wrapper">
+      <div className="settings-row">
+        <label htmlFor="play-side">Play side</label>
+        <select
+          id="play-side"
+          value={draft.playSide}
+          onChange={(e) => setDraft({ ...draft, playSide: e.target.value as PlaySide })}
+        >
+          <option value="1p">1P</option>
+          <option value="2p">2P</option>
+        </select>
+      </div>
+
+      <div className="settings-row">
+        <label htmlFor="key-layout">Key layout</label>
+        <select
+          id="key-layout"
+          value={draft.keyLayout}
+          onChange={(e) => setDraft({ ...draft, keyLayout: e.target.value as KeyLayoutId })}
+        >
+          {layouts.map(layout => (
+            <option key={layout.id} value={layout.id}>{layout.name}</option>
+          ))}
+        </select>
+      </div>
+
+      {numberFields.map(({ key, label, min, max }) => (
+        <div className="settings-row" key={key}>
+          <label htmlFor={key}>{label}</label>
+          <input
+            id={key}
+            type="number"
+            min={min}
+            max={max}
+            value={draft[key]}
+            onChange={(e) => setDraft({ ...draft, [key]: Number(e.target.value) })}
+          />
+        </div>
+      ))}
+
+      <div className="settings-row">
+        <label htmlFor="websocket-enabled">WebSocket server (for OBS)</label>
+        <input
+          id="websocket-enabled"
+          type="checkbox"
+          checked={draft.websocketEnabled}
+          onChange={(e) => setDraft({ ...draft, websocketEnabled: e.target.checked })}
+        />
+      </div>
+
+      <div className="settings-row">
+        <label htmlFor="overlay-enabled">Overlay server (OBS browser source)</label>
+        <input
+          id="overlay-enabled"
+          type="checkbox"
+          checked={draft.overlayEnabled}
+          onChange={(e) => setDraft({ ...draft, overlayEnabled: e.target.checked })}
+        />
+      </div>
+      {settings.overlayEnabled && (
+        <p className="settings-note">http://127.0.0.1:{settings.httpPort}/overlay</p>
+      )}
+
+      <div className="settings-row">
+        <label htmlFor="api-enabled">Statistics REST API (read-only)</label>
+        <input
+          id="api-enabled"
+          type="checkbox"
+          checked={draft.apiEnabled}
+          onChange={(e) => setDraft({ ...draft, apiEnabled: e.target.checked })}
+        />
+      </div>
+      {settings.apiEnabled && (
+        <p className="settings-note">http://127.0.0.1:{settings.httpPort}/api/today</p>
+      )}
+
+      <div className="settings-row">
+        <label htmlFor="osc-enabled">OSC output (UDP)</label>
+        <input
+          id="osc-enabled"
+          type="checkbox"
+          checked={draft.oscEnabled}
+          onChange={(e) => setDraft({ ...draft, oscEnabled: e.target.checked })}
+        />
+      </div>
+      {draft.oscEnabled && textFields.map(({ key, label }) => (
+        <div className="settings-row" key={key}>
+          <label htmlFor={key}>{label}</label>
+          <input
+            id={key}
+            type="text"
+            value={draft[key]}
+            onChange={(e) => setDraft({ ...draft, [key]: e.target.value })}
+          />
+        </div>
+      ))}
+
+      <div className="settings-row">
+        <label htmlFor="record-inputs">Record inputs</label>
+        <input
+          id="record-inputs"
+          type="checkbox"
+          checked={draft.recordInputs}
+          onChange={(e) => setDraft({ ...draft, recordInputs: e.target.checked })}
+        />
+      </div>
+
+      {error && <p className="settings-error">{error}</p>}
+
+      <div className="settings-actions">
+        <button onClick={handleSave}>Save</button>
+        <button onClick={() => { setDraft(settings); setError(null); }}>Reset</button>
+      </div>
+    </div>
+  );
+}
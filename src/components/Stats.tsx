@@ -1,6 +1,6 @@
 import dayjs from 'dayjs';
 import { useEffect, useState } from 'react';
-import { getYearStatistics, saveCurrentCount, StatisticsData } from '../api/database';
+import { correctStatistics, deleteStatistics, getHourlyStatistics, getRangeSummary, getStreaks, getYearStatistics, RangeSummary, saveCurrentCount, StatisticsData, Streaks } from '../api/database';
 import { IoMdRefresh } from 'react-icons/io';
 import { MdNavigateBefore, MdNavigateNext } from 'react-icons/md';
 
@@ -16,8 +16,15 @@ export default function Stats() {
   const [heatmapData, setHeatmapData] = useState<MonthData[]>([]);
   const [currentYear, setCurrentYear] = useState(dayjs().year());
   const [isUpdating, setIsUpdating] = useState(false);
+  const [hourlyData, setHourlyData] = useState<number[][]>([]);
+  const [summary, setSummary] = useState<RangeSummary | null>(null);
+  const [streaks, setStreaks] = useState<Streaks | null>(null);
+  const [selectedDay, setSelectedDay] = useState<StatisticsData | null>(null);
+  const [correctedCount, setCorrectedCount] = useState('');
+  const [correctionReason, setCorrectionReason] = useState('');
 
   const weekdays = ['', 'Mon', '', 'Wed', '', 'Fri', ''];
+  const hourLabels = [0, 6, 12, 18];
 
   const updateStatistics = async () => {
     setIsUpdating(true);
@@ -25,6 +32,8 @@ export default function Stats() {
       await saveCurrentCount()
       const data = await getYearStatistics(currentYear);
       setStatsData(data);
+      await fetchHourlyStatistics(currentYear);
+      await fetchSummary(currentYear);
     } catch (error) {
       console.error('Failed to upload statistics: ', error);
     } finally {
@@ -32,6 +41,48 @@ export default function Stats() {
     }
   }
 
+  const reloadStatistics = async () => {
+    await fetchYearStatistics(currentYear);
+    await fetchHourlyStatistics(currentYear);
+    await fetchSummary(currentYear);
+  }
+
+  const selectDay = (day: StatisticsData) => {
+    if (!day.date) return;
+    setSelectedDay(day);
+    setCorrectedCount(String(day.count));
+    setCorrectionReason('');
+  }
+
+  const handleCorrect = async () => {
+    if (!selectedDay) return;
+    const notesCount = Number(correctedCount);
+    if (!Number.isInteger(notesCount) || notesCount < 0 || !correctionReason.trim()) return;
+    try {
+      await correctStatistics(selectedDay.date, notesCount, correctionReason);
+      setSelectedDay(null);
+      await reloadStatistics();
+    } catch (error) {
+      console.error('Failed to correct statistics:', error);
+    }
+  }
+
+  const handleDelete = async () => {
+    if (!selectedDay) return;
+    try {
+      // 削除前に対象を確認する
+      const preview = await deleteStatistics(selectedDay.date, selectedDay.date, false);
+      const message = `Delete ${preview.notesCount.toLocaleString()} keys and ${preview.sessions} sessions on ${selectedDay.date}?`;
+      if (!window.confirm(message)) return;
+
+      await deleteStatistics(selectedDay.date, selectedDay.date, true);
+      setSelectedDay(null);
+      await reloadStatistics();
+    } catch (error) {
+      console.error('Failed to delete statistics:', error);
+    }
+  }
+
   const generateHeatmapData = () => {
     const startDate = dayjs(`${currentYear}-01-01`).startOf('week');
     const endDate = dayjs(`${currentYear}-12-31`);
@@ -90,6 +141,25 @@ export default function Stats() {
     }
   }
 
+  const fetchHourlyStatistics = async (year: number) => {
+    try {
+      const data = await getHourlyStatistics(`${year}-01-01`, `${year}-12-31`);
+      setHourlyData(data);
+    } catch (error) {
+      console.error('Failed to fetch hourly statistics:', error);
+    }
+  }
+
+  const fetchSummary = async (year: number) => {
+    try {
+      const data = await getRangeSummary(`${year}-01-01`, `${year}-12-31`);
+      setSummary(data);
+      setStreaks(await getStreaks());
+    } catch (error) {
+      console.error('Failed to fetch summary:', error);
+    }
+  }
+
   const handleYearChange = (direction: 'prev' | 'next') => {
     const newYear = direction === 'prev' ? currentYear - 1 : currentYear + 1;
     setCurrentYear(newYear);
@@ -105,8 +175,17 @@ export default function Stats() {
     return 5;
   };
 
+  const getHourlyLevel = (count: number, max: number): number => {
+    if (count === 0 || max === 0) return 0;
+    return Math.min(5, Math.ceil((count / max) * 5));
+  };
+
+  const hourlyMax = Math.max(0, ...hourlyData.flat());
+
   useEffect(() => {
     fetchYearStatistics(currentYear);
+    fetchHourlyStatistics(currentYear);
+    fetchSummary(currentYear);
   }, [currentYear])
 
   useEffect(() => {
@@ -172,7 +251,8 @@ export default function Stats() {
                           <div
                             key={`${monthIndex}-${weekIndex}-${dayIndex}`}
                             className={`contribution-day ${level === -1 ? 'empty' : `level-${level}`
-                              }`}
+                              } ${day.date && day.date === selectedDay?.date ? 'selected' : ''}`}
+                            onClick={() => selectDay(day)}
                             title={
                               day.date
                                 ? `${day.date}: ${day.count.toLocaleString()} keys`
@@ -203,6 +283,92 @@ export default function Stats() {
           <span className="legend-text">More</span>
         </div>
       </div>
+
+      {/* 選択した日の修正・削除 */}
+      {selectedDay && (
+        <div className="day-editor">
+          <span className="day-editor-date">{selectedDay.date}</span>
+          <input
+            type="number"
+            min={0}
+            value={correctedCount}
+            onChange={(e) => setCorrectedCount(e.target.value)}
+            aria-label="Corrected keys"
+          />
+          <input
+            type="text"
+            value={correctionReason}
+            onChange={(e) => setCorrectionReason(e.target.value)}
+            placeholder="Reason"
+            aria-label="Correction reason"
+          />
+          <button onClick={handleCorrect} disabled={!correctionReason.trim()}>Save</button>
+          <button onClick={handleDelete} className="danger">Delete</button>
+          <button onClick={() => setSelectedDay(null)}>Cancel</button>
+        </div>
+      )}
+
+      {/* 年間サマリー */}
+      {summary && (
+        <div className="summary-container">
+          <div className="summary-item">
+            <span className="summary-label">Total</span>
+            <span className="summary-value">{summary.total.toLocaleString()}</span>
+          </div>
+          <div className="summary-item">
+            <span className="summary-label">Active days</span>
+            <span className="summary-value">{summary.activeDays}</span>
+          </div>
+          <div className="summary-item">
+            <span className="summary-label">Average / day</span>
+            <span className="summary-value">{Math.round(summary.averagePerActiveDay).toLocaleString()}</span>
+          </div>
+          <div className="summary-item">
+            <span className="summary-label">Best day</span>
+            <span className="summary-value">
+              {summary.bestDay ? `${summary.bestDay.date} (${summary.bestDay.notesCount.toLocaleString()})` : '-'}
+            </span>
+          </div>
+          {streaks && (
+            <div className="summary-item">
+              <span className="summary-label">Streak</span>
+              <span className="summary-value">{streaks.current} (best {streaks.longest})</span>
+            </div>
+          )}
+        </div>
+      )}
+
+      {/* 時間帯ヒートマップ */}
+      <div className="hourly-container">
+        <div className="hourly-content">
+          <div className="weekday-labels">
+            {weekdays.map((day, index) => (
+              <div key={index} className="weekday-label">{day}</div>
+            ))}
+          </div>
+
+          <div>
+            <div className="hour-labels">
+              {Array.from({ length: 24 }, (_, hour) => (
+                <div key={hour} className="hour-label">
+                  {hourLabels.includes(hour) ? hour : ''}
+                </div>
+              ))}
+            </div>
+            {hourlyData.map((hours, weekday) => (
+              <div key={weekday} className="hourly-row">
+                {hours.map((count, hour) => (
+                  <div
+                    key={`${weekday}-${hour}`}
+                    className={`contribution-day level-${getHourlyLevel(count, hourlyMax)}`}
+                    title={`${hour}:00 - ${hour}:59: ${count.toLocaleString()} keys`}
+                  />
+                ))}
+              </div>
+            ))}
+          </div>
+        </div>
+      </div>
     </div>
   );
 };
\ No newline at end of file